@@ -2,8 +2,7 @@ use bevy::prelude::*;
 
 use crate::{
     level_manager::LevelManager,
-    player::{Player, PlayerState},
-    util::CardinalDirection,
+    rules::{CardinalDirection, Player, PlayerState},
 };
 
 #[derive(Component)]
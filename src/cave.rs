@@ -1,34 +1,56 @@
+use std::{
+    collections::BTreeSet,
+    f32::consts::{FRAC_PI_2, PI},
+};
+
 use bevy::prelude::*;
 use bevy_kira_audio::{AudioChannel, AudioControl};
 
 use crate::{
     audio::{AudioAssets, SoundChannel},
-    level_manager::LevelManager,
-    player::{Player, PlayerHistory, PlayerHistoryEvent},
-    states::level::DespawnOnTransition,
+    rules::{CardinalDirection, CaveData, CaveLock, GemColour, Map, Puzzle},
+    states::{level::DespawnOnTransition, loading::ModelAssets},
     util::Spin,
 };
 
+/// The colours of the gems picked up, mirrored from the puzzle so that the caves are only
+/// swapped over when it changes
 #[derive(Default, Resource)]
-pub struct HasGem(pub bool);
+pub struct HeldGems(pub BTreeSet<GemColour>);
 
-#[derive(Debug, Reflect)]
-pub struct CaveData {
-    pub first_pos: (u8, u8),
-    pub second_pos: (u8, u8),
-    pub gem_pos: Option<(u8, u8)>,
-}
+/// An open cave mouth, with the lock that had to be opened
+#[derive(Component)]
+pub struct Cave(pub CaveLock);
 
+/// A sealed cave mouth, shown until its lock is opened
 #[derive(Component)]
-pub struct Cave;
+pub struct GemCave(pub CaveLock);
 
 #[derive(Component)]
-pub struct GemCave;
+pub struct Gem(pub GemColour);
 
+/// Colours the gem model below it
 #[derive(Component)]
-pub struct Gem;
+pub struct GemTint(pub GemColour);
 
-pub fn spawn_gem(commands: &mut Commands, x: u8, y: u8, height: f32, scene: Handle<Scene>) {
+pub fn gem_color(colour: GemColour) -> Color {
+    match colour {
+        // the colour of the model itself
+        GemColour::Red => Color::rgb_linear(0.8, 0.0, 0.004),
+        GemColour::Blue => Color::rgb_linear(0.02, 0.1, 0.8),
+        GemColour::Green => Color::rgb_linear(0.02, 0.6, 0.05),
+        GemColour::Yellow => Color::rgb_linear(0.8, 0.6, 0.0),
+    }
+}
+
+pub fn spawn_gem(
+    commands: &mut Commands,
+    x: u8,
+    y: u8,
+    height: f32,
+    colour: GemColour,
+    scene: Handle<Scene>,
+) {
     commands
         .spawn(SceneBundle {
             scene,
@@ -36,66 +58,151 @@ pub fn spawn_gem(commands: &mut Commands, x: u8, y: u8, height: f32, scene: Hand
             ..Default::default()
         })
         .insert(Spin(height))
-        .insert(Gem)
+        .insert(Gem(colour))
+        .insert(GemTint(colour))
         .insert(Name::new("Gem"))
         .insert(DespawnOnTransition);
 }
 
-pub fn swap_cave_visibility(
-    mut caves: Query<&mut Visibility, (With<Cave>, Without<GemCave>)>,
-    mut gem_caves: Query<&mut Visibility, (With<GemCave>, Without<Cave>)>,
-    has_gem: Res<HasGem>,
-) {
-    if has_gem.0 {
-        for mut visibility in caves.iter_mut() {
-            *visibility = Visibility::Visible;
-        }
-        for mut visibility in gem_caves.iter_mut() {
-            *visibility = Visibility::Hidden;
-        }
+/// Turn the cave model so that its mouth is walked into going the way of the entrance
+fn cave_rotation(entrance: CardinalDirection) -> Quat {
+    Quat::from_rotation_y(match entrance {
+        CardinalDirection::North => -FRAC_PI_2,
+        CardinalDirection::East => -PI,
+        CardinalDirection::South => FRAC_PI_2,
+        CardinalDirection::West => 0.0,
+    })
+}
+
+/// Both mouths of a pair of caves, open or sealed depending on its lock before any gems have
+/// been picked up. Sealed mouths that need a gem of a particular colour show it above them.
+pub fn spawn_cave(commands: &mut Commands, map: &Map, cave: &CaveData, model_assets: &ModelAssets) {
+    let (open_visibility, sealed_visibility) = if cave.lock.is_open() {
+        (Visibility::Visible, Visibility::Hidden)
     } else {
-        for mut visibility in caves.iter_mut() {
-            *visibility = Visibility::Hidden;
+        (Visibility::Hidden, Visibility::Visible)
+    };
+    for (x, y) in [cave.first_pos, cave.second_pos] {
+        let transform = Transform::from_xyz(
+            x as f32,
+            map.grid_heights[y as usize][x as usize] as f32,
+            y as f32,
+        )
+        .with_rotation(cave_rotation(cave.entrance));
+        commands
+            .spawn(SceneBundle {
+                scene: model_assets.cave1.clone(),
+                transform,
+                visibility: open_visibility,
+                ..Default::default()
+            })
+            .insert(Cave(cave.lock))
+            .insert(Name::new("Cave"))
+            .insert(DespawnOnTransition);
+        let mut sealed = commands.spawn(SceneBundle {
+            scene: model_assets.cave2.clone(),
+            transform,
+            visibility: sealed_visibility,
+            ..Default::default()
+        });
+        sealed
+            .insert(GemCave(cave.lock))
+            .insert(Name::new("Sealed cave"))
+            .insert(DespawnOnTransition);
+        if let CaveLock::Gem(colour) = cave.lock {
+            sealed.with_children(|parent| {
+                parent
+                    .spawn(SceneBundle {
+                        scene: model_assets.gem.clone(),
+                        // just above the mouth, which is on the side of the model
+                        transform: Transform::from_xyz(-0.45, 1.1, 0.0)
+                            .with_scale(Vec3::splat(0.6)),
+                        ..Default::default()
+                    })
+                    .insert(GemTint(colour));
+            });
         }
-        for mut visibility in gem_caves.iter_mut() {
-            *visibility = Visibility::Visible;
+    }
+}
+
+pub fn swap_cave_visibility(
+    mut caves: Query<(&Cave, &mut Visibility), Without<GemCave>>,
+    mut gem_caves: Query<(&GemCave, &mut Visibility), Without<Cave>>,
+    held_gems: Res<HeldGems>,
+) {
+    let shown = |open: bool| {
+        if open {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
         }
+    };
+    for (cave, mut visibility) in caves.iter_mut() {
+        *visibility = shown(cave.0.opens_with(&held_gems.0));
+    }
+    for (gem_cave, mut visibility) in gem_caves.iter_mut() {
+        *visibility = shown(!gem_cave.0.opens_with(&held_gems.0));
     }
 }
 
-pub fn check_if_at_gem(
+/// Mirror which gems have been picked up, putting any back whose pick up gets undone
+pub fn sync_gem(
     mut commands: Commands,
-    player: Query<&Player>,
-    mut player_history: ResMut<PlayerHistory>,
-    level_manager: Res<LevelManager>,
-    gems: Query<Entity, With<Gem>>,
-    mut has_gem: ResMut<HasGem>,
+    puzzle: Res<Puzzle>,
+    gems: Query<(Entity, &Gem)>,
+    mut held_gems: ResMut<HeldGems>,
+    model_assets: Res<ModelAssets>,
     sound_channel: Res<AudioChannel<SoundChannel>>,
     audio_assets: Res<AudioAssets>,
 ) {
-    let map = &level_manager.get_current_level().map;
-    if let Some(cave_data) = &map.cave_data {
-        if let Some((x, y)) = cave_data.gem_pos {
-            let player = player
-                .get_single()
-                .expect("There should only be one player");
-
-            if player.grid_pos_x == x && player.grid_pos_y == y {
-                for entity in gems.iter() {
-                    // should only be a single iteration
-                    commands.entity(entity).despawn_recursive();
-                    sound_channel.play(audio_assets.pickup.clone());
-                    if let Some(PlayerHistoryEvent::PlayerMove(old_player)) = player_history.0.pop()
-                    {
-                        player_history
-                            .0
-                            .push(PlayerHistoryEvent::PlayerMoveToGem(old_player));
-                    } else {
-                        warn!("Failed to add scale pickup event to history");
-                    }
-                    has_gem.0 = true;
-                }
+    if held_gems.0 != puzzle.state.gems {
+        held_gems.0 = puzzle.state.gems.clone();
+    }
+    let map = &puzzle.map;
+    for gem in &map.gems {
+        let spawned = gems.iter().find(|(_, spawned)| spawned.0 == gem.colour);
+        let held = puzzle.state.gems.contains(&gem.colour);
+        match spawned {
+            Some((entity, _)) if held => {
+                commands.entity(entity).despawn_recursive();
+                sound_channel.play(audio_assets.pickup.clone());
+            }
+            None if !held => {
+                let (x, y) = gem.pos;
+                let height = map.grid_heights[y as usize][x as usize] as f32 + 0.3;
+                spawn_gem(
+                    &mut commands,
+                    x,
+                    y,
+                    height,
+                    gem.colour,
+                    model_assets.gem.clone(),
+                );
             }
+            _ => {}
         }
     }
 }
+
+/// Give gem models the colour of their gem once their scene has been spawned
+pub fn tint_gems(
+    mut commands: Commands,
+    meshes: Query<(Entity, &Handle<StandardMaterial>), Added<Handle<StandardMaterial>>>,
+    parents: Query<&Parent>,
+    tints: Query<&GemTint>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, material) in meshes.iter() {
+        let Some(tint) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| tints.get(ancestor).ok())
+        else {
+            continue;
+        };
+        let Some(mut tinted) = materials.get(material).cloned() else {
+            continue;
+        };
+        tinted.base_color = gem_color(tint.0);
+        commands.entity(entity).insert(materials.add(tinted));
+    }
+}
@@ -0,0 +1,539 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    rules::CardinalDirection,
+    save::{default_store, Stored},
+};
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        // bindings put in place before the plugin, such as the tests' in-memory ones, are kept
+        if !app.world.contains_resource::<Controls>() {
+            app.insert_resource(Controls::load(default_store("controls")));
+        }
+        app.init_resource::<StickPushes>()
+            .init_resource::<PointerPresses>()
+            .add_systems(First, clear_pointer_presses)
+            .add_systems(PreUpdate, read_sticks.after(InputSystem));
+    }
+}
+
+/// The key bindings, kept between sessions
+pub type Controls = Stored<KeyBindings>;
+
+/// Something the player can do with a key or button press
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Control {
+    MoveNorth,
+    MoveEast,
+    MoveSouth,
+    MoveWest,
+    Ladder,
+    Rope,
+    Rune,
+    Potion,
+    Reach,
+    Hint,
+    Undo,
+    Redo,
+    Restart,
+    SkipLevel,
+    Continue,
+    Pause,
+    PlaybackToggle,
+    PlaybackStep,
+}
+
+impl Control {
+    pub const ALL: [Control; 18] = [
+        Control::MoveNorth,
+        Control::MoveEast,
+        Control::MoveSouth,
+        Control::MoveWest,
+        Control::Ladder,
+        Control::Rope,
+        Control::Rune,
+        Control::Potion,
+        Control::Reach,
+        Control::Hint,
+        Control::Undo,
+        Control::Redo,
+        Control::Restart,
+        Control::SkipLevel,
+        Control::Continue,
+        Control::Pause,
+        Control::PlaybackToggle,
+        Control::PlaybackStep,
+    ];
+
+    /// The key of the control's name in the locale files
+    pub fn name_key(&self) -> &'static str {
+        match self {
+            Control::MoveNorth => "control.move_north",
+            Control::MoveEast => "control.move_east",
+            Control::MoveSouth => "control.move_south",
+            Control::MoveWest => "control.move_west",
+            Control::Ladder => "control.ladder",
+            Control::Rope => "control.rope",
+            Control::Rune => "control.rune",
+            Control::Potion => "control.potion",
+            Control::Reach => "control.reach",
+            Control::Hint => "control.hint",
+            Control::Undo => "control.undo",
+            Control::Redo => "control.redo",
+            Control::Restart => "control.restart",
+            Control::SkipLevel => "control.skip_level",
+            Control::Continue => "control.continue",
+            Control::Pause => "control.pause",
+            Control::PlaybackToggle => "control.playback_toggle",
+            Control::PlaybackStep => "control.playback_step",
+        }
+    }
+
+    /// The control that moves the climber that way
+    pub fn movement(direction: CardinalDirection) -> Control {
+        match direction {
+            CardinalDirection::North => Control::MoveNorth,
+            CardinalDirection::East => Control::MoveEast,
+            CardinalDirection::South => Control::MoveSouth,
+            CardinalDirection::West => Control::MoveWest,
+        }
+    }
+
+    /// The gamepad buttons that do the same as the keys. These can't be changed.
+    pub fn gamepad_buttons(&self) -> &'static [GamepadButtonType] {
+        use GamepadButtonType::*;
+
+        match self {
+            Control::MoveNorth => &[DPadUp],
+            Control::MoveEast => &[DPadRight],
+            Control::MoveSouth => &[DPadDown],
+            Control::MoveWest => &[DPadLeft],
+            Control::Ladder => &[LeftTrigger],
+            Control::Rope => &[RightTrigger],
+            Control::Rune => &[LeftTrigger2],
+            Control::Potion => &[RightTrigger2],
+            Control::Reach => &[RightThumb],
+            Control::Hint => &[LeftThumb],
+            Control::Undo => &[West],
+            Control::Redo => &[North],
+            Control::Restart => &[Select],
+            Control::SkipLevel => &[],
+            Control::Continue => &[South],
+            Control::Pause => &[Start],
+            Control::PlaybackToggle | Control::PlaybackStep => &[],
+        }
+    }
+
+    /// The movement a push of the stick in this direction stands for
+    fn from_stick(x: f32, y: f32) -> Option<Self> {
+        if x.abs().max(y.abs()) < STICK_THRESHOLD {
+            None
+        } else if x.abs() > y.abs() {
+            Some(if x > 0.0 {
+                Control::MoveEast
+            } else {
+                Control::MoveWest
+            })
+        } else {
+            Some(if y > 0.0 {
+                Control::MoveNorth
+            } else {
+                Control::MoveSouth
+            })
+        }
+    }
+}
+
+/// How far the stick has to be pushed to count as a move
+const STICK_THRESHOLD: f32 = 0.6;
+
+/// Which way each gamepad's left stick is pushed, so that pushing it acts like a d-pad press
+/// rather than moving every frame it is held
+#[derive(Debug, Default, Resource)]
+pub struct StickPushes {
+    held: HashMap<Gamepad, Control>,
+    just_pushed: Vec<Control>,
+}
+
+pub fn read_sticks(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut stick_pushes: ResMut<StickPushes>,
+) {
+    let stick_pushes = &mut *stick_pushes;
+    stick_pushes.just_pushed.clear();
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or_default()
+        };
+        let pushed = Control::from_stick(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        match pushed {
+            Some(control) => {
+                if stick_pushes.held.insert(gamepad, control) != Some(control) {
+                    stick_pushes.just_pushed.push(control);
+                }
+            }
+            None => {
+                stick_pushes.held.remove(&gamepad);
+            }
+        }
+    }
+}
+
+/// Controls set off this frame with the mouse or a touch, by the on-screen buttons or by walking
+/// a route that was tapped
+#[derive(Debug, Default, Resource)]
+pub struct PointerPresses(pub Vec<Control>);
+
+fn clear_pointer_presses(mut pointer_presses: ResMut<PointerPresses>) {
+    if !pointer_presses.0.is_empty() {
+        pointer_presses.0.clear();
+    }
+}
+
+/// Everything that can set off a control: the bound keys, the gamepad buttons and the stick,
+/// and the mouse or touch screen
+#[derive(SystemParam)]
+pub struct ControlInput<'w> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    gamepad_input: Res<'w, Input<GamepadButton>>,
+    stick_pushes: Res<'w, StickPushes>,
+    pointer_presses: Res<'w, PointerPresses>,
+    controls: Res<'w, Controls>,
+}
+
+impl ControlInput<'_> {
+    pub fn just_pressed(&self, control: Control) -> bool {
+        self.controls.just_pressed(&self.keyboard_input, control)
+            || self.gamepad_just_pressed(control)
+            || self.pointer_presses.0.contains(&control)
+    }
+
+    /// Pressed on any gamepad, leaving out the keyboard
+    pub fn gamepad_just_pressed(&self, control: Control) -> bool {
+        self.stick_pushes.just_pushed.contains(&control)
+            || self.button_just_pressed(control.gamepad_buttons())
+    }
+
+    /// Whether one of the buttons was just pressed on any gamepad
+    pub fn button_just_pressed(&self, button_types: &[GamepadButtonType]) -> bool {
+        self.gamepad_input
+            .get_just_pressed()
+            .any(|button| button_types.contains(&button.button_type))
+    }
+}
+
+/// A key along with the modifiers held down with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyCombo {
+    pub key: KeyCode,
+    #[serde(default)]
+    pub control: bool,
+    #[serde(default)]
+    pub shift: bool,
+}
+
+impl KeyCombo {
+    pub fn key(key: KeyCode) -> Self {
+        Self {
+            key,
+            control: false,
+            shift: false,
+        }
+    }
+
+    pub fn with_control(key: KeyCode) -> Self {
+        Self {
+            control: true,
+            ..Self::key(key)
+        }
+    }
+
+    /// The key as it was just pressed, with whichever modifiers are held
+    pub fn pressed(key: KeyCode, keyboard_input: &Input<KeyCode>) -> Self {
+        Self {
+            key,
+            control: keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]),
+            shift: keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+        }
+    }
+
+    /// Whether it was just pressed. The modifiers have to match exactly, so that Z doesn't undo
+    /// and Ctrl+Shift+Z doesn't do both undo and redo.
+    fn just_pressed(&self, keyboard_input: &Input<KeyCode>) -> bool {
+        keyboard_input.just_pressed(self.key) && Self::pressed(self.key, keyboard_input) == *self
+    }
+
+    /// Whether it is a modifier, which can't be bound on its own
+    pub fn is_modifier(key: KeyCode) -> bool {
+        matches!(
+            key,
+            KeyCode::ControlLeft
+                | KeyCode::ControlRight
+                | KeyCode::ShiftLeft
+                | KeyCode::ShiftRight
+                | KeyCode::AltLeft
+                | KeyCode::AltRight
+                | KeyCode::SuperLeft
+                | KeyCode::SuperRight
+        )
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.control {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        let name = format!("{:?}", self.key);
+        // number keys are called Key1 and so on
+        match name.strip_prefix("Key") {
+            Some(digit) if !digit.is_empty() => write!(f, "{}", digit),
+            _ => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Which keys do what. Every control can have a few bindings; the first is the one shown in game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<Control, Vec<KeyCombo>>",
+    into = "BTreeMap<Control, Vec<KeyCombo>>"
+)]
+pub struct KeyBindings(BTreeMap<Control, Vec<KeyCombo>>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use KeyCode::*;
+
+        let bindings = Control::ALL.into_iter().map(|control| {
+            let combos = match control {
+                Control::MoveNorth => vec![KeyCombo::key(W), KeyCombo::key(Up)],
+                Control::MoveEast => vec![KeyCombo::key(D), KeyCombo::key(Right)],
+                Control::MoveSouth => vec![KeyCombo::key(S), KeyCombo::key(Down)],
+                Control::MoveWest => vec![KeyCombo::key(A), KeyCombo::key(Left)],
+                Control::Ladder => vec![KeyCombo::key(Key1)],
+                Control::Rope => vec![KeyCombo::key(Key2)],
+                Control::Rune => vec![KeyCombo::key(Key3)],
+                Control::Potion => vec![KeyCombo::key(Key4)],
+                Control::Reach => vec![KeyCombo::key(Tab)],
+                Control::Hint => vec![KeyCombo::key(H)],
+                Control::Undo => vec![KeyCombo::with_control(Z)],
+                Control::Redo => vec![
+                    KeyCombo::with_control(Y),
+                    KeyCombo {
+                        shift: true,
+                        ..KeyCombo::with_control(Z)
+                    },
+                ],
+                Control::Restart => vec![KeyCombo::key(R)],
+                Control::SkipLevel => vec![KeyCombo::key(F1)],
+                Control::Continue => vec![KeyCombo::key(Return), KeyCombo::key(Space)],
+                Control::Pause => vec![KeyCombo::key(Escape)],
+                Control::PlaybackToggle => vec![KeyCombo::key(P)],
+                Control::PlaybackStep => vec![KeyCombo::key(Period)],
+            };
+            (control, combos)
+        });
+        Self(bindings.collect())
+    }
+}
+
+/// Saved bindings are laid over the defaults, so that controls added since still have a key
+impl From<BTreeMap<Control, Vec<KeyCombo>>> for KeyBindings {
+    fn from(value: BTreeMap<Control, Vec<KeyCombo>>) -> Self {
+        let mut bindings = Self::default();
+        bindings.0.extend(value);
+        bindings
+    }
+}
+
+impl From<KeyBindings> for BTreeMap<Control, Vec<KeyCombo>> {
+    fn from(value: KeyBindings) -> Self {
+        value.0
+    }
+}
+
+impl KeyBindings {
+    pub fn just_pressed(&self, keyboard_input: &Input<KeyCode>, control: Control) -> bool {
+        self.combos(control)
+            .iter()
+            .any(|combo| combo.just_pressed(keyboard_input))
+    }
+
+    pub fn combos(&self, control: Control) -> &[KeyCombo] {
+        self.0.get(&control).map_or(&[], Vec::as_slice)
+    }
+
+    /// The name of the first key bound to a control, to show in game
+    pub fn label(&self, control: Control) -> String {
+        self.combos(control)
+            .first()
+            .map_or_else(|| "-".to_string(), KeyCombo::to_string)
+    }
+
+    /// Make a key combination the first binding of a control, taking it away from any other
+    /// control that had it
+    pub fn rebind(&mut self, control: Control, combo: KeyCombo) {
+        for combos in self.0.values_mut() {
+            combos.retain(|bound| *bound != combo);
+        }
+        let combos = self.0.entry(control).or_default();
+        if combos.is_empty() {
+            combos.push(combo);
+        } else {
+            combos[0] = combo;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::gamepad::{GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadEvent};
+
+    use super::*;
+    use crate::testing::{connect_gamepad, input_app};
+
+    /// Records the controls set off on each update
+    #[derive(Default, Resource)]
+    struct Pressed(Vec<Control>);
+
+    fn record_pressed(input: ControlInput, mut pressed: ResMut<Pressed>) {
+        pressed.0 = Control::ALL
+            .into_iter()
+            .filter(|&control| input.just_pressed(control))
+            .collect();
+    }
+
+    fn app() -> App {
+        let mut app = input_app();
+        app.init_resource::<Pressed>()
+            .add_systems(Update, record_pressed);
+        connect_gamepad(&mut app);
+        app
+    }
+
+    fn send(app: &mut App, event: impl Into<GamepadEvent>) -> Vec<Control> {
+        app.world.send_event(event.into());
+        app.update();
+        app.world.resource::<Pressed>().0.clone()
+    }
+
+    #[test]
+    fn gamepad_buttons_set_off_controls() {
+        let mut app = app();
+        let pad = Gamepad::new(0);
+        let pressed = send(
+            &mut app,
+            GamepadButtonChangedEvent::new(pad, GamepadButtonType::LeftTrigger, 1.0),
+        );
+        assert_eq!(pressed, vec![Control::Ladder]);
+
+        // holding the button doesn't place another ladder
+        app.update();
+        assert!(app.world.resource::<Pressed>().0.is_empty());
+
+        send(
+            &mut app,
+            GamepadButtonChangedEvent::new(pad, GamepadButtonType::LeftTrigger, 0.0),
+        );
+        let pressed = send(
+            &mut app,
+            GamepadButtonChangedEvent::new(pad, GamepadButtonType::West, 1.0),
+        );
+        assert_eq!(pressed, vec![Control::Undo]);
+    }
+
+    #[test]
+    fn pushing_the_stick_moves_once() {
+        let mut app = app();
+        let pad = Gamepad::new(0);
+        let pressed = send(
+            &mut app,
+            GamepadAxisChangedEvent::new(pad, GamepadAxisType::LeftStickY, 0.9),
+        );
+        assert_eq!(pressed, vec![Control::MoveNorth]);
+        let pressed = send(
+            &mut app,
+            GamepadAxisChangedEvent::new(pad, GamepadAxisType::LeftStickX, -0.3),
+        );
+        assert!(pressed.is_empty());
+
+        // it has to come back to the middle before it moves again
+        let pressed = send(
+            &mut app,
+            GamepadAxisChangedEvent::new(pad, GamepadAxisType::LeftStickY, 0.0),
+        );
+        assert!(pressed.is_empty());
+        let pressed = send(
+            &mut app,
+            GamepadAxisChangedEvent::new(pad, GamepadAxisType::LeftStickX, -1.0),
+        );
+        assert_eq!(pressed, vec![Control::MoveWest]);
+    }
+
+    #[test]
+    fn rebinding_takes_the_key_from_other_controls() {
+        let mut bindings = KeyBindings::default();
+        bindings.rebind(Control::Ladder, KeyCombo::key(KeyCode::W));
+        assert_eq!(
+            bindings.combos(Control::Ladder),
+            &[KeyCombo::key(KeyCode::W)]
+        );
+        assert_eq!(
+            bindings.combos(Control::MoveNorth),
+            &[KeyCombo::key(KeyCode::Up)]
+        );
+        assert_eq!(bindings.label(Control::MoveNorth), "Up");
+
+        bindings.rebind(Control::MoveEast, KeyCombo::key(KeyCode::Up));
+        assert_eq!(bindings.combos(Control::MoveNorth), &[]);
+        assert_eq!(bindings.label(Control::MoveNorth), "-");
+        bindings.rebind(Control::MoveNorth, KeyCombo::key(KeyCode::I));
+        assert_eq!(bindings.label(Control::MoveNorth), "I");
+    }
+
+    #[test]
+    fn saved_bindings_fill_in_new_controls() {
+        let saved = "{Undo: [(key: U, control: true)], Ladder: [(key: Key9)]}";
+        let bindings: KeyBindings = ron::from_str(saved).unwrap();
+        assert_eq!(bindings.label(Control::Undo), "Ctrl+U");
+        assert_eq!(bindings.label(Control::Ladder), "9");
+        assert_eq!(bindings.label(Control::Restart), "R");
+
+        let written = ron::to_string(&bindings).unwrap();
+        assert_eq!(ron::from_str::<KeyBindings>(&written).unwrap(), bindings);
+    }
+
+    #[test]
+    fn modifiers_have_to_match() {
+        let bindings = KeyBindings::default();
+        let mut keyboard_input = Input::<KeyCode>::default();
+        keyboard_input.press(KeyCode::Z);
+        assert!(!bindings.just_pressed(&keyboard_input, Control::Undo));
+
+        keyboard_input.clear();
+        keyboard_input.press(KeyCode::ControlLeft);
+        keyboard_input.press(KeyCode::ShiftRight);
+        keyboard_input.release(KeyCode::Z);
+        keyboard_input.press(KeyCode::Z);
+        assert!(!bindings.just_pressed(&keyboard_input, Control::Undo));
+        assert!(bindings.just_pressed(&keyboard_input, Control::Redo));
+    }
+}
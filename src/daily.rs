@@ -0,0 +1,187 @@
+//! The daily challenge: one generated level a day, the same for everyone, with the best result
+//! for each day kept between sessions.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    generator::{generate, Difficulty, GeneratedLevel},
+    level_manager::LevelManager,
+    rules::Puzzle,
+    save::{default_store, Stored},
+    states::{level::level_transition, GameState},
+};
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DailyRecords::load(default_store("daily")))
+            .add_systems(
+                OnEnter(GameState::LevelTransition),
+                record_daily_result
+                    .before(level_transition)
+                    .run_if(playing_daily),
+            );
+    }
+}
+
+/// The best result of each daily challenge played, kept between sessions
+pub type DailyRecords = Stored<DailyData>;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyData {
+    /// Keyed by the day, counted from 1970-01-01
+    pub best: BTreeMap<u32, DailyResult>,
+}
+
+/// How a daily challenge was finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub stamina_left: u16,
+    pub moves: u16,
+}
+
+impl DailyResult {
+    /// More stamina left is better, and then fewer moves
+    pub fn beats(&self, other: &DailyResult) -> bool {
+        (self.stamina_left, other.moves) > (other.stamina_left, self.moves)
+    }
+}
+
+impl DailyData {
+    /// Remember a result if it is the best one for the day. Returns whether it was.
+    pub fn record(&mut self, day: u32, result: DailyResult) -> bool {
+        let best = self.best.entry(day).or_insert(result);
+        if result.beats(best) {
+            *best = result;
+        }
+        *best == result
+    }
+}
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Days since 1970-01-01, in UTC so that everyone gets the same level at the same time
+#[cfg(not(target_arch = "wasm32"))]
+pub fn today() -> u32 {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    (since_epoch.as_secs() / SECONDS_PER_DAY) as u32
+}
+
+/// Days since 1970-01-01, in UTC so that everyone gets the same level at the same time
+#[cfg(target_arch = "wasm32")]
+pub fn today() -> u32 {
+    // the system clock isn't available in the browser, but the date is
+    (js_sys::Date::now() / 1000.0 / SECONDS_PER_DAY as f64) as u32
+}
+
+/// Mix the day's bits about, so that days next to each other get unrelated seeds. This is
+/// spelled out rather than using a `Hasher`, whose output may change between Rust versions.
+pub fn seed_for(day: u32) -> u64 {
+    // the finaliser of SplitMix64
+    let mut seed = (day as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    seed = (seed ^ (seed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    seed = (seed ^ (seed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    seed ^ (seed >> 31)
+}
+
+/// The day as year-month-day
+pub fn date(day: u32) -> String {
+    // from Howard Hinnant's `civil_from_days`
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day_of_month)
+}
+
+/// The day's level
+pub fn daily_level(day: u32) -> GeneratedLevel {
+    let mut generated = generate(seed_for(day), Difficulty::Medium);
+    generated.level.name = format!("Daily {}", date(day));
+    generated
+}
+
+fn playing_daily(level_manager: Res<LevelManager>) -> bool {
+    level_manager.daily.is_some()
+}
+
+fn record_daily_result(
+    puzzle: Res<Puzzle>,
+    level_manager: Res<LevelManager>,
+    mut daily_records: ResMut<DailyRecords>,
+) {
+    // skipping doesn't count
+    let Some(day) = level_manager.daily.filter(|_| puzzle.is_complete()) else {
+        return;
+    };
+    let result = DailyResult {
+        stamina_left: puzzle.state.player.stamina,
+        moves: puzzle.state.moves,
+    };
+    if daily_records.data.record(day, result) {
+        if let Err(e) = daily_records.save() {
+            warn!("Couldn't save the daily result: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_read_as_dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(20_743), "2026-10-17");
+    }
+
+    #[test]
+    fn days_get_different_seeds() {
+        assert_ne!(seed_for(20_743), seed_for(20_744));
+        // the level depends on nothing but the day
+        let level = daily_level(20_743).level;
+        assert_eq!(
+            level.map.grid_heights,
+            daily_level(20_743).level.map.grid_heights
+        );
+        assert_eq!(level.name, "Daily 2026-10-17");
+    }
+
+    #[test]
+    fn seeds_stay_the_same_between_builds() {
+        // everyone has to get the same level, whatever version of the game they have
+        assert_eq!(seed_for(20_743), 0xcf1f_8f79_2b82_3197);
+    }
+
+    #[test]
+    fn only_the_best_result_is_kept() {
+        let mut data = DailyData::default();
+        let result = |stamina_left, moves| DailyResult {
+            stamina_left,
+            moves,
+        };
+        assert!(data.record(1, result(2, 20)));
+        assert!(!data.record(1, result(1, 10)));
+        assert!(data.record(1, result(2, 18)));
+        assert!(data.record(1, result(3, 30)));
+        assert_eq!(data.best[&1], result(3, 30));
+    }
+}
@@ -1,14 +1,24 @@
+use std::collections::{BTreeSet, HashMap};
+
 use bevy::prelude::*;
 
-use crate::states::GameState;
+use crate::{
+    replay::Playback,
+    rules::{
+        Alignment, CardinalDirection, HorizontalLadderKey, Puzzle, RopeKey, VerticalLadderKey,
+    },
+    states::{loading::ModelAssets, GameState, PauseState},
+};
 
 use self::{
-    ladder::{handle_ladder_input, Ladder},
-    rewind::{handle_rewind_input, update_countdown_image},
-    rope::handle_rope_input,
+    ladder::{handle_ladder_input, place_horizontal_ladder, place_vertical_ladder, Ladder},
+    potion::{animate_potion_effect, handle_potion_input},
+    rewind::{handle_rewind_input, sync_runes, update_countdown_image},
+    rope::{handle_rope_input, place_rope},
 };
 
 pub mod ladder;
+pub mod potion;
 pub mod rewind;
 pub mod rope;
 
@@ -17,27 +27,124 @@ pub struct EquipmentPlugin;
 impl Plugin for EquipmentPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Ladder>()
-            .register_type::<Inventory>()
-            .insert_resource(Inventory::default())
+            .insert_resource(EquipmentEntities::default())
             .add_systems(
                 Update,
                 (
-                    handle_ladder_input,
-                    handle_rope_input,
-                    handle_rewind_input,
+                    (
+                        handle_ladder_input,
+                        handle_rope_input,
+                        handle_rewind_input,
+                        handle_potion_input,
+                    )
+                        .run_if(not(resource_exists::<Playback>()))
+                        .run_if(in_state(PauseState::Playing)),
+                    sync_equipment.run_if(resource_changed::<Puzzle>()),
+                    sync_runes.run_if(resource_changed::<Puzzle>()),
                     update_countdown_image,
+                    animate_potion_effect,
                 )
                     .run_if(in_state(GameState::Level)),
             );
     }
 }
 
-#[derive(Debug, Default, Resource, Reflect)]
-#[reflect(Resource)]
-pub struct Inventory {
-    pub ladder_count: u8,
-    pub rope_count: u8,
-    pub potion_count: u8,
-    pub rewind_count: u8,
-    pub weight: u8,
+/// Scene entities spawned for the equipment placed on the current map
+#[derive(Debug, Default, Resource)]
+pub struct EquipmentEntities {
+    pub vertical_ladders: HashMap<VerticalLadderKey, Entity>,
+    pub horizontal_ladders: HashMap<HorizontalLadderKey, Entity>,
+    pub ropes: HashMap<RopeKey, Entity>,
+}
+
+fn despawn_removed<K: Ord>(
+    commands: &mut Commands,
+    entities: &mut HashMap<K, Entity>,
+    placed: &BTreeSet<K>,
+) {
+    entities.retain(|key, entity| {
+        let keep = placed.contains(key);
+        if !keep {
+            commands.entity(*entity).despawn_recursive();
+        }
+        keep
+    });
+}
+
+/// Spawn and despawn ladders and ropes to match the equipment placed in the puzzle
+pub fn sync_equipment(
+    mut commands: Commands,
+    puzzle: Res<Puzzle>,
+    mut equipment_entities: ResMut<EquipmentEntities>,
+    model_assets: Res<ModelAssets>,
+) {
+    let map = &puzzle.map;
+    let placed = &puzzle.state.equipment;
+    let entities = &mut *equipment_entities;
+    despawn_removed(
+        &mut commands,
+        &mut entities.vertical_ladders,
+        &placed.vertical_ladders,
+    );
+    despawn_removed(
+        &mut commands,
+        &mut entities.horizontal_ladders,
+        &placed.horizontal_ladders,
+    );
+    despawn_removed(&mut commands, &mut entities.ropes, &placed.ropes);
+
+    for key in placed.vertical_ladders.iter() {
+        if !entities.vertical_ladders.contains_key(key) {
+            // the ladder leans against the wall from the square it was placed on
+            let (x, y) = map
+                .step(key.x, key.y, key.direction.reverse(), 1)
+                .expect("Ladders are placed from a square on the map");
+            let entity = place_vertical_ladder(
+                &mut commands,
+                model_assets.ladder.clone(),
+                key.direction,
+                x as f32,
+                y as f32,
+                key.height as f32,
+            );
+            entities.vertical_ladders.insert(key.clone(), entity);
+        }
+    }
+
+    let player = &puzzle.state.player;
+    let facing = player.direction();
+    let in_front = map.step(player.grid_pos_x, player.grid_pos_y, facing, 1);
+    for key in placed.horizontal_ladders.iter() {
+        if !entities.horizontal_ladders.contains_key(key) {
+            // lay the ladder from the side the player is facing it from, if they are next to it
+            let direction =
+                if Alignment::from(facing) == key.alignment && in_front == Some((key.x, key.y)) {
+                    facing
+                } else {
+                    match key.alignment {
+                        Alignment::Xaxis => CardinalDirection::East,
+                        Alignment::Yaxis => CardinalDirection::North,
+                    }
+                };
+            let (x, y) = map
+                .step(key.x, key.y, direction.reverse(), 1)
+                .expect("Ladders bridge a gap between two squares on the map");
+            let entity = place_horizontal_ladder(
+                &mut commands,
+                model_assets.ladder.clone(),
+                direction,
+                x as f32,
+                y as f32,
+                key.height as f32,
+            );
+            entities.horizontal_ladders.insert(key.clone(), entity);
+        }
+    }
+
+    for key in placed.ropes.iter() {
+        if !entities.ropes.contains_key(key) {
+            let entity = place_rope(&mut commands, &model_assets, map, key);
+            entities.ropes.insert(key.clone(), entity);
+        }
+    }
 }
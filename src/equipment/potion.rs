@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    controls::{Control, ControlInput},
+    rules::{Action, Player, Puzzle, RuleError},
+};
+
+const EFFECT_DURATION: f32 = 0.6;
+
+#[derive(Debug, Component)]
+pub struct PotionEffect {
+    pub elapsed: f32,
+}
+
+pub fn handle_potion_input(
+    mut commands: Commands,
+    input: ControlInput,
+    player_query: Query<Entity, With<Player>>,
+    mut puzzle: ResMut<Puzzle>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    if input.just_pressed(Control::Potion) {
+        let entity = player_query
+            .get_single()
+            .expect("There should only be one player");
+        match puzzle.apply(Action::DrinkPotion) {
+            Ok(_) => {
+                sound_channel.play(audio_assets.pickup.clone());
+
+                // green burst around the climber
+                commands.entity(entity).with_children(|parent| {
+                    parent
+                        .spawn(MaterialMeshBundle {
+                            mesh: meshes.add(
+                                shape::UVSphere {
+                                    radius: 0.5,
+                                    sectors: 16,
+                                    stacks: 8,
+                                }
+                                .into(),
+                            ),
+                            material: materials.add(StandardMaterial {
+                                base_color: Color::rgba(0.2, 0.9, 0.3, 0.6),
+                                alpha_mode: AlphaMode::Blend,
+                                unlit: true,
+                                ..Default::default()
+                            }),
+                            transform: Transform::from_xyz(0.0, 0.5, 0.0),
+                            ..Default::default()
+                        })
+                        .insert(PotionEffect { elapsed: 0.0 });
+                });
+            }
+            Err(RuleError::LevelComplete) => {}
+            Err(_) => {
+                sound_channel.play(audio_assets.error.clone());
+            }
+        }
+    }
+}
+
+pub fn animate_potion_effect(
+    mut commands: Commands,
+    mut effects: Query<(
+        Entity,
+        &mut PotionEffect,
+        &mut Transform,
+        &Handle<StandardMaterial>,
+    )>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+) {
+    for (entity, mut effect, mut transform, material) in effects.iter_mut() {
+        effect.elapsed += time.delta_seconds();
+        let progress = effect.elapsed / EFFECT_DURATION;
+        if progress >= 1.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.scale = Vec3::splat(0.5 + progress);
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color.set_a(0.6 * (1.0 - progress));
+        }
+    }
+}
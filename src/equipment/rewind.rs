@@ -1,20 +1,18 @@
 use bevy::prelude::*;
 
 use crate::{
-    level_manager::LevelManager,
-    player::{Player, PlayerHistory, PlayerHistoryEvent, PlayerState},
-    states::loading::TextureAssets,
+    controls::{Control, ControlInput},
+    rules::{Action, Puzzle, Rune},
+    states::{level::DespawnOnTransition, loading::TextureAssets},
 };
 
-use super::Inventory;
-
 #[derive(Debug, Component)]
 pub struct RewindRune {
     pub x: u8,
     pub y: u8,
     pub countdown: u8,
-    pub stamina: u16,
-    pub timestamp: f32,
+    /// The move the rune goes off on, which tells apart runes placed on the same square
+    pub due: u16,
 }
 
 #[derive(Debug, Component)]
@@ -28,8 +26,7 @@ pub fn spawn_rune(
     height: f32,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
-    stamina: u16,
-    timestamp: f32,
+    due: u16,
     countdown: u8,
 ) {
     commands
@@ -50,9 +47,9 @@ pub fn spawn_rune(
             x,
             y,
             countdown,
-            stamina,
-            timestamp,
+            due,
         })
+        .insert(DespawnOnTransition)
         .with_children(|parent| {
             let mut spawn_countdown = |texture: Handle<Image>, n: u8| {
                 parent
@@ -98,40 +95,50 @@ pub fn update_countdown_image(
     }
 }
 
-pub fn handle_rewind_input(
+/// Spawn and despawn runes to match the ones in the puzzle, and keep their countdowns up to date
+pub fn sync_runes(
     mut commands: Commands,
-    keyboard_input: Res<Input<KeyCode>>,
-    player_query: Query<&Player>,
+    puzzle: Res<Puzzle>,
+    mut rewind_runes: Query<(Entity, &mut RewindRune)>,
     texture_assets: Res<TextureAssets>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    level_manager: Res<LevelManager>,
-    mut player_history: ResMut<PlayerHistory>,
-    mut inventory: ResMut<Inventory>,
-    time: Res<Time>,
 ) {
-    if let Ok(player) = player_query.get_single() {
-        if keyboard_input.just_pressed(KeyCode::Key3)
-            && matches!(player.state, PlayerState::Standing(_))
-            && inventory.rewind_count > 0
-        {
-            let player_height = level_manager.get_current_level().map.grid_heights
-                [player.grid_pos_y as usize][player.grid_pos_x as usize];
-
-            spawn_rune(
-                &mut commands,
-                &texture_assets,
-                player.grid_pos_x,
-                player.grid_pos_y,
-                player_height as f32 + 0.01,
-                &mut meshes,
-                &mut materials,
-                player.stamina,
-                time.elapsed_seconds(),
-                5,
-            );
-            inventory.rewind_count -= 1;
-            player_history.0.push(PlayerHistoryEvent::PlaceRune);
+    let state = &puzzle.state;
+    let due = |rune: &Rune| state.moves + rune.countdown as u16;
+    let mut missing: Vec<&Rune> = state.runes.iter().collect();
+    for (entity, mut rewind_rune) in rewind_runes.iter_mut() {
+        let found = missing.iter().position(|rune| {
+            (rune.x, rune.y, due(rune)) == (rewind_rune.x, rewind_rune.y, rewind_rune.due)
+        });
+        if let Some(index) = found {
+            let rune = missing.swap_remove(index);
+            if rewind_rune.countdown != rune.countdown {
+                rewind_rune.countdown = rune.countdown;
+            }
+        } else {
+            commands.entity(entity).despawn_recursive();
         }
     }
+    for rune in missing {
+        let height = puzzle.map.grid_heights[rune.y as usize][rune.x as usize];
+        spawn_rune(
+            &mut commands,
+            &texture_assets,
+            rune.x,
+            rune.y,
+            height as f32 + 0.01,
+            &mut meshes,
+            &mut materials,
+            due(rune),
+            rune.countdown,
+        );
+    }
+}
+
+pub fn handle_rewind_input(input: ControlInput, mut puzzle: ResMut<Puzzle>) {
+    if input.just_pressed(Control::Rune) {
+        // nothing happens if a rune can't be placed here
+        let _ = puzzle.apply(Action::PlaceRune);
+    }
 }
@@ -0,0 +1,317 @@
+//! Levels made up from a seed: random hills, kept only once the solver has shown that they can
+//! be finished and take some working out.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    rating::Par,
+    rules::{
+        CardinalDirection, CaveData, CaveLock, Gem, GemColour, Map, PuzzleState, LADDER_WEIGHT,
+    },
+    solver::{solve_within, Goal},
+    states::level::Level,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    #[cfg(test)]
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    /// The next difficulty up, or this one if it is already the hardest
+    pub fn harder(&self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Medium,
+            Difficulty::Medium | Difficulty::Hard => Difficulty::Hard,
+        }
+    }
+
+    /// Width and depth of the map
+    fn size(&self) -> (u8, u8) {
+        match self {
+            Difficulty::Easy => (5, 5),
+            Difficulty::Medium => (6, 5),
+            Difficulty::Hard => (6, 6),
+        }
+    }
+
+    fn max_height(&self) -> u8 {
+        match self {
+            Difficulty::Easy => 3,
+            Difficulty::Medium => 4,
+            Difficulty::Hard => 5,
+        }
+    }
+
+    /// Levels that can be finished in fewer presses than this are too easy to keep
+    fn fewest_presses(&self) -> usize {
+        match self {
+            Difficulty::Easy => 8,
+            Difficulty::Medium => 11,
+            Difficulty::Hard => 14,
+        }
+    }
+
+    /// Stamina given on top of what the quickest way to the flag needs
+    fn spare_stamina(&self) -> u16 {
+        match self {
+            Difficulty::Easy => 3,
+            Difficulty::Medium => 1,
+            Difficulty::Hard => 0,
+        }
+    }
+
+    /// Chance of each piece of equipment being unlocked
+    fn unlock_chance(&self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.3,
+            Difficulty::Medium => 0.5,
+            Difficulty::Hard => 0.7,
+        }
+    }
+
+    fn cave_chance(&self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.0,
+            Difficulty::Medium => 0.3,
+            Difficulty::Hard => 0.5,
+        }
+    }
+}
+
+/// A level made by [`generate`], along with what it was made from
+#[derive(Debug, Clone)]
+pub struct GeneratedLevel {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub level: Level,
+}
+
+/// Maps to try from each seed before moving on to the next one derived from it
+const MOST_ATTEMPTS: u32 = 100;
+/// Nodes the solver may search through for each map. Maps that need more are thrown away, which
+/// keeps generating quick enough in the browser, where there are no threads to do it on.
+const MOST_NODES: usize = 30_000;
+/// Stamina to look for solutions with, before cutting it down to what the solution needs
+const PLENTY_OF_STAMINA: u16 = 60;
+
+/// Make a level that can be finished. The same seed and difficulty always give the same level,
+/// on every platform and build, as only fixed-size numbers are drawn from a generator whose
+/// output is pinned down, unlike `StdRng`'s.
+pub fn generate(seed: u64, difficulty: Difficulty) -> GeneratedLevel {
+    generate_within(seed, difficulty, MOST_ATTEMPTS)
+}
+
+/// Like [`generate`], but trying `attempts` maps from each seed before moving on to the next
+fn generate_within(seed: u64, difficulty: Difficulty, attempts: u32) -> GeneratedLevel {
+    let level = (0..)
+        .find_map(|round| try_seed(derived_seed(seed, round), difficulty, attempts))
+        .expect("Some seed should make a level");
+    GeneratedLevel {
+        seed,
+        difficulty,
+        level: Level {
+            name: format!("{} #{}", difficulty.name(), seed % 10_000),
+            ..level
+        },
+    }
+}
+
+/// The seed for each round of attempts, starting with the one asked for. Multiplying by an odd
+/// number spreads the rounds of neighbouring seeds apart, so they don't share levels.
+fn derived_seed(seed: u64, round: u64) -> u64 {
+    seed.wrapping_add(round.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+/// The first good enough level out of a run of maps made from the seed, if there is one
+fn try_seed(seed: u64, difficulty: Difficulty, attempts: u32) -> Option<Level> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    (0..attempts).find_map(|_| try_level(&mut rng, difficulty))
+}
+
+/// A random square on the map
+fn square(rng: &mut ChaCha8Rng, width: u8, depth: u8) -> (u8, u8) {
+    (rng.gen_range(0..width), rng.gen_range(0..depth))
+}
+
+fn try_level(rng: &mut ChaCha8Rng, difficulty: Difficulty) -> Option<Level> {
+    let (width, depth) = difficulty.size();
+    let max_height = difficulty.max_height();
+
+    // raise rectangles of ground, so that neighbouring squares tend to be close in height
+    let mut heights = vec![vec![1u8; width as usize]; depth as usize];
+    for _ in 0..(width as u32 * depth as u32 / 2) {
+        let (left, top) = square(rng, width, depth);
+        let (right, bottom) = (
+            (left + rng.gen_range(0..3u8)).min(width - 1),
+            (top + rng.gen_range(0..3u8)).min(depth - 1),
+        );
+        for row in &mut heights[top as usize..=bottom as usize] {
+            for height in &mut row[left as usize..=right as usize] {
+                *height = (*height + 1).min(max_height);
+            }
+        }
+    }
+    let climbable: Vec<Vec<bool>> = (0..depth)
+        .map(|_| (0..width).map(|_| rng.gen_bool(0.45)).collect())
+        .collect();
+
+    let start = square(rng, width, depth);
+    let flag = square(rng, width, depth);
+    let apart = start.0.abs_diff(flag.0) + start.1.abs_diff(flag.1);
+    if apart < (width + depth) / 2 {
+        return None;
+    }
+    let free = |pos: (u8, u8)| pos != start && pos != flag;
+    let scale = Some(square(rng, width, depth)).filter(|&pos| free(pos) && rng.gen_bool(0.6));
+
+    let (mut caves, mut gems) = (Vec::new(), Vec::new());
+    if rng.gen_bool(difficulty.cave_chance()) {
+        // caves go into a wall to the north, like most of the authored ones
+        let under_wall = |(x, y): (u8, u8)| {
+            y > 0 && heights[y as usize - 1][x as usize] > heights[y as usize][x as usize]
+        };
+        let first_pos = square(rng, width, depth);
+        let second_pos = square(rng, width, depth);
+        let gem_pos = Some(square(rng, width, depth)).filter(|&pos| free(pos) && rng.gen_bool(0.5));
+        if first_pos == second_pos
+            || ![first_pos, second_pos].into_iter().all(under_wall)
+            || ![first_pos, second_pos].into_iter().all(free)
+        {
+            return None;
+        }
+        let mut cave = CaveData {
+            first_pos,
+            second_pos,
+            entrance: CardinalDirection::North,
+            lock: CaveLock::Open,
+        };
+        if let Some(pos) = gem_pos {
+            cave.lock = CaveLock::AnyGem;
+            gems.push(Gem {
+                pos,
+                colour: GemColour::default(),
+            });
+        }
+        caves.push(cave);
+    }
+
+    let chance = difficulty.unlock_chance();
+    let mut level = Level {
+        name: String::new(),
+        map: Map::new(heights, climbable, start, flag, scale, caves, gems),
+        stamina_budget: PLENTY_OF_STAMINA,
+        weight_budget: 0,
+        ladder_unlocked: rng.gen_bool(chance),
+        rope_unlocked: rng.gen_bool(chance),
+        potion_unlocked: rng.gen_bool(chance),
+        rewind_unlocked: rng.gen_bool(chance),
+        par: None,
+        tutorial: Vec::new(),
+    };
+    if level.ladder_unlocked
+        || level.rope_unlocked
+        || level.potion_unlocked
+        || level.rewind_unlocked
+    {
+        level.weight_budget = rng.gen_range(1..=LADDER_WEIGHT + 2);
+    }
+
+    let solution = solve_within(&level, Goal::Flag, MOST_NODES)?;
+    if solution.actions.len() < difficulty.fewest_presses() {
+        return None;
+    }
+    // only give as much stamina as the solution needs at its lowest point, and a little over
+    let mut state = PuzzleState::new(&level.map, PLENTY_OF_STAMINA);
+    state.inventory = solution.loadout;
+    let mut lowest = state.player.stamina;
+    for &action in &solution.actions {
+        state = state.apply(&level.map, action).ok()?.0;
+        lowest = lowest.min(state.player.stamina);
+    }
+    level.stamina_budget = PLENTY_OF_STAMINA - lowest + difficulty.spare_stamina();
+    level.par = Par::of(&level, &solution);
+
+    // a scale that can't be got to along with the flag isn't worth showing
+    if level.map.scale_pos.is_some()
+        && solve_within(&level, Goal::FlagWithScale, MOST_NODES).is_none()
+    {
+        level.map.scale_pos = None;
+    }
+    Some(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::solve;
+
+    #[test]
+    fn the_same_seed_makes_the_same_level() {
+        let first = generate(7, Difficulty::Medium).level;
+        let second = generate(7, Difficulty::Medium).level;
+        assert_eq!(first.map.grid_heights, second.map.grid_heights);
+        assert_eq!(first.map.flag_pos, second.map.flag_pos);
+        assert_eq!(first.stamina_budget, second.stamina_budget);
+    }
+
+    #[test]
+    fn levels_stay_the_same_between_builds() {
+        // changing the generator, or what it draws from the random numbers, changes these
+        let level = generate(7, Difficulty::Medium).level;
+        assert_eq!(
+            level.map.grid_heights,
+            [
+                [3, 3, 2, 2, 2, 1],
+                [4, 4, 2, 2, 3, 2],
+                [3, 3, 1, 2, 4, 3],
+                [2, 1, 1, 2, 4, 4],
+                [1, 1, 1, 2, 4, 4],
+            ]
+        );
+        assert_eq!(level.map.player_start_pos, (4, 3));
+        assert_eq!(level.map.flag_pos, (0, 1));
+    }
+
+    #[test]
+    fn generated_levels_can_be_finished_but_not_trivially() {
+        for difficulty in Difficulty::ALL {
+            for seed in 0..4 {
+                let level = generate(seed, difficulty).level;
+                let solution = solve(&level, Goal::Flag)
+                    .unwrap_or_else(|| panic!("{} can't be finished", level.name));
+                assert!(
+                    solution.actions.len() >= difficulty.fewest_presses(),
+                    "{} is too easy",
+                    level.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_that_make_nothing_move_on_to_others() {
+        // with only one map to a seed, plenty of seeds don't make a level
+        let seed = (0..)
+            .find(|&seed| try_seed(seed, Difficulty::Hard, 1).is_none())
+            .unwrap();
+        let level = generate_within(seed, Difficulty::Hard, 1).level;
+        let solution = solve(&level, Goal::Flag).unwrap();
+        assert!(solution.actions.len() >= Difficulty::Hard.fewest_presses());
+    }
+}
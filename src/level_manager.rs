@@ -1,459 +1,554 @@
-use bevy::prelude::*;
+use std::{
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
-use crate::{cave::CaveData, map::Map, states::level::Level};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    tasks::AsyncComputeTaskPool,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    generator::GeneratedLevel,
+    rating::Par,
+    rules::{CaveData, Gem, GemColour, Map},
+    states::{level::Level, loading::LevelAssets},
+    tutorial::TutorialPrompt,
+};
 
 #[derive(Debug, Default, Resource, Reflect)]
 #[reflect(Resource)]
 pub struct LevelManager {
+    #[reflect(ignore)]
     pub levels: Vec<Level>,
     pub current: usize,
+    /// A generated level being played instead of the campaign
+    #[reflect(ignore)]
+    pub generated: Option<GeneratedLevel>,
+    /// The day of the daily challenge, when that is what the generated level is
+    pub daily: Option<u32>,
+    /// A level from the editor being playtested, played instead of both of the above without
+    /// touching them
+    #[reflect(ignore)]
+    pub playtest: Option<Level>,
 }
 impl LevelManager {
     pub fn get_current_level(&self) -> &Level {
-        &self.levels[self.current]
+        match (&self.playtest, &self.generated) {
+            (Some(playtest), _) => playtest,
+            (None, Some(generated)) => &generated.level,
+            (None, None) => &self.levels[self.current],
+        }
+    }
+}
+
+/// A level being generated off the main thread, which becomes the generated level once it is
+/// ready
+#[derive(Resource)]
+pub struct PendingLevel {
+    level: Arc<Mutex<Option<GeneratedLevel>>>,
+    /// The day, if this is the daily challenge
+    pub daily: Option<u32>,
+}
+
+impl PendingLevel {
+    pub fn spawn(
+        daily: Option<u32>,
+        make: impl FnOnce() -> GeneratedLevel + Send + 'static,
+    ) -> Self {
+        let level = Arc::new(Mutex::new(None));
+        let result = level.clone();
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                *result.lock().unwrap() = Some(make());
+            })
+            .detach();
+        Self { level, daily }
+    }
+
+    /// The level, once it has been made
+    pub fn take(&self) -> Option<GeneratedLevel> {
+        self.level.lock().unwrap().take()
+    }
+}
+
+/// An ordered list of levels, loaded from a `.campaign.ron` manifest.
+#[derive(Debug, TypeUuid, TypePath)]
+#[uuid = "0d8a0a4e-3c56-4d6b-9a0f-5f1f3a5b7c21"]
+pub struct Campaign {
+    pub levels: Vec<Level>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CampaignFile {
+    levels: Vec<String>,
+}
+
+/// A single level as written in `assets/levels/*.ron`.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct LevelFile {
+    #[serde(default)]
+    name: String,
+    heights: Vec<Vec<u8>>,
+    // one string per row: '#' is climbable, '.' is not
+    climbable: Vec<String>,
+    start: (u8, u8),
+    flag: (u8, u8),
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scale: Option<(u8, u8)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    caves: Vec<CaveData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    gems: Vec<Gem>,
+    stamina_budget: u16,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    weight_budget: u8,
+    #[serde(default, skip_serializing_if = "is_false")]
+    ladder_unlocked: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    rope_unlocked: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    potion_unlocked: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    rewind_unlocked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    par: Option<Par>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tutorial: Vec<TutorialPrompt>,
+}
+
+fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Syntax(ron::error::SpannedError),
+    EmptyGrid,
+    RaggedHeights {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    ClimbableRowCount {
+        expected: usize,
+        found: usize,
+    },
+    ClimbableRowLength {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    InvalidClimbableCell {
+        row: usize,
+        column: usize,
+        found: char,
+    },
+    OutOfBounds {
+        what: &'static str,
+        pos: (u8, u8),
+    },
+    /// A pair of caves with both mouths on the same square, which would lead nowhere
+    SameSquareCave {
+        pos: (u8, u8),
+    },
+    /// Two pairs of caves with a mouth on the same square
+    SharedCaveMouth {
+        pos: (u8, u8),
+    },
+    DuplicateGem {
+        colour: GemColour,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Syntax(e) => write!(f, "invalid level syntax: {}", e),
+            LevelError::EmptyGrid => write!(f, "`heights` must have at least one row and column"),
+            LevelError::RaggedHeights {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`heights` row {} has {} columns, expected {}",
+                row, found, expected
+            ),
+            LevelError::ClimbableRowCount { expected, found } => write!(
+                f,
+                "`climbable` has {} rows, expected {} to match `heights`",
+                found, expected
+            ),
+            LevelError::ClimbableRowLength {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`climbable` row {} has {} columns, expected {}",
+                row, found, expected
+            ),
+            LevelError::InvalidClimbableCell { row, column, found } => write!(
+                f,
+                "`climbable` row {} column {} is '{}', expected '#' or '.'",
+                row, column, found
+            ),
+            LevelError::OutOfBounds { what, pos } => {
+                write!(f, "{} position {:?} is outside the map", what, pos)
+            }
+            LevelError::SameSquareCave { pos } => {
+                write!(f, "both mouths of a pair of caves are on {:?}", pos)
+            }
+            LevelError::SharedCaveMouth { pos } => {
+                write!(f, "more than one pair of caves opens onto {:?}", pos)
+            }
+            LevelError::DuplicateGem { colour } => {
+                write!(f, "there is more than one {:?} gem", colour)
+            }
+        }
     }
-    pub fn get_current_map_mut(&mut self) -> &mut Map {
-        &mut self.levels[self.current].map
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<ron::error::SpannedError> for LevelError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        LevelError::Syntax(value)
     }
 }
 
-pub fn init_level_manager(mut commands: Commands) {
+/// Parse and validate a level written in the RON level format.
+pub fn parse_level(source: &[u8]) -> Result<Level, LevelError> {
+    let file: LevelFile = ron::de::from_bytes(source)?;
+
+    let height = file.heights.len();
+    let width = file.heights.first().map_or(0, |row| row.len());
+    if width == 0 {
+        return Err(LevelError::EmptyGrid);
+    }
+    for (row, heights) in file.heights.iter().enumerate() {
+        if heights.len() != width {
+            return Err(LevelError::RaggedHeights {
+                row,
+                expected: width,
+                found: heights.len(),
+            });
+        }
+    }
+
+    if file.climbable.len() != height {
+        return Err(LevelError::ClimbableRowCount {
+            expected: height,
+            found: file.climbable.len(),
+        });
+    }
+    let mut grid_climbable = Vec::with_capacity(height);
+    for (row, line) in file.climbable.iter().enumerate() {
+        let cells = line
+            .chars()
+            .enumerate()
+            .map(|(column, c)| match c {
+                '#' => Ok(true),
+                '.' => Ok(false),
+                found => Err(LevelError::InvalidClimbableCell { row, column, found }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if cells.len() != width {
+            return Err(LevelError::ClimbableRowLength {
+                row,
+                expected: width,
+                found: cells.len(),
+            });
+        }
+        grid_climbable.push(cells);
+    }
+
+    let check = |what: &'static str, pos: (u8, u8)| {
+        if (pos.0 as usize) < width && (pos.1 as usize) < height {
+            Ok(())
+        } else {
+            Err(LevelError::OutOfBounds { what, pos })
+        }
+    };
+    check("start", file.start)?;
+    check("flag", file.flag)?;
+    if let Some(scale) = file.scale {
+        check("scale", scale)?;
+    }
+    for (index, cave) in file.caves.iter().enumerate() {
+        if cave.first_pos == cave.second_pos {
+            return Err(LevelError::SameSquareCave {
+                pos: cave.first_pos,
+            });
+        }
+        for pos in [cave.first_pos, cave.second_pos] {
+            check("cave", pos)?;
+            if file.caves[..index]
+                .iter()
+                .any(|other| other.other_end(pos).is_some())
+            {
+                return Err(LevelError::SharedCaveMouth { pos });
+            }
+        }
+    }
+    for (index, gem) in file.gems.iter().enumerate() {
+        check("gem", gem.pos)?;
+        // picking up one gem of a colour would pick up every other too
+        if file.gems[..index]
+            .iter()
+            .any(|other| other.colour == gem.colour)
+        {
+            return Err(LevelError::DuplicateGem { colour: gem.colour });
+        }
+    }
+
+    Ok(Level {
+        name: file.name,
+        map: Map::new(
+            file.heights,
+            grid_climbable,
+            file.start,
+            file.flag,
+            file.scale,
+            file.caves,
+            file.gems,
+        ),
+        stamina_budget: file.stamina_budget,
+        weight_budget: file.weight_budget,
+        ladder_unlocked: file.ladder_unlocked,
+        rope_unlocked: file.rope_unlocked,
+        potion_unlocked: file.potion_unlocked,
+        rewind_unlocked: file.rewind_unlocked,
+        par: file.par,
+        tutorial: file.tutorial,
+    })
+}
+
+/// Write a level in the RON level format, the reverse of [`parse_level`].
+pub fn level_to_ron(level: &Level) -> Result<String, ron::Error> {
+    let map = &level.map;
+    let file = LevelFile {
+        name: level.name.clone(),
+        heights: map.grid_heights.clone(),
+        climbable: map
+            .grid_climbable
+            .iter()
+            .map(|row| row.iter().map(|&c| if c { '#' } else { '.' }).collect())
+            .collect(),
+        start: map.player_start_pos,
+        flag: map.flag_pos,
+        scale: map.scale_pos,
+        caves: map.caves.clone(),
+        gems: map.gems.clone(),
+        stamina_budget: level.stamina_budget,
+        weight_budget: level.weight_budget,
+        ladder_unlocked: level.ladder_unlocked,
+        rope_unlocked: level.rope_unlocked,
+        potion_unlocked: level.potion_unlocked,
+        rewind_unlocked: level.rewind_unlocked,
+        par: level.par,
+        tutorial: level.tutorial.clone(),
+    };
+    // keep each row of the grids on its own line, like the hand-written levels
+    let config = ron::ser::PrettyConfig::new().depth_limit(2);
+    ron::ser::to_string_pretty(&file, config)
+}
+
+/// Read a campaign and its levels straight from disk, for when the asset server isn't running
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_campaign(manifest: &Path) -> Result<Vec<Level>, Box<dyn std::error::Error>> {
+    let campaign: CampaignFile = ron::de::from_bytes(&std::fs::read(manifest)?)?;
+    let directory = manifest.parent().unwrap_or_else(|| Path::new(""));
+    let mut levels = Vec::with_capacity(campaign.levels.len());
+    for file_name in campaign.levels {
+        let path = directory.join(file_name);
+        let level = parse_level(&std::fs::read(&path)?)
+            .map_err(|e| format!("level file {}: {}", path.display(), e))?;
+        levels.push(level);
+    }
+    Ok(levels)
+}
+
+#[derive(Default)]
+pub struct CampaignLoader;
+
+impl AssetLoader for CampaignLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let campaign: CampaignFile = ron::de::from_bytes(bytes)?;
+            // level files are listed relative to the manifest
+            let directory = load_context
+                .path()
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .to_path_buf();
+            let mut levels = Vec::with_capacity(campaign.levels.len());
+            for file_name in campaign.levels {
+                let path = directory.join(file_name);
+                let source = load_context.read_asset_bytes(&path).await?;
+                let level = parse_level(&source).map_err(|e| {
+                    bevy::asset::Error::msg(format!("level file {}: {}", path.display(), e))
+                })?;
+                levels.push(level);
+            }
+            load_context.set_default_asset(LoadedAsset::new(Campaign { levels }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["campaign.ron"]
+    }
+}
+
+pub fn init_level_manager(
+    mut commands: Commands,
+    level_assets: Res<LevelAssets>,
+    campaigns: Res<Assets<Campaign>>,
+) {
+    let campaign = campaigns
+        .get(&level_assets.campaign)
+        .expect("The campaign should be loaded before the main menu");
     commands.insert_resource(LevelManager {
         current: 0,
-        levels: vec![
-            // Intro level
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![7, 8, 8, 9, 8, 7],
-                        vec![6, 6, 6, 6, 5, 6],
-                        vec![6, 5, 5, 5, 5, 6],
-                        vec![4, 3, 3, 3, 3, 4],
-                        vec![1, 1, 1, 1, 1, 2],
-                    ],
-                    vec![
-                        vec![false, false, false, false, false, false],
-                        vec![false, false, false, false, false, false],
-                        vec![false, true, false, false, false, false],
-                        vec![false, false, false, false, true, false],
-                        vec![true, true, true, true, true, false],
-                    ],
-                    (0, 4),
-                    (4, 1),
-                    None,
-                    None,
-                ),
-                stamina_budget: 27,
-                weight_budget: 0,
-                ladder_unlocked: false,
-                rope_unlocked: false,
-                potion_unlocked: false,
-                rewind_unlocked: false,
-            },
-            // Introducing vertical ladders
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![4, 5, 5, 4, 5, 6],
-                        vec![3, 3, 3, 3, 3, 3],
-                        vec![1, 1, 1, 1, 1, 1],
-                    ],
-                    vec![
-                        vec![false, false, false, false, false, false],
-                        vec![true, true, true, true, true, true],
-                        vec![false, false, false, false, false, false],
-                    ],
-                    (0, 2),
-                    (4, 0),
-                    Some((2, 1)),
-                    None,
-                ),
-                stamina_budget: 8,
-                weight_budget: 4,
-                ladder_unlocked: true,
-                rope_unlocked: false,
-                potion_unlocked: false,
-                rewind_unlocked: false,
-            },
-            // Introducing horizontal ladders
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![4, 5, 6, 6, 5, 5],
-                        vec![3, 3, 1, 3, 3, 3],
-                        vec![1, 1, 1, 1, 1, 1],
-                    ],
-                    vec![
-                        vec![false, false, false, false, true, true],
-                        vec![false, false, false, false, false, false],
-                        vec![false, false, false, false, false, false],
-                    ],
-                    (0, 1),
-                    (4, 0),
-                    Some((5, 0)),
-                    None,
-                ),
-                stamina_budget: 13,
-                weight_budget: 2,
-                ladder_unlocked: true,
-                rope_unlocked: false,
-                potion_unlocked: false,
-                rewind_unlocked: false,
-            },
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![7, 7, 7, 7, 7, 8],
-                        vec![6, 6, 6, 6, 7, 5],
-                        vec![3, 1, 1, 3, 3, 3],
-                    ],
-                    vec![
-                        vec![false, false, false, false, false, false],
-                        vec![true, true, true, true, false, false],
-                        vec![false, false, false, false, false, false],
-                    ],
-                    (0, 2),
-                    (5, 1),
-                    None,
-                    None,
-                ),
-                stamina_budget: 10,
-                weight_budget: 2,
-                ladder_unlocked: true,
-                rope_unlocked: false,
-                potion_unlocked: false,
-                rewind_unlocked: false,
-            },
-            // Teaching about ladder reuse
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![4, 6, 4, 6, 6, 4],
-                        vec![4, 5, 2, 5, 5, 5],
-                        vec![3, 3, 1, 3, 3, 2],
-                    ],
-                    vec![
-                        vec![false, false, false, false, false, false],
-                        vec![false, false, false, false, false, false],
-                        vec![false, false, false, false, false, false],
-                    ],
-                    (0, 2),
-                    (5, 1),
-                    Some((3, 1)),
-                    None,
-                ),
-                stamina_budget: 7,
-                weight_budget: 2,
-                ladder_unlocked: true,
-                rope_unlocked: false,
-                potion_unlocked: false,
-                rewind_unlocked: false,
-            },
-            // Introducing rope
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![6, 6, 6, 6, 5, 5],
-                        vec![3, 4, 5, 4, 3, 2],
-                        vec![1, 2, 3, 4, 2, 2],
-                    ],
-                    vec![
-                        vec![true, true, true, true, true, true],
-                        vec![true, true, true, true, true, true],
-                        vec![true, true, true, true, true, true],
-                    ],
-                    (0, 0),
-                    (5, 2),
-                    Some((5, 1)),
-                    None,
-                ),
-                stamina_budget: 9,
-                weight_budget: 2,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: false,
-                rewind_unlocked: false,
-            },
-            // Introducing cave & potion
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![4, 5, 6, 7, 6, 5],
-                        vec![3, 4, 5, 5, 5, 3],
-                        vec![2, 2, 2, 1, 1, 1],
-                    ],
-                    vec![
-                        vec![true, true, true, true, true, true],
-                        vec![true, true, true, true, true, true],
-                        vec![true, true, true, true, true, true],
-                    ],
-                    (0, 2),
-                    (2, 1),
-                    None,
-                    Some(CaveData {
-                        first_pos: (4, 2),
-                        second_pos: (4, 1),
-                        gem_pos: None,
-                    }),
-                ),
-                stamina_budget: 6,
-                weight_budget: 2,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: false,
-            },
-            // Gem cave
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![4, 6, 7, 7, 5, 4],
-                        vec![3, 5, 6, 6, 4, 4],
-                        vec![2, 3, 5, 5, 3, 3],
-                        vec![1, 1, 2, 2, 2, 1],
-                    ],
-                    vec![
-                        vec![true, true, false, false, true, true],
-                        vec![true, true, false, false, false, true],
-                        vec![true, true, true, true, false, true],
-                        vec![true, true, true, true, true, true],
-                    ],
-                    (3, 3),
-                    (2, 1),
-                    Some((4, 1)),
-                    Some(CaveData {
-                        first_pos: (3, 3),
-                        second_pos: (3, 1),
-                        gem_pos: Some((1, 2)),
-                    }),
-                ),
-                stamina_budget: 15,
-                weight_budget: 3,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: false,
-            },
-            // Reusing ladder
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![5, 7, 6, 5, 4],
-                        vec![3, 5, 5, 4, 3],
-                        vec![1, 3, 1, 3, 1],
-                        vec![1, 1, 1, 1, 1],
-                    ],
-                    vec![
-                        vec![false, false, false, false, false],
-                        vec![false, false, false, false, false],
-                        vec![false, false, false, false, false],
-                        vec![false, false, false, false, false],
-                    ],
-                    (1, 3),
-                    (1, 1),
-                    None,
-                    Some(CaveData {
-                        first_pos: (3, 2),
-                        second_pos: (2, 1),
-                        gem_pos: Some((1, 2)),
-                    }),
-                ),
-                stamina_budget: 10,
-                weight_budget: 2,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: false,
-            },
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![7, 7, 7, 6],
-                        vec![6, 5, 5, 5],
-                        vec![3, 3, 3, 3],
-                        vec![2, 1, 1, 1],
-                    ],
-                    vec![
-                        vec![true, true, true, true],
-                        vec![false, false, false, false],
-                        vec![false, false, false, false],
-                        vec![true, true, true, true],
-                    ],
-                    (1, 3),
-                    (2, 0),
-                    Some((0, 0)),
-                    None,
-                ),
-                stamina_budget: 19,
-                weight_budget: 3,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: false,
-            },
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![4, 1, 4, 5, 6, 5],
-                        vec![2, 1, 3, 2, 4, 4],
-                        vec![4, 2, 2, 2, 3, 2],
-                        vec![2, 2, 1, 1, 1, 1],
-                    ],
-                    vec![
-                        vec![false, false, false, false, false, false],
-                        vec![false, false, false, false, true, false],
-                        vec![false, false, false, false, false, false],
-                        vec![false, false, false, false, false, false],
-                    ],
-                    (0, 0),
-                    (5, 1),
-                    Some((4, 2)),
-                    Some(CaveData {
-                        first_pos: (2, 2),
-                        second_pos: (4, 1),
-                        gem_pos: Some((2, 0)),
-                    }),
-                ),
-                stamina_budget: 12,
-                weight_budget: 4,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: false,
-            },
-            // Introducing rewind rune
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![6, 7, 6, 5],
-                        vec![4, 5, 4, 4],
-                        vec![3, 3, 3, 3],
-                        vec![1, 1, 1, 1],
-                    ],
-                    vec![
-                        vec![false, false, false, false],
-                        vec![false, false, false, false],
-                        vec![false, false, false, false],
-                        vec![false, false, false, false],
-                    ],
-                    (1, 3),
-                    (1, 1),
-                    Some((2, 3)),
-                    None,
-                ),
-                stamina_budget: 8,
-                weight_budget: 3,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: true,
-            },
-            Level {
-                map: Map::new(
-                    vec![vec![6, 7, 7], vec![4, 5, 4], vec![4, 4, 2], vec![1, 1, 1]],
-                    vec![
-                        vec![true, true, true],
-                        vec![true, true, true],
-                        vec![true, true, true],
-                        vec![true, true, true],
-                    ],
-                    (1, 3),
-                    (2, 0),
-                    None,
-                    None,
-                ),
-                stamina_budget: 16,
-                weight_budget: 2,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: true,
-            },
-            // Difficult cave
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![4, 5, 5, 7, 7, 6],
-                        vec![4, 4, 1, 5, 3, 5],
-                        vec![2, 3, 1, 1, 3, 2],
-                    ],
-                    vec![
-                        vec![true, true, true, false, false, false],
-                        vec![true, true, true, false, false, false],
-                        vec![true, true, true, true, false, false],
-                    ],
-                    (0, 2),
-                    (3, 1),
-                    Some((4, 2)),
-                    Some(CaveData {
-                        first_pos: (3, 2),
-                        second_pos: (5, 1),
-                        gem_pos: None,
-                    }),
-                ),
-                stamina_budget: 11,
-                weight_budget: 4,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: true,
-            },
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![2, 2, 5, 7, 7, 5],
-                        vec![2, 3, 5, 5, 3, 3],
-                        vec![2, 1, 1, 1, 2, 2],
-                    ],
-                    vec![
-                        vec![true, true, true, true, true, true],
-                        vec![true, true, true, true, true, true],
-                        vec![true, true, true, true, true, true],
-                    ],
-                    (3, 2),
-                    (4, 0),
-                    Some((1, 0)),
-                    None,
-                ),
-                stamina_budget: 11,
-                weight_budget: 4,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: true,
-            },
-            Level {
-                map: Map::new(
-                    vec![
-                        vec![5, 6, 8, 7, 6, 4],
-                        vec![4, 4, 7, 7, 5, 2],
-                        vec![3, 3, 4, 2, 4, 2],
-                        vec![2, 3, 2, 3, 3, 2],
-                        vec![1, 2, 1, 2, 2, 2],
-                    ],
-                    vec![
-                        vec![true, true, false, false, true, true],
-                        vec![true, true, false, false, false, true],
-                        vec![true, true, true, false, false, true],
-                        vec![true, true, true, false, false, true],
-                        vec![true, true, true, true, true, true],
-                    ],
-                    (2, 2),
-                    (3, 0),
-                    Some((5, 1)),
-                    Some(CaveData {
-                        first_pos: (4, 4),
-                        second_pos: (2, 1),
-                        gem_pos: Some((0, 3)),
-                    }),
-                ),
-                stamina_budget: 11,
-                weight_budget: 5,
-                ladder_unlocked: true,
-                rope_unlocked: true,
-                potion_unlocked: true,
-                rewind_unlocked: true,
-            },
-        ],
-    })
+        levels: campaign.levels.clone(),
+        generated: None,
+        daily: None,
+        playtest: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+
+    #[test]
+    fn every_campaign_level_survives_a_round_trip() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/levels");
+        let campaign: CampaignFile =
+            ron::de::from_bytes(&fs::read(directory.join("main.campaign.ron")).unwrap()).unwrap();
+        for file_name in campaign.levels {
+            let level = parse_level(&fs::read(directory.join(&file_name)).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", file_name, e));
+            let written = level_to_ron(&level).unwrap();
+            let reread = parse_level(written.as_bytes()).unwrap();
+            assert_eq!(level_to_ron(&reread).unwrap(), written, "{}", file_name);
+        }
+    }
+
+    #[test]
+    fn playtests_leave_the_campaign_alone() {
+        let level = |name: &str| {
+            let source = format!(
+                r#"(
+                    name: "{}",
+                    heights: [[1, 1]],
+                    climbable: [".."],
+                    start: (0, 0),
+                    flag: (1, 0),
+                    stamina_budget: 5,
+                )"#,
+                name
+            );
+            parse_level(source.as_bytes()).unwrap()
+        };
+        let mut level_manager = LevelManager {
+            levels: vec![level("Campaign")],
+            ..Default::default()
+        };
+        level_manager.playtest = Some(level("Edited"));
+        assert_eq!(level_manager.get_current_level().name, "Edited");
+
+        level_manager.playtest = None;
+        assert_eq!(level_manager.get_current_level().name, "Campaign");
+    }
+
+    #[test]
+    fn broken_levels_are_rejected() {
+        let ragged = br#"(
+            heights: [[1, 1], [1]],
+            climbable: ["..", "."],
+            start: (0, 0),
+            flag: (1, 0),
+            stamina_budget: 5,
+        )"#;
+        assert!(matches!(
+            parse_level(ragged),
+            Err(LevelError::RaggedHeights { row: 1, .. })
+        ));
+
+        let flag_off_the_map = br#"(
+            heights: [[1, 1]],
+            climbable: [".#"],
+            start: (0, 0),
+            flag: (2, 0),
+            stamina_budget: 5,
+        )"#;
+        assert!(matches!(
+            parse_level(flag_off_the_map),
+            Err(LevelError::OutOfBounds { what: "flag", .. })
+        ));
+
+        let two_red_gems = br#"(
+            heights: [[1, 1, 1]],
+            climbable: ["..."],
+            start: (0, 0),
+            flag: (2, 0),
+            gems: [(pos: (1, 0)), (pos: (2, 0), colour: Red)],
+            stamina_budget: 5,
+        )"#;
+        assert!(matches!(
+            parse_level(two_red_gems),
+            Err(LevelError::DuplicateGem {
+                colour: GemColour::Red
+            })
+        ));
+
+        let crossed_caves = br#"(
+            heights: [[1, 1, 1]],
+            climbable: ["..."],
+            start: (0, 0),
+            flag: (2, 0),
+            caves: [
+                (first_pos: (0, 0), second_pos: (1, 0)),
+                (first_pos: (2, 0), second_pos: (1, 0), entrance: South),
+            ],
+            stamina_budget: 5,
+        )"#;
+        assert!(matches!(
+            parse_level(crossed_caves),
+            Err(LevelError::SharedCaveMouth { pos: (1, 0) })
+        ));
+
+        let cave_to_itself = br#"(
+            heights: [[1, 1, 1]],
+            climbable: ["..."],
+            start: (0, 0),
+            flag: (2, 0),
+            caves: [(first_pos: (1, 0), second_pos: (1, 0))],
+            stamina_budget: 5,
+        )"#;
+        assert!(matches!(
+            parse_level(cave_to_itself),
+            Err(LevelError::SameSquareCave { pos: (1, 0) })
+        ));
+    }
 }
@@ -0,0 +1,418 @@
+//! The game's text in each language, loaded from `assets/locales`, and the language picked in the
+//! settings. Text is looked up by key, such as `menu.start`, and `{0}`, `{1}` and so on in the
+//! text are filled in with values passed along with the key, so translations can reorder them.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    settings::Settings,
+    states::{loading::LocaleAssets, GameState},
+};
+
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Localization>()
+            .add_systems(OnExit(GameState::Loading), init_localization)
+            .add_systems(
+                Update,
+                (
+                    pick_language.run_if(resource_changed::<Settings>()),
+                    localize_texts.run_if(resource_changed::<Localization>()),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The code of the pseudo-locale, which is English with every letter accented and padded out to
+/// be longer, in brackets. Text that runs out of its box shows up as a missing bracket.
+pub const PSEUDO_LOCALE: &str = "pseudo";
+
+/// Every language's text, loaded from a `.locales.ron` manifest
+#[derive(Debug, TypeUuid, TypePath)]
+#[uuid = "5b0c7d0e-2f7a-4c1e-8d57-3e6f0e2a9b44"]
+pub struct Locales {
+    pub tables: Vec<LocaleTable>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LocalesFile {
+    locales: Vec<String>,
+}
+
+/// One language's text, as written in `assets/locales/*.ron`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LocaleTable {
+    /// The file's name without the extension, such as `en`
+    #[serde(skip)]
+    pub code: String,
+    /// The language's name in that language
+    pub name: String,
+    pub strings: HashMap<String, String>,
+}
+
+fn parse_locale(code: &str, bytes: &[u8]) -> Result<LocaleTable, ron::error::SpannedError> {
+    let mut table: LocaleTable = ron::de::from_bytes(bytes)?;
+    table.code = code.to_string();
+    Ok(table)
+}
+
+fn locale_code(file_name: &str) -> &str {
+    file_name.strip_suffix(".ron").unwrap_or(file_name)
+}
+
+#[derive(Default)]
+pub struct LocalesLoader;
+
+impl AssetLoader for LocalesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest: LocalesFile = ron::de::from_bytes(bytes)?;
+            // locale files are listed relative to the manifest
+            let directory = load_context
+                .path()
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .to_path_buf();
+            let mut tables = Vec::with_capacity(manifest.locales.len());
+            for file_name in manifest.locales {
+                let path = directory.join(&file_name);
+                let source = load_context.read_asset_bytes(&path).await?;
+                let table = parse_locale(locale_code(&file_name), &source).map_err(|e| {
+                    bevy::asset::Error::msg(format!("locale file {}: {}", path.display(), e))
+                })?;
+                tables.push(table);
+            }
+            load_context.set_default_asset(LoadedAsset::new(Locales { tables }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["locales.ron"]
+    }
+}
+
+/// The text of the language in use. Until the locales are loaded, every key stands for itself.
+#[derive(Debug, Default, Resource)]
+pub struct Localization {
+    /// The first table is English, which stands in for anything missing from the others
+    tables: Vec<LocaleTable>,
+    language: String,
+}
+
+impl Localization {
+    pub fn new(tables: Vec<LocaleTable>, language: &str) -> Self {
+        let mut localization = Self {
+            tables,
+            language: String::new(),
+        };
+        localization.set_language(language);
+        localization
+    }
+
+    /// The code of the language in use
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Switch language, falling back to English for one that isn't there
+    pub fn set_language(&mut self, code: &str) {
+        let known = code == PSEUDO_LOCALE || self.tables.iter().any(|table| table.code == code);
+        self.language = if known {
+            code.to_string()
+        } else {
+            self.tables
+                .first()
+                .map(|table| table.code.clone())
+                .unwrap_or_default()
+        };
+    }
+
+    /// The codes of every language, ending with the pseudo-locale
+    fn codes(&self) -> impl Iterator<Item = &str> {
+        self.tables
+            .iter()
+            .map(|table| table.code.as_str())
+            .chain([PSEUDO_LOCALE])
+    }
+
+    /// The language after the given one, wrapping back round to the first
+    pub fn next_language(&self, code: &str) -> String {
+        let codes: Vec<&str> = self.codes().collect();
+        let index = codes.iter().position(|&other| other == code);
+        let next = index.map_or(0, |index| (index + 1) % codes.len());
+        codes[next].to_string()
+    }
+
+    /// What a language calls itself
+    pub fn language_name(&self, code: &str) -> String {
+        match self.tables.iter().find(|table| table.code == code) {
+            Some(table) => table.name.clone(),
+            None if code == PSEUDO_LOCALE => "Pseudo-locale".to_string(),
+            None => code.to_string(),
+        }
+    }
+
+    /// The text for a key, in English if the language doesn't have it, or the key itself if
+    /// English doesn't either
+    fn template(&self, key: &str) -> String {
+        let lookup = |table: &LocaleTable| table.strings.get(key).cloned();
+        let current = self
+            .tables
+            .iter()
+            .find(|table| table.code == self.language)
+            .and_then(lookup);
+        let text = current
+            .or_else(|| self.tables.first().and_then(lookup))
+            .unwrap_or_else(|| key.to_string());
+        if self.language == PSEUDO_LOCALE {
+            pseudo(&text)
+        } else {
+            text
+        }
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.template(key)
+    }
+
+    /// The text for a key with the values filled in
+    pub fn format(&self, key: &str, args: &[&dyn fmt::Display]) -> String {
+        let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+        fill(&self.template(key), &args)
+    }
+
+    fn localize(&self, localized: &Localized) -> String {
+        fill(&self.template(&localized.key), &localized.args)
+    }
+
+    /// A text bundle for a key, which is looked up again whenever the language changes
+    pub fn text(&self, localized: Localized, style: TextStyle) -> (TextBundle, Localized) {
+        (
+            TextBundle::from_section(self.localize(&localized), style),
+            localized,
+        )
+    }
+}
+
+/// Put the values in place of `{0}`, `{1}` and so on. Anything else in braces is left alone.
+fn fill(template: &str, args: &[String]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let arg = rest.find('}').and_then(|end| {
+            let index: usize = rest[1..end].parse().ok()?;
+            Some((args.get(index)?, end))
+        });
+        match arg {
+            Some((arg, end)) => {
+                text.push_str(arg);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Accent every letter and pad the text out by about 40%, which is as much longer as
+/// translations tend to run, keeping the `{0}` placeholders as they are
+pub fn pseudo(text: &str) -> String {
+    let mut accented = String::from("[");
+    let mut in_placeholder = false;
+    let mut letters = 0usize;
+    for c in text.chars() {
+        match c {
+            '{' => in_placeholder = true,
+            '}' => in_placeholder = false,
+            _ => {}
+        }
+        if in_placeholder || !c.is_alphabetic() {
+            accented.push(c);
+            continue;
+        }
+        letters += 1;
+        accented.push(match c {
+            'a' => 'á',
+            'c' => 'ç',
+            'e' => 'é',
+            'i' => 'í',
+            'n' => 'ñ',
+            'o' => 'ö',
+            'u' => 'ü',
+            'y' => 'ý',
+            'A' => 'Å',
+            'C' => 'Ç',
+            'E' => 'É',
+            'I' => 'Î',
+            'N' => 'Ñ',
+            'O' => 'Ø',
+            'U' => 'Ü',
+            'Y' => 'Ý',
+            other => other,
+        });
+    }
+    if letters > 0 {
+        accented.push(' ');
+        accented.extend(std::iter::repeat_n('~', (letters * 2).div_ceil(5)));
+    }
+    accented.push(']');
+    accented
+}
+
+/// Text to look up again when the language changes. Only the first section is replaced, so
+/// further sections can hold values that are kept up to date elsewhere.
+#[derive(Debug, Clone, Component)]
+pub struct Localized {
+    pub key: String,
+    pub args: Vec<String>,
+}
+
+impl Localized {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn with_args(key: &str, args: &[&dyn fmt::Display]) -> Self {
+        Self {
+            key: key.to_string(),
+            args: args.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+fn init_localization(
+    mut commands: Commands,
+    locale_assets: Res<LocaleAssets>,
+    locales: Res<Assets<Locales>>,
+    settings: Res<Settings>,
+) {
+    let tables = locales
+        .get(&locale_assets.locales)
+        .expect("The locales should be loaded before the main menu")
+        .tables
+        .clone();
+    commands.insert_resource(Localization::new(tables, &settings.data.language));
+}
+
+fn pick_language(settings: Res<Settings>, mut localization: ResMut<Localization>) {
+    if localization.language() != settings.data.language {
+        localization.set_language(&settings.data.language);
+    }
+}
+
+fn localize_texts(localization: Res<Localization>, mut texts: Query<(&Localized, &mut Text)>) {
+    for (localized, mut text) in &mut texts {
+        text.sections[0].value = localization.localize(localized);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn read_locales() -> Vec<LocaleTable> {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/locales");
+        let manifest: LocalesFile =
+            ron::de::from_bytes(&fs::read(directory.join("all.locales.ron")).unwrap()).unwrap();
+        manifest
+            .locales
+            .iter()
+            .map(|file_name| {
+                parse_locale(
+                    locale_code(file_name),
+                    &fs::read(directory.join(file_name)).unwrap(),
+                )
+                .unwrap_or_else(|e| panic!("{}: {}", file_name, e))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_locale_has_every_key() {
+        let tables = read_locales();
+        let english = &tables[0];
+        assert_eq!(english.code, "en");
+        for table in &tables[1..] {
+            for key in english.strings.keys() {
+                assert!(table.strings.contains_key(key), "{}: {}", table.code, key);
+            }
+            for key in table.strings.keys() {
+                assert!(english.strings.contains_key(key), "{}: {}", table.code, key);
+            }
+        }
+    }
+
+    #[test]
+    fn values_are_filled_in_any_order() {
+        let args = ["3".to_string(), "Z".to_string()];
+        assert_eq!(
+            fill("Undo {0} moves with {1}.", &args),
+            "Undo 3 moves with Z."
+        );
+        assert_eq!(fill("{1}: {0}", &args), "Z: 3");
+        assert_eq!(fill("{2} {x} {", &args), "{2} {x} {");
+    }
+
+    #[test]
+    fn missing_text_falls_back_to_english() {
+        let table = |code: &str, strings: &[(&str, &str)]| LocaleTable {
+            code: code.to_string(),
+            name: code.to_string(),
+            strings: strings
+                .iter()
+                .map(|&(key, text)| (key.to_string(), text.to_string()))
+                .collect(),
+        };
+        let tables = vec![
+            table("en", &[("a", "Apple"), ("b", "Banana")]),
+            table("de", &[("a", "Apfel")]),
+        ];
+        let mut localization = Localization::new(tables, "de");
+        assert_eq!(localization.get("a"), "Apfel");
+        assert_eq!(localization.get("b"), "Banana");
+        assert_eq!(localization.get("c"), "c");
+
+        localization.set_language("xx");
+        assert_eq!(localization.language(), "en");
+        assert_eq!(localization.next_language("en"), "de");
+        assert_eq!(localization.next_language("de"), PSEUDO_LOCALE);
+        assert_eq!(localization.next_language(PSEUDO_LOCALE), "en");
+    }
+
+    #[test]
+    fn pseudo_text_is_longer_and_keeps_placeholders() {
+        let text = pseudo("Undo {0} moves");
+        assert_eq!(text, "[Üñdö {0} mövés ~~~~]");
+        assert_eq!(fill(&text, &["2".to_string()]), "[Üñdö 2 mövés ~~~~]");
+        assert_eq!(pseudo("-"), "[-]");
+    }
+}
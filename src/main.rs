@@ -4,14 +4,27 @@
 // Feel free to delete this line.
 #![allow(clippy::too_many_arguments, clippy::type_complexity)]
 
-use audio::{MusicChannel, SoundChannel, VolumeSettings};
+use audio::{MusicChannel, SoundChannel};
 use bevy::prelude::*;
 use bevy_kira_audio::{AudioApp, AudioPlugin};
 use clouds::CloudMaterial;
+use controls::ControlsPlugin;
+use daily::DailyPlugin;
 use equipment::EquipmentPlugin;
+use localization::LocalizationPlugin;
 use player::PlayerPlugin;
+use pointer::PointerPlugin;
 use post_process::PostProcessPlugin;
-use states::{level::LevelPlugin, loading::LoadingPlugin, menu::MenuPlugin};
+use replay::ReplayPlugin;
+use save::SavePlugin;
+use settings::SettingsPlugin;
+use states::{
+    controls::ControlsScreenPlugin, editor::LevelEditorPlugin, level::LevelPlugin,
+    level_select::LevelSelectPlugin, loading::LoadingPlugin, menu::MenuPlugin, pause::PausePlugin,
+    settings::SettingsScreenPlugin,
+};
+use stats::StatsPlugin;
+use tutorial::TutorialPlugin;
 use ui::{constants::SKY_BLUE, UiPlugin};
 
 #[cfg(debug_assertions)]
@@ -21,42 +34,80 @@ mod audio;
 mod camera;
 mod cave;
 mod clouds;
+mod controls;
+mod daily;
 mod equipment;
+mod generator;
 mod level_manager;
+mod localization;
 mod map;
 mod player;
+mod pointer;
 mod post_process;
+mod rating;
+mod replay;
+mod rules;
+mod save;
 mod scale;
+mod settings;
+mod solver;
 mod states;
+mod stats;
+#[cfg(test)]
+mod testing;
+mod tutorial;
 mod ui;
 mod undo;
 mod util;
 
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins,
-            AudioPlugin,
-            MaterialPlugin::<CloudMaterial>::default(),
-            LoadingPlugin,
-            MenuPlugin,
-            LevelPlugin,
-            PlayerPlugin,
-            EquipmentPlugin,
-            UiPlugin,
-            PostProcessPlugin,
-            #[cfg(debug_assertions)]
-            EditorPlugin::default(),
-        ))
-        .add_state::<states::GameState>()
-        .insert_resource(ClearColor(SKY_BLUE))
-        .insert_resource(AmbientLight {
-            brightness: 1.0,
-            ..Default::default()
-        })
-        .insert_resource(Msaa::Sample4)
-        .add_audio_channel::<MusicChannel>()
-        .add_audio_channel::<SoundChannel>()
-        .insert_resource(VolumeSettings::default())
-        .run();
+    // checking a replay headless exits here, before a window is opened
+    #[cfg(not(target_arch = "wasm32"))]
+    let playback = replay::playback_from_args();
+
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins,
+        AudioPlugin,
+        MaterialPlugin::<CloudMaterial>::default(),
+        LoadingPlugin,
+        MenuPlugin,
+        LevelSelectPlugin,
+        ControlsPlugin,
+        ControlsScreenPlugin,
+        LevelPlugin,
+        PlayerPlugin,
+        EquipmentPlugin,
+        UiPlugin,
+        PostProcessPlugin,
+        #[cfg(debug_assertions)]
+        EditorPlugin::default(),
+    ))
+    // plugins only go fifteen to a tuple
+    .add_plugins((
+        SavePlugin,
+        LevelEditorPlugin,
+        ReplayPlugin,
+        PointerPlugin,
+        DailyPlugin,
+        StatsPlugin,
+        SettingsPlugin,
+        SettingsScreenPlugin,
+        PausePlugin,
+        LocalizationPlugin,
+        TutorialPlugin,
+    ))
+    .add_state::<states::GameState>()
+    .insert_resource(ClearColor(SKY_BLUE))
+    .insert_resource(AmbientLight {
+        brightness: 1.0,
+        ..Default::default()
+    })
+    .add_audio_channel::<MusicChannel>()
+    .add_audio_channel::<SoundChannel>();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(playback) = playback {
+        app.insert_resource(playback);
+    }
+    app.run();
 }
@@ -1,118 +1,19 @@
-use std::collections::HashMap;
-
 use bevy::prelude::*;
 
 use crate::{
-    cave::{spawn_gem, Cave, CaveData, GemCave, HasGem},
-    equipment::{
-        ladder::{HorizontalLadderKey, VerticalLadderKey},
-        rope::RopeKey,
-    },
+    cave::{spawn_cave, spawn_gem, HeldGems},
     level_manager::LevelManager,
+    rules::Map,
     states::{level::DespawnOnTransition, loading::ModelAssets},
-    util::CardinalDirection,
 };
 
-#[derive(Debug, Resource, Reflect)]
-pub struct Map {
-    pub grid_heights: Vec<Vec<u8>>,
-    pub grid_climbable: Vec<Vec<bool>>,
-    pub player_start_pos: (u8, u8),
-    pub flag_pos: (u8, u8),
-    pub scale_pos: Option<(u8, u8)>,
-    pub vertical_ladders: HashMap<VerticalLadderKey, Entity>,
-    pub horizontal_ladders: HashMap<HorizontalLadderKey, Entity>,
-    pub ropes: HashMap<RopeKey, Entity>,
-    pub cave_data: Option<CaveData>,
-}
-impl Map {
-    pub fn new(
-        grid_heights: Vec<Vec<u8>>,
-        grid_climbable: Vec<Vec<bool>>,
-        player_pos: (u8, u8),
-        flag_pos: (u8, u8),
-        scale_pos: Option<(u8, u8)>,
-        cave_data: Option<CaveData>,
-    ) -> Self {
-        Self {
-            grid_heights,
-            grid_climbable,
-            player_start_pos: player_pos,
-            flag_pos,
-            scale_pos,
-            vertical_ladders: HashMap::new(),
-            horizontal_ladders: HashMap::new(),
-            ropes: HashMap::new(),
-            cave_data,
-        }
-    }
-    pub fn is_ladder_or_rope(
-        &self,
-        x: u8,
-        y: u8,
-        height: u8,
-        direction: CardinalDirection,
-    ) -> bool {
-        let (x_offset, y_offset) = match direction {
-            CardinalDirection::North => (0, -1),
-            CardinalDirection::East => (1, 0),
-            CardinalDirection::South => (0, 1),
-            CardinalDirection::West => (-1, 0),
-        };
-        // grid square directly in front of the player (might be out of bounds)
-        let grid_facing_x: u8 = match (x as i16 + x_offset).try_into() {
-            Ok(x) => x,
-            Err(_) => return false,
-        };
-        let grid_facing_y: u8 = match (y as i16 + y_offset).try_into() {
-            Ok(x) => x,
-            Err(_) => return false,
-        };
-        self.vertical_ladders.contains_key(&VerticalLadderKey {
-            x: grid_facing_x,
-            y: grid_facing_y,
-            height: height - 2,
-            direction,
-        }) || self.vertical_ladders.contains_key(&VerticalLadderKey {
-            x: grid_facing_x,
-            y: grid_facing_y,
-            height: height - 1,
-            direction,
-        }) || self.horizontal_ladders.contains_key(&HorizontalLadderKey {
-            x,
-            y,
-            height,
-            alignment: direction.into(),
-        }) || self.ropes.contains_key(&RopeKey {
-            x: grid_facing_x,
-            y: grid_facing_y,
-            direction: direction.reverse(),
-        })
-    }
-
-    pub fn reset(&mut self) {
-        self.vertical_ladders = HashMap::new();
-        self.horizontal_ladders = HashMap::new();
-        self.ropes = HashMap::new();
-    }
-
-    pub fn midpoint(&self) -> (f32, f32) {
-        (
-            self.grid_heights[0].len() as f32 / 2.0,
-            self.grid_heights.len() as f32 / 2.0,
-        )
-    }
-}
-
-pub fn create_map_on_level_load(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    model_assets: Res<ModelAssets>,
-    level_manager: Res<LevelManager>,
-    mut has_gem: ResMut<HasGem>,
+/// Spawn a column for every grid square, lighter where it can be climbed
+pub fn spawn_terrain(
+    commands: &mut Commands,
+    materials: &mut Assets<StandardMaterial>,
+    meshes: &mut Assets<Mesh>,
+    map: &Map,
 ) {
-    let map = &level_manager.get_current_level().map;
     for y in 0..map.grid_heights.len() {
         for x in 0..map.grid_heights[0].len() {
             let colour = if map.grid_climbable[y][x] {
@@ -141,63 +42,103 @@ pub fn create_map_on_level_load(
                 .insert(DespawnOnTransition);
         }
     }
-    if let Some(cave_data) = &map.cave_data {
-        let mut no_gem_visibility = Visibility::Hidden;
-        let mut yes_gem_visibility = Visibility::Hidden;
-        if let Some((x, y)) = cave_data.gem_pos {
-            yes_gem_visibility = Visibility::Visible;
-            has_gem.0 = false;
-            let height = map.grid_heights[y as usize][x as usize] as f32 + 0.3;
-            spawn_gem(&mut commands, x, y, height, model_assets.gem.clone());
-        } else {
-            no_gem_visibility = Visibility::Visible;
-            has_gem.0 = true;
+}
+
+/// The grid square whose column of terrain a ray hits first, used to work out which square was
+/// clicked on
+pub fn pick_cell(map: &Map, ray: Ray) -> Option<(u8, u8)> {
+    let mut nearest: Option<(f32, (u8, u8))> = None;
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let min = Vec3::new(x as f32 - 0.5, 0.0, y as f32 - 0.5);
+            let max = Vec3::new(
+                x as f32 + 0.5,
+                map.grid_heights[y][x] as f32,
+                y as f32 + 0.5,
+            );
+            if let Some(distance) = ray_box_distance(ray, min, max) {
+                if nearest.is_none_or(|(nearest, _)| distance < nearest) {
+                    nearest = Some((distance, (x as u8, y as u8)));
+                }
+            }
         }
+    }
+    nearest.map(|(_, cell)| cell)
+}
+
+/// How far along the ray it enters the box, if it hits it at all
+fn ray_box_distance(ray: Ray, min: Vec3, max: Vec3) -> Option<f32> {
+    let inverse = ray.direction.recip();
+    let to_min = (min - ray.origin) * inverse;
+    let to_max = (max - ray.origin) * inverse;
+    let enter = to_min.min(to_max).max_element();
+    let exit = to_min.max(to_max).min_element();
+    (enter <= exit && exit >= 0.0).then_some(enter.max(0.0))
+}
+
+pub fn create_map_on_level_load(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    model_assets: Res<ModelAssets>,
+    level_manager: Res<LevelManager>,
+    mut held_gems: ResMut<HeldGems>,
+) {
+    let map = &level_manager.get_current_level().map;
+    spawn_terrain(&mut commands, &mut materials, &mut meshes, map);
+    held_gems.0.clear();
+    for gem in &map.gems {
+        let (x, y) = gem.pos;
+        let height = map.grid_heights[y as usize][x as usize] as f32 + 0.3;
+        spawn_gem(
+            &mut commands,
+            x,
+            y,
+            height,
+            gem.colour,
+            model_assets.gem.clone(),
+        );
+    }
+    for cave in &map.caves {
+        spawn_cave(&mut commands, map, cave, &model_assets);
+    }
+}
 
-        let x = cave_data.first_pos.0 as usize;
-        let y = cave_data.first_pos.1 as usize;
-        commands
-            .spawn(SceneBundle {
-                scene: model_assets.cave1.clone(),
-                transform: Transform::from_xyz(x as f32, map.grid_heights[y][x] as f32, y as f32)
-                    .with_rotation(Quat::from_rotation_y(-1.571)),
-                visibility: no_gem_visibility,
-                ..Default::default()
-            })
-            .insert(DespawnOnTransition)
-            .insert(Cave);
-        commands
-            .spawn(SceneBundle {
-                scene: model_assets.cave2.clone(),
-                transform: Transform::from_xyz(x as f32, map.grid_heights[y][x] as f32, y as f32)
-                    .with_rotation(Quat::from_rotation_y(-1.571)),
-                visibility: yes_gem_visibility,
-                ..Default::default()
-            })
-            .insert(GemCave)
-            .insert(DespawnOnTransition);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let x = cave_data.second_pos.0 as usize;
-        let y = cave_data.second_pos.1 as usize;
-        commands
-            .spawn(SceneBundle {
-                scene: model_assets.cave1.clone(),
-                transform: Transform::from_xyz(x as f32, map.grid_heights[y][x] as f32, y as f32)
-                    .with_rotation(Quat::from_rotation_y(-1.571)),
-                visibility: no_gem_visibility,
-                ..Default::default()
-            })
-            .insert(DespawnOnTransition)
-            .insert(Cave);
-        commands
-            .spawn(SceneBundle {
-                scene: model_assets.cave2.clone(),
-                transform: Transform::from_xyz(x as f32, map.grid_heights[y][x] as f32, y as f32)
-                    .with_rotation(Quat::from_rotation_y(-1.571)),
-                visibility: yes_gem_visibility,
-                ..Default::default()
-            })
-            .insert(GemCave)
-            .insert(DespawnOnTransition);
+    #[test]
+    fn clicking_straight_down() {
+        let map = Map::from_heights(&[&[1, 2], &[3, 4]], &[]);
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let ray = Ray {
+                origin: Vec3::new(x as f32, 10.0, y as f32),
+                direction: Vec3::NEG_Y,
+            };
+            assert_eq!(pick_cell(&map, ray), Some((x, y)));
+        }
+        let beside = Ray {
+            origin: Vec3::new(3.0, 10.0, 0.0),
+            direction: Vec3::NEG_Y,
+        };
+        assert_eq!(pick_cell(&map, beside), None);
+    }
+
+    #[test]
+    fn taller_columns_are_in_the_way() {
+        // looking north along the ground, the tall column at the front hides the ones behind
+        let map = Map::from_heights(&[&[1], &[1], &[5]], &[]);
+        let low = Ray {
+            origin: Vec3::new(0.0, 0.5, 10.0),
+            direction: Vec3::NEG_Z,
+        };
+        assert_eq!(pick_cell(&map, low), Some((0, 2)));
+        // looking steeply down, over the top of it
+        let high = Ray {
+            origin: Vec3::new(0.0, 11.0, 2.0),
+            direction: Vec3::new(0.0, -10.0, -1.0).normalize(),
+        };
+        assert_eq!(pick_cell(&map, high), Some((0, 1)));
     }
 }
@@ -0,0 +1,226 @@
+//! Playing with the mouse or a touch screen: tapping a square next to the climber moves there,
+//! and tapping one further away shows the cheapest way there, which a second tap (or holding
+//! the first) walks.
+
+use std::collections::VecDeque;
+
+use bevy::{input::touch::Touch, prelude::*, ui::UiSystem, window::PrimaryWindow};
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    camera::MainCamera,
+    controls::{Control, PointerPresses},
+    map::pick_cell,
+    replay::Playback,
+    rules::{Action, CardinalDirection, Puzzle, PuzzleState},
+    solver::route,
+    states::{level::DespawnOnTransition, GameState, PauseState},
+    ui::{constants::UI_YELLOW, touch::press_control_buttons},
+};
+
+pub struct PointerPlugin;
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Walk>()
+            .add_systems(
+                PreUpdate,
+                (read_pointer, walk_route)
+                    .chain()
+                    .after(UiSystem::Focus)
+                    .after(press_control_buttons)
+                    .run_if(in_state(GameState::Level))
+                    .run_if(in_state(PauseState::Playing))
+                    .run_if(not(resource_exists::<Playback>())),
+            )
+            .add_systems(
+                Update,
+                clear_stale_route
+                    .run_if(resource_changed::<Puzzle>())
+                    .run_if(in_state(GameState::Level)),
+            )
+            .add_systems(OnExit(GameState::Level), stop_walking);
+    }
+}
+
+/// How long a press has to be held to walk straight to a square, rather than be shown the way
+const LONG_PRESS_SECONDS: f32 = 0.4;
+/// Time between the steps of a walk
+const STEP_SECONDS: f32 = 0.2;
+
+/// A press of the mouse or a finger on the terrain, which counts as a tap if let go quickly
+struct Press {
+    position: Vec2,
+    held: f32,
+}
+
+/// The route to a tapped square, which is shown before it is walked one step at a time
+#[derive(Debug, Default, Resource)]
+pub struct Walk {
+    target: Option<(u8, u8)>,
+    moves: VecDeque<CardinalDirection>,
+    walking: bool,
+    /// Where things should stand before the next step. Anything else, like an undo or a key
+    /// press, stops the walk.
+    expected: Option<PuzzleState>,
+    timer: Timer,
+}
+
+/// Marks a square on the route shown
+#[derive(Component)]
+struct RouteMarker;
+
+fn read_pointer(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mouse_input: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    interactions: Query<&Interaction>,
+    markers: Query<Entity, With<RouteMarker>>,
+    time: Res<Time>,
+    puzzle: Res<Puzzle>,
+    mut walk: ResMut<Walk>,
+    mut press: Local<Option<Press>>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    let started = if mouse_input.just_pressed(MouseButton::Left) {
+        windows.get_single().ok().and_then(Window::cursor_position)
+    } else {
+        touches.iter_just_pressed().next().map(Touch::position)
+    };
+    if let Some(position) = started {
+        // presses on the buttons are for the buttons
+        if !interactions
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+        {
+            *press = Some(Press {
+                position,
+                held: 0.0,
+            });
+        }
+        return;
+    }
+    let Some(current) = press.as_mut() else {
+        return;
+    };
+    current.held += time.delta_seconds();
+    let long = current.held >= LONG_PRESS_SECONDS;
+    let released = mouse_input.just_released(MouseButton::Left)
+        || touches.any_just_released()
+        || touches.any_just_canceled();
+    if !long && !released {
+        return;
+    }
+    let position = current.position;
+    *press = None;
+
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(target) = camera
+        .viewport_to_world(camera_transform, position)
+        .and_then(|ray| pick_cell(&puzzle.map, ray))
+    else {
+        return;
+    };
+    let shown = walk.target == Some(target) && !walk.walking;
+    for marker in markers.iter() {
+        commands.entity(marker).despawn_recursive();
+    }
+    *walk = Walk::default();
+    let Some(moves) = route(&puzzle.map, &puzzle.state, target) else {
+        sound_channel.play(audio_assets.error.clone());
+        return;
+    };
+    if moves.is_empty() {
+        return;
+    }
+
+    let player = &puzzle.state.player;
+    let next_to = player.grid_pos_x.abs_diff(target.0) + player.grid_pos_y.abs_diff(target.1) == 1;
+    walk.target = Some(target);
+    walk.moves = moves.into();
+    if long || shown || next_to {
+        walk.walking = true;
+        walk.expected = Some(puzzle.state.clone());
+        walk.timer = Timer::from_seconds(STEP_SECONDS, TimerMode::Repeating);
+        // take the first step straight away
+        let duration = walk.timer.duration();
+        walk.timer.set_elapsed(duration);
+        return;
+    }
+
+    // show the squares on the way
+    let mesh = meshes.add(shape::Plane::from_size(0.3).into());
+    let material = materials.add(StandardMaterial {
+        base_color: UI_YELLOW,
+        unlit: true,
+        ..Default::default()
+    });
+    let mut state = puzzle.state.clone();
+    for &direction in walk.moves.iter() {
+        let Ok((next, _)) = state.apply(&puzzle.map, Action::Move(direction)) else {
+            break;
+        };
+        state = next;
+        let (x, y) = (state.player.grid_pos_x, state.player.grid_pos_y);
+        let height = puzzle.map.grid_heights[y as usize][x as usize] as f32;
+        commands
+            .spawn(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_xyz(x as f32, height + 0.02, y as f32),
+                ..Default::default()
+            })
+            .insert(RouteMarker)
+            .insert(DespawnOnTransition);
+    }
+}
+
+/// Press the next move of the walk, once the last one has had time to play out
+fn walk_route(
+    time: Res<Time>,
+    puzzle: Res<Puzzle>,
+    mut walk: ResMut<Walk>,
+    mut pointer_presses: ResMut<PointerPresses>,
+) {
+    if !walk.walking || !walk.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let on_course = walk.expected.as_ref() == Some(&puzzle.state);
+    let Some(direction) = walk.moves.pop_front().filter(|_| on_course) else {
+        *walk = Walk::default();
+        return;
+    };
+    walk.expected = puzzle
+        .state
+        .apply(&puzzle.map, Action::Move(direction))
+        .ok()
+        .map(|(next, _)| next);
+    pointer_presses.0.push(Control::movement(direction));
+}
+
+/// The route shown no longer fits once the puzzle has moved on without it
+fn clear_stale_route(
+    mut commands: Commands,
+    markers: Query<Entity, With<RouteMarker>>,
+    mut walk: ResMut<Walk>,
+) {
+    if walk.walking || walk.target.is_none() {
+        return;
+    }
+    for marker in markers.iter() {
+        commands.entity(marker).despawn_recursive();
+    }
+    *walk = Walk::default();
+}
+
+fn stop_walking(mut walk: ResMut<Walk>) {
+    *walk = Walk::default();
+}
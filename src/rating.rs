@@ -0,0 +1,113 @@
+//! Par for each level, and the stars given for how a finish compares to it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    rules::{Puzzle, PuzzleState},
+    solver::Solution,
+    states::level::Level,
+};
+
+/// Most stars a level can be finished with
+pub const MOST_STARS: u8 = 3;
+
+/// What a good finish of a level looks like
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Par {
+    /// Most moves to take, not counting turning on the spot
+    pub moves: u16,
+    /// Least stamina to have left at the flag
+    pub stamina_left: u16,
+}
+
+impl Par {
+    /// The par set by a solution: its moves, and the stamina it has left at the flag
+    pub fn of(level: &Level, solution: &Solution) -> Option<Par> {
+        let mut state = PuzzleState::new(&level.map, level.stamina_budget);
+        state.inventory = solution.loadout;
+        for &action in &solution.actions {
+            state = state.apply(&level.map, action).ok()?.0;
+        }
+        Some(Par {
+            moves: state.moves,
+            stamina_left: state.player.stamina,
+        })
+    }
+}
+
+/// How a level was finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Finish {
+    pub moves: u16,
+    pub stamina_left: u16,
+    pub scale: bool,
+}
+
+impl Finish {
+    pub fn of(puzzle: &Puzzle) -> Self {
+        Self {
+            moves: puzzle.state.moves,
+            stamina_left: puzzle.state.player.stamina,
+            scale: puzzle.state.has_scale,
+        }
+    }
+
+    /// One star for reaching the flag, one for taking no more moves than par and one for having
+    /// at least par's stamina left. Levels without a par only have the first to give.
+    pub fn stars(&self, par: Option<&Par>) -> u8 {
+        par.map_or(1, |par| {
+            1 + u8::from(self.moves <= par.moves) + u8::from(self.stamina_left >= par.stamina_left)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rules::Map,
+        solver::{solve, Goal},
+    };
+
+    #[test]
+    fn par_comes_from_the_solution() {
+        let level = Level {
+            name: "Test".to_string(),
+            map: Map::from_heights(&[&[1, 1, 1, 1]], &[]),
+            stamina_budget: 6,
+            weight_budget: 0,
+            ladder_unlocked: false,
+            rope_unlocked: false,
+            potion_unlocked: false,
+            rewind_unlocked: false,
+            par: None,
+            tutorial: Vec::new(),
+        };
+        let solution = solve(&level, Goal::Flag).unwrap();
+        assert_eq!(
+            Par::of(&level, &solution),
+            Some(Par {
+                moves: 3,
+                stamina_left: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn stars_for_beating_par() {
+        let par = Par {
+            moves: 10,
+            stamina_left: 3,
+        };
+        let finish = |moves, stamina_left| Finish {
+            moves,
+            stamina_left,
+            scale: false,
+        };
+        assert_eq!(finish(10, 3).stars(Some(&par)), MOST_STARS);
+        assert_eq!(finish(8, 1).stars(Some(&par)), 2);
+        assert_eq!(finish(12, 5).stars(Some(&par)), 2);
+        assert_eq!(finish(12, 1).stars(Some(&par)), 1);
+        assert_eq!(finish(1, 20).stars(None), 1);
+    }
+}
@@ -0,0 +1,473 @@
+use std::{fmt, io};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::{Control, ControlInput},
+    level_manager::LevelManager,
+    rules::{Outcome, Puzzle, RuleError, Step},
+    states::{
+        editor::EditorLevel, level::Level, transition::TransitionManager, GameState, PauseState,
+    },
+    ui::equipment::{InfoUiRoot, PickingUiRoot},
+};
+
+/// Seconds between steps while a replay plays by itself
+const STEP_INTERVAL: f32 = 0.4;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            start_playback
+                .run_if(in_state(GameState::MainMenu).and_then(resource_exists::<Playback>())),
+        )
+        .add_systems(
+            Update,
+            step_playback
+                .run_if(in_state(GameState::Level).and_then(resource_exists::<Playback>()))
+                .run_if(in_state(PauseState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Level), finish_replay);
+    }
+}
+
+/// Everything done during one attempt at a level
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    /// Position of the level in the campaign
+    pub level: usize,
+    /// Name of the level, to notice a replay of a level that has since been moved or changed
+    pub level_name: String,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Syntax(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// The campaign doesn't have a level at this position
+    UnknownLevel(usize),
+    /// An action came out differently to when it was recorded
+    Desync {
+        step: usize,
+        recorded: Result<Outcome, RuleError>,
+        replayed: Result<Outcome, RuleError>,
+    },
+    /// The equipment was changed after it no longer could be
+    Loadout {
+        step: usize,
+        error: RuleError,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::Syntax(e) => write!(f, "invalid replay file: {}", e),
+            ReplayError::Serialize(e) => write!(f, "couldn't write replay: {}", e),
+            ReplayError::UnknownLevel(level) => {
+                write!(f, "there is no level {} in the campaign", level + 1)
+            }
+            ReplayError::Desync {
+                step,
+                recorded,
+                replayed,
+            } => write!(
+                f,
+                "step {} was recorded as {:?} but replayed as {:?}",
+                step + 1,
+                recorded,
+                replayed
+            ),
+            ReplayError::Loadout { step, error } => {
+                write!(f, "step {} changes the equipment, but {}", step + 1, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(value: io::Error) -> Self {
+        ReplayError::Io(value)
+    }
+}
+
+impl Replay {
+    pub fn record(index: usize, level: &Level, puzzle: &Puzzle) -> Self {
+        Self {
+            level: index,
+            level_name: level.name.clone(),
+            steps: puzzle.steps().to_vec(),
+        }
+    }
+
+    pub fn from_ron(contents: &str) -> Result<Self, ReplayError> {
+        ron::from_str(contents).map_err(ReplayError::Syntax)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ReplayError> {
+        // one step to a line, so replays can be read and diffed
+        let config = ron::ser::PrettyConfig::new().depth_limit(2);
+        ron::ser::to_string_pretty(self, config).map_err(ReplayError::Serialize)
+    }
+
+    /// Take every step on a fresh puzzle, checking that each action comes out as it was
+    /// recorded. Returns the puzzle as it was left.
+    pub fn play(&self, level: &Level) -> Result<Puzzle, ReplayError> {
+        let mut puzzle = Puzzle::new(level.map.clone(), level.stamina_budget);
+        for (index, step) in self.steps.iter().enumerate() {
+            take_step(&mut puzzle, index, step)?;
+        }
+        Ok(puzzle)
+    }
+}
+
+/// Take the recorded step at `index`, failing if it doesn't come out as it was recorded
+fn take_step(puzzle: &mut Puzzle, index: usize, step: &Step) -> Result<(), ReplayError> {
+    match step {
+        Step::Loadout(inventory) => puzzle
+            .set_loadout(*inventory)
+            .map_err(|error| ReplayError::Loadout { step: index, error }),
+        Step::Action(action, recorded) => {
+            let replayed = puzzle.apply(*action);
+            if replayed == *recorded {
+                Ok(())
+            } else {
+                Err(ReplayError::Desync {
+                    step: index,
+                    recorded: *recorded,
+                    replayed,
+                })
+            }
+        }
+    }
+}
+
+/// A replay being watched in game. Player input is ignored while this exists; `.` takes the next
+/// step and P plays or pauses the rest, or whatever those are bound to.
+#[derive(Debug, Resource)]
+pub struct Playback {
+    replay: Replay,
+    /// Index of the next step to take
+    next: usize,
+    started: bool,
+    playing: bool,
+    timer: Timer,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next: 0,
+            started: false,
+            playing: false,
+            timer: Timer::from_seconds(STEP_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Set up a replay given on the command line. `--replay <file>` watches it in game, and adding
+/// `--headless` checks it plays back the same without opening a window, exiting with a failure
+/// if it doesn't.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn playback_from_args() -> Option<Playback> {
+    use std::{fs, path::PathBuf, process};
+
+    let mut path = None;
+    let mut headless = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--replay" => path = args.next().map(PathBuf::from),
+            "--headless" => headless = true,
+            _ => {}
+        }
+    }
+    let path = path?;
+    let replay = match fs::read_to_string(&path)
+        .map_err(ReplayError::from)
+        .and_then(|contents| Replay::from_ron(&contents))
+    {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("Couldn't read replay {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    };
+    if headless {
+        match check(&replay) {
+            Ok(puzzle) => {
+                println!(
+                    "Replayed {} steps of level {} ({}){}",
+                    replay.steps.len(),
+                    replay.level + 1,
+                    replay.level_name,
+                    if puzzle.is_complete() {
+                        ", reaching the flag"
+                    } else {
+                        ""
+                    }
+                );
+                process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Replay {} failed: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+    }
+    Some(Playback::new(replay))
+}
+
+/// Play a replay against the campaign on disk
+#[cfg(not(target_arch = "wasm32"))]
+fn check(replay: &Replay) -> Result<Puzzle, Box<dyn std::error::Error>> {
+    use std::{env, path::PathBuf};
+
+    use crate::level_manager::read_campaign;
+
+    // look for the assets in the same place Bevy does
+    let root = env::var_os("BEVY_ASSET_ROOT")
+        .or_else(|| env::var_os("CARGO_MANIFEST_DIR"))
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(PathBuf::from))
+        })
+        .unwrap_or_default();
+    let levels = read_campaign(&root.join("assets/levels/main.campaign.ron"))?;
+    let level = levels
+        .get(replay.level)
+        .ok_or(ReplayError::UnknownLevel(replay.level))?;
+    Ok(replay.play(level)?)
+}
+
+/// Jump straight into the replayed level once the campaign has loaded
+fn start_playback(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut level_manager: ResMut<LevelManager>,
+    mut transition_manager: ResMut<TransitionManager>,
+) {
+    if playback.started || !matches!(*transition_manager, TransitionManager::Normal) {
+        return;
+    }
+    let index = playback.replay.level;
+    let Some(level) = level_manager.levels.get(index) else {
+        error!("{}", ReplayError::UnknownLevel(index));
+        commands.remove_resource::<Playback>();
+        return;
+    };
+    if level.name != playback.replay.level_name {
+        warn!(
+            "The replay was recorded on \"{}\" but level {} is now \"{}\"",
+            playback.replay.level_name,
+            index + 1,
+            level.name
+        );
+    }
+    level_manager.current = index;
+    playback.started = true;
+    *transition_manager = TransitionManager::TransitioningOutReload(0.0);
+}
+
+fn step_playback(
+    input: ControlInput,
+    time: Res<Time>,
+    mut playback: ResMut<Playback>,
+    mut puzzle: ResMut<Puzzle>,
+    mut picking_ui: Query<&mut Visibility, With<PickingUiRoot>>,
+    mut info_ui: Query<&mut Visibility, (With<InfoUiRoot>, Without<PickingUiRoot>)>,
+) {
+    if input.just_pressed(Control::PlaybackToggle) {
+        playback.playing = !playback.playing;
+        playback.timer.reset();
+    }
+    let due = input.just_pressed(Control::PlaybackStep)
+        || (playback.playing && playback.timer.tick(time.delta()).just_finished());
+    if !due {
+        return;
+    }
+    let Some(step) = playback.replay.steps.get(playback.next).cloned() else {
+        if playback.playing {
+            info!("The replay is over");
+            playback.playing = false;
+        }
+        return;
+    };
+    playback.next += 1;
+    info!(
+        "Step {}/{}: {:?}",
+        playback.next,
+        playback.replay.steps.len(),
+        step
+    );
+    if let Err(e) = take_step(&mut puzzle, playback.next - 1, &step) {
+        warn!("{}", e);
+    }
+    // the equipment can't be changed once the climb has started
+    if let Step::Action(_, Ok(_)) = step {
+        for mut visibility in picking_ui.iter_mut() {
+            *visibility = Visibility::Hidden;
+        }
+        for mut visibility in info_ui.iter_mut() {
+            *visibility = Visibility::Visible;
+        }
+    }
+}
+
+/// Keep a replay of the attempt at the level just left, or stop watching one
+fn finish_replay(
+    mut commands: Commands,
+    puzzle: Res<Puzzle>,
+    level_manager: Res<LevelManager>,
+    playback: Option<Res<Playback>>,
+    editor_level: Option<Res<EditorLevel>>,
+) {
+    if playback.is_some() {
+        commands.remove_resource::<Playback>();
+        return;
+    }
+    // an edited or generated level wouldn't replay against the campaign
+    if editor_level.is_some() || level_manager.generated.is_some() || puzzle.steps().is_empty() {
+        return;
+    }
+    let replay = Replay::record(
+        level_manager.current,
+        level_manager.get_current_level(),
+        &puzzle,
+    );
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(e) = write_replay(&replay) {
+        warn!("Couldn't save the replay: {}", e);
+    }
+    #[cfg(target_arch = "wasm32")]
+    if let Ok(contents) = replay.to_ron() {
+        info!("Replay of level {}:\n{}", replay.level + 1, contents);
+    }
+}
+
+/// Write the replay to the data directory, replacing the last one of the same level
+#[cfg(not(target_arch = "wasm32"))]
+fn write_replay(replay: &Replay) -> Result<(), ReplayError> {
+    use std::fs;
+
+    let Some(directory) = crate::save::data_dir().map(|dir| dir.join("replays")) else {
+        return Ok(());
+    };
+    fs::create_dir_all(&directory)?;
+    let path = directory.join(format!("level-{:02}.ron", replay.level + 1));
+    fs::write(&path, replay.to_ron()?)?;
+    info!("Replay saved to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        level_manager::read_campaign,
+        rules::{Action, CardinalDirection::*, Inventory, Map},
+        solver::{solve, Goal},
+    };
+
+    use super::*;
+
+    fn level() -> Level {
+        Level {
+            name: "Test".to_string(),
+            map: Map::from_heights(&[&[1, 1, 3], &[1, 1, 1]], &[]),
+            stamina_budget: 10,
+            weight_budget: 2,
+            ladder_unlocked: true,
+            rope_unlocked: false,
+            potion_unlocked: false,
+            rewind_unlocked: false,
+            par: None,
+            tutorial: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn recorded_steps_play_back_the_same() {
+        let level = level();
+        let mut puzzle = Puzzle::new(level.map.clone(), level.stamina_budget);
+        puzzle
+            .set_loadout(Inventory {
+                ladder_count: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        for action in [
+            Action::Move(East),
+            Action::Move(East),
+            Action::Move(North),
+            Action::Move(North),
+            Action::Move(East),
+            Action::UseLadder,
+            Action::Undo,
+            Action::Redo,
+            Action::Move(East),
+        ] {
+            let _ = puzzle.apply(action);
+        }
+        assert!(puzzle
+            .steps()
+            .contains(&Step::Action(Action::Move(North), Err(RuleError::Blocked))));
+
+        let replay = Replay::record(3, &level, &puzzle);
+        let reread = Replay::from_ron(&replay.to_ron().unwrap()).unwrap();
+        assert_eq!(reread, replay);
+        let replayed = reread.play(&level).unwrap();
+        assert_eq!(replayed.state, puzzle.state);
+        assert_eq!(replayed.steps(), puzzle.steps());
+    }
+
+    #[test]
+    fn a_changed_level_is_noticed() {
+        let mut level = level();
+        let mut puzzle = Puzzle::new(level.map.clone(), level.stamina_budget);
+        for action in [Action::Move(East), Action::Move(East), Action::Move(South)] {
+            puzzle.apply(action).unwrap();
+        }
+        let replay = Replay::record(0, &level, &puzzle);
+
+        level.map.grid_heights[0][1] = 4;
+        assert!(matches!(
+            replay.play(&level),
+            Err(ReplayError::Desync {
+                step: 1,
+                recorded: Ok(Outcome::Moved),
+                replayed: Err(RuleError::Blocked),
+            })
+        ));
+    }
+
+    #[test]
+    fn solutions_replay_to_the_flag() {
+        let manifest =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/levels/main.campaign.ron");
+        let levels = read_campaign(&manifest).unwrap();
+        let level = &levels[0];
+        let solution = solve(level, Goal::Flag).unwrap();
+        let mut puzzle = Puzzle::new(level.map.clone(), level.stamina_budget);
+        puzzle.set_loadout(solution.loadout).unwrap();
+        for action in solution.actions {
+            puzzle.apply(action).unwrap();
+        }
+        let replay =
+            Replay::from_ron(&Replay::record(0, level, &puzzle).to_ron().unwrap()).unwrap();
+        assert!(replay.play(level).unwrap().is_complete());
+    }
+}
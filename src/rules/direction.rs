@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CardinalDirection {
+    North,
+    East,
+    South,
+    West,
+}
+impl CardinalDirection {
+    pub fn reverse(&self) -> Self {
+        match self {
+            CardinalDirection::North => CardinalDirection::South,
+            CardinalDirection::East => CardinalDirection::West,
+            CardinalDirection::South => CardinalDirection::North,
+            CardinalDirection::West => CardinalDirection::East,
+        }
+    }
+
+    /// The direction a quarter turn clockwise, seen from above
+    pub fn clockwise(&self) -> Self {
+        match self {
+            CardinalDirection::North => CardinalDirection::East,
+            CardinalDirection::East => CardinalDirection::South,
+            CardinalDirection::South => CardinalDirection::West,
+            CardinalDirection::West => CardinalDirection::North,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Alignment {
+    Xaxis,
+    Yaxis,
+}
+
+impl From<CardinalDirection> for Alignment {
+    fn from(value: CardinalDirection) -> Self {
+        match value {
+            CardinalDirection::North => Alignment::Yaxis,
+            CardinalDirection::East => Alignment::Xaxis,
+            CardinalDirection::South => Alignment::Yaxis,
+            CardinalDirection::West => Alignment::Xaxis,
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::{collections::BTreeSet, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Alignment, CardinalDirection, Map, Player, PlayerState};
+
+pub const LADDER_WEIGHT: u8 = 2;
+pub const ROPE_WEIGHT: u8 = 1;
+pub const POTION_WEIGHT: u8 = 1;
+pub const REWIND_WEIGHT: u8 = 1;
+
+pub const POTION_STAMINA: u16 = 3;
+
+/// Number of moves before a placed rune teleports the player back to it
+pub const RUNE_COUNTDOWN: u8 = 5;
+
+/// Equipment carried by the player. Picked before the first move and used up during the climb.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Inventory {
+    pub ladder_count: u8,
+    pub rope_count: u8,
+    pub potion_count: u8,
+    pub rewind_count: u8,
+}
+
+impl Inventory {
+    pub fn weight(&self) -> u8 {
+        self.ladder_count * LADDER_WEIGHT
+            + self.rope_count * ROPE_WEIGHT
+            + self.potion_count * POTION_WEIGHT
+            + self.rewind_count * REWIND_WEIGHT
+    }
+}
+
+impl fmt::Display for Inventory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = [
+            (self.ladder_count, "ladder"),
+            (self.rope_count, "rope"),
+            (self.potion_count, "potion"),
+            (self.rewind_count, "rune"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{} {}{}", count, name, if count > 1 { "s" } else { "" }))
+        .collect();
+        if items.is_empty() {
+            write!(f, "no equipment")
+        } else {
+            write!(f, "{}", items.join(", "))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct VerticalLadderKey {
+    pub x: u8,
+    pub y: u8,
+    pub height: u8,
+    pub direction: CardinalDirection,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct HorizontalLadderKey {
+    pub x: u8,
+    pub y: u8,
+    pub height: u8,
+    pub alignment: Alignment,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct RopeKey {
+    pub x: u8,
+    pub y: u8,
+    pub direction: CardinalDirection,
+}
+
+/// A rune placed on the ground, waiting to pull the player back
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rune {
+    pub x: u8,
+    pub y: u8,
+    pub countdown: u8,
+    /// Stamina the player had when placing it, restored on teleport
+    pub stamina: u16,
+}
+
+/// Ladders and ropes placed on the map so far
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct PlacedEquipment {
+    pub vertical_ladders: BTreeSet<VerticalLadderKey>,
+    pub horizontal_ladders: BTreeSet<HorizontalLadderKey>,
+    pub ropes: BTreeSet<RopeKey>,
+}
+
+impl PlacedEquipment {
+    pub fn is_ladder_or_rope(
+        &self,
+        x: u8,
+        y: u8,
+        height: u8,
+        direction: CardinalDirection,
+    ) -> bool {
+        let (x_offset, y_offset) = match direction {
+            CardinalDirection::North => (0, -1),
+            CardinalDirection::East => (1, 0),
+            CardinalDirection::South => (0, 1),
+            CardinalDirection::West => (-1, 0),
+        };
+        // grid square directly in front of the player (might be out of bounds)
+        let grid_facing_x: u8 = match (x as i16 + x_offset).try_into() {
+            Ok(x) => x,
+            Err(_) => return false,
+        };
+        let grid_facing_y: u8 = match (y as i16 + y_offset).try_into() {
+            Ok(x) => x,
+            Err(_) => return false,
+        };
+        // a vertical ladder reaches two squares up from where it was placed
+        let vertical_ladder_below = |drop: u8| {
+            height.checked_sub(drop).is_some_and(|height| {
+                self.vertical_ladders.contains(&VerticalLadderKey {
+                    x: grid_facing_x,
+                    y: grid_facing_y,
+                    height,
+                    direction,
+                })
+            })
+        };
+        vertical_ladder_below(2)
+            || vertical_ladder_below(1)
+            || self.horizontal_ladders.contains(&HorizontalLadderKey {
+                x,
+                y,
+                height,
+                alignment: direction.into(),
+            })
+            || self.ropes.contains(&RopeKey {
+                x: grid_facing_x,
+                y: grid_facing_y,
+                direction: direction.reverse(),
+            })
+    }
+}
+
+fn is_valid_vertical_ladder_placement(
+    map: &Map,
+    facing: Option<(u8, u8)>,
+    player_height: u8,
+) -> bool {
+    facing.is_some_and(|(x, y)| map.grid_heights[y as usize][x as usize] >= player_height + 2)
+}
+
+fn is_valid_horizontal_ladder_placement(
+    map: &Map,
+    facing: Option<(u8, u8)>,
+    facing_2: Option<(u8, u8)>,
+    player_height: u8,
+) -> bool {
+    if let (Some((x, y)), Some((x_2, y_2))) = (facing, facing_2) {
+        let height = map.grid_heights[y as usize][x as usize];
+        let height_2 = map.grid_heights[y_2 as usize][x_2 as usize];
+        (height <= player_height && height_2 == player_height)
+            || (height == player_height && height_2 <= player_height)
+    } else {
+        false
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LadderPlacement {
+    Vertical(VerticalLadderKey),
+    Horizontal(HorizontalLadderKey),
+}
+
+/// Where a ladder would be placed (or picked up from) by a player using one in their current
+/// position. A vertical placement against a wall in front takes priority over bridging a gap.
+pub fn ladder_placement(map: &Map, player: &Player) -> Option<LadderPlacement> {
+    let player_height = match &player.state {
+        PlayerState::Standing(_) => {
+            map.grid_heights[player.grid_pos_y as usize][player.grid_pos_x as usize]
+        }
+        PlayerState::Climbing(climb_state) => climb_state.elevation,
+        PlayerState::StandingOnLadder(ladder_state) => ladder_state.elevation,
+    };
+    let player_direction = player.direction();
+    // grid square directly in front of the player, and the one after it
+    let facing = map.step(player.grid_pos_x, player.grid_pos_y, player_direction, 1);
+    let facing_2 = map.step(player.grid_pos_x, player.grid_pos_y, player_direction, 2);
+
+    if is_valid_vertical_ladder_placement(map, facing, player_height) {
+        let (x, y) = facing?;
+        Some(LadderPlacement::Vertical(VerticalLadderKey {
+            x,
+            y,
+            height: player_height,
+            direction: player_direction,
+        }))
+    } else if is_valid_horizontal_ladder_placement(map, facing, facing_2, player_height) {
+        let (x, y) = facing?;
+        Some(LadderPlacement::Horizontal(HorizontalLadderKey {
+            x,
+            y,
+            height: player_height,
+            alignment: player_direction.into(),
+        }))
+    } else {
+        None
+    }
+}
+
+/// Where a rope would be tied by a player in their current position. Ropes can only be tied
+/// while standing at the top of a cliff.
+pub fn rope_placement(map: &Map, player: &Player) -> Option<RopeKey> {
+    if let PlayerState::Standing(direction) = player.state {
+        let player_height =
+            map.grid_heights[player.grid_pos_y as usize][player.grid_pos_x as usize];
+        // grid square directly in front of the player
+        let (facing_x, facing_y) = map.step(player.grid_pos_x, player.grid_pos_y, direction, 1)?;
+        let facing_height = map.grid_heights[facing_y as usize][facing_x as usize];
+        if player_height > facing_height {
+            Some(RopeKey {
+                x: player.grid_pos_x,
+                y: player.grid_pos_y,
+                direction,
+            })
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_placed() {
+        let equipment = PlacedEquipment::default();
+        assert!(!equipment.is_ladder_or_rope(1, 1, 2, CardinalDirection::North));
+    }
+
+    #[test]
+    fn off_the_edge_of_the_map() {
+        let mut equipment = PlacedEquipment::default();
+        equipment.ropes.insert(RopeKey {
+            x: 0,
+            y: 0,
+            direction: CardinalDirection::East,
+        });
+        assert!(!equipment.is_ladder_or_rope(0, 0, 2, CardinalDirection::West));
+        assert!(!equipment.is_ladder_or_rope(0, 0, 2, CardinalDirection::North));
+    }
+
+    #[test]
+    fn vertical_ladders_reach_two_up() {
+        let mut equipment = PlacedEquipment::default();
+        equipment.vertical_ladders.insert(VerticalLadderKey {
+            x: 1,
+            y: 0,
+            height: 1,
+            direction: CardinalDirection::North,
+        });
+        assert!(!equipment.is_ladder_or_rope(1, 1, 1, CardinalDirection::North));
+        assert!(equipment.is_ladder_or_rope(1, 1, 2, CardinalDirection::North));
+        assert!(equipment.is_ladder_or_rope(1, 1, 3, CardinalDirection::North));
+        assert!(!equipment.is_ladder_or_rope(1, 1, 4, CardinalDirection::North));
+        // only from the side it was placed on
+        assert!(!equipment.is_ladder_or_rope(0, 0, 2, CardinalDirection::East));
+    }
+
+    #[test]
+    fn horizontal_ladders_are_aligned() {
+        let mut equipment = PlacedEquipment::default();
+        equipment.horizontal_ladders.insert(HorizontalLadderKey {
+            x: 1,
+            y: 1,
+            height: 2,
+            alignment: Alignment::Xaxis,
+        });
+        assert!(equipment.is_ladder_or_rope(1, 1, 2, CardinalDirection::East));
+        assert!(equipment.is_ladder_or_rope(1, 1, 2, CardinalDirection::West));
+        assert!(!equipment.is_ladder_or_rope(1, 1, 2, CardinalDirection::North));
+        assert!(!equipment.is_ladder_or_rope(1, 1, 1, CardinalDirection::East));
+    }
+
+    #[test]
+    fn ropes_hang_down_the_whole_cliff() {
+        let mut equipment = PlacedEquipment::default();
+        equipment.ropes.insert(RopeKey {
+            x: 1,
+            y: 0,
+            direction: CardinalDirection::South,
+        });
+        for height in 1..5 {
+            assert!(equipment.is_ladder_or_rope(1, 1, height, CardinalDirection::North));
+        }
+        assert!(!equipment.is_ladder_or_rope(1, 1, 2, CardinalDirection::East));
+        assert!(!equipment.is_ladder_or_rope(0, 0, 2, CardinalDirection::East));
+    }
+
+    #[test]
+    fn inventory_weight_and_description() {
+        let inventory = Inventory {
+            ladder_count: 2,
+            rope_count: 1,
+            potion_count: 0,
+            rewind_count: 0,
+        };
+        assert_eq!(inventory.weight(), 2 * LADDER_WEIGHT + ROPE_WEIGHT);
+        assert_eq!(inventory.to_string(), "2 ladders, 1 rope");
+        assert_eq!(Inventory::default().to_string(), "no equipment");
+    }
+}
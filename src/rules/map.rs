@@ -0,0 +1,203 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::CardinalDirection;
+
+/// The terrain of a level. Nothing in here changes while the level is being played; placed
+/// equipment lives in [`PlacedEquipment`](super::PlacedEquipment).
+#[derive(Debug, Clone)]
+pub struct Map {
+    pub grid_heights: Vec<Vec<u8>>,
+    pub grid_climbable: Vec<Vec<bool>>,
+    pub player_start_pos: (u8, u8),
+    pub flag_pos: (u8, u8),
+    pub scale_pos: Option<(u8, u8)>,
+    /// Pairs of linked caves
+    pub caves: Vec<CaveData>,
+    /// Gems lying on the map, at most one of each colour
+    pub gems: Vec<Gem>,
+}
+
+/// Two linked cave mouths. Walking into either one in the entrance direction comes out of the
+/// other, still facing that way.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CaveData {
+    pub first_pos: (u8, u8),
+    pub second_pos: (u8, u8),
+    #[serde(default = "north", skip_serializing_if = "is_north")]
+    pub entrance: CardinalDirection,
+    #[serde(default, skip_serializing_if = "CaveLock::is_open")]
+    pub lock: CaveLock,
+}
+
+fn north() -> CardinalDirection {
+    CardinalDirection::North
+}
+
+fn is_north(direction: &CardinalDirection) -> bool {
+    *direction == CardinalDirection::North
+}
+
+impl CaveData {
+    /// The other end of the pair, if (x, y) is one of its mouths
+    pub fn other_end(&self, pos: (u8, u8)) -> Option<(u8, u8)> {
+        if pos == self.first_pos {
+            Some(self.second_pos)
+        } else if pos == self.second_pos {
+            Some(self.first_pos)
+        } else {
+            None
+        }
+    }
+}
+
+/// What it takes to go through a pair of caves
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CaveLock {
+    #[default]
+    Open,
+    /// Open once any gem has been picked up
+    AnyGem,
+    /// Open once the gem of this colour has been picked up
+    Gem(GemColour),
+}
+
+impl CaveLock {
+    pub fn is_open(&self) -> bool {
+        *self == CaveLock::Open
+    }
+
+    /// Whether the caves can be gone through holding these gems
+    pub fn opens_with(&self, gems: &BTreeSet<GemColour>) -> bool {
+        match self {
+            CaveLock::Open => true,
+            CaveLock::AnyGem => !gems.is_empty(),
+            CaveLock::Gem(colour) => gems.contains(colour),
+        }
+    }
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+pub enum GemColour {
+    #[default]
+    Red,
+    Blue,
+    Green,
+    Yellow,
+}
+
+impl GemColour {
+    pub const ALL: [GemColour; 4] = [
+        GemColour::Red,
+        GemColour::Blue,
+        GemColour::Green,
+        GemColour::Yellow,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Gem {
+    pub pos: (u8, u8),
+    #[serde(default)]
+    pub colour: GemColour,
+}
+
+impl Map {
+    pub fn new(
+        grid_heights: Vec<Vec<u8>>,
+        grid_climbable: Vec<Vec<bool>>,
+        player_pos: (u8, u8),
+        flag_pos: (u8, u8),
+        scale_pos: Option<(u8, u8)>,
+        caves: Vec<CaveData>,
+        gems: Vec<Gem>,
+    ) -> Self {
+        Self {
+            grid_heights,
+            grid_climbable,
+            player_start_pos: player_pos,
+            flag_pos,
+            scale_pos,
+            caves,
+            gems,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid_heights[0].len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid_heights.len()
+    }
+
+    /// Grid square `distance` steps away from (x, y) in the given direction, if it is on the map
+    pub fn step(
+        &self,
+        x: u8,
+        y: u8,
+        direction: CardinalDirection,
+        distance: i16,
+    ) -> Option<(u8, u8)> {
+        let (x_offset, y_offset) = match direction {
+            CardinalDirection::North => (0, -1),
+            CardinalDirection::East => (1, 0),
+            CardinalDirection::South => (0, 1),
+            CardinalDirection::West => (-1, 0),
+        };
+        let new_x = x as i16 + x_offset * distance;
+        let new_y = y as i16 + y_offset * distance;
+        if new_x < 0 || new_x >= self.width() as i16 || new_y < 0 || new_y >= self.height() as i16 {
+            None
+        } else {
+            Some((new_x as u8, new_y as u8))
+        }
+    }
+
+    /// The pair of caves with a mouth at (x, y), if there is one
+    pub fn cave_at(&self, pos: (u8, u8)) -> Option<&CaveData> {
+        self.caves.iter().find(|cave| cave.other_end(pos).is_some())
+    }
+
+    pub fn midpoint(&self) -> (f32, f32) {
+        (
+            self.grid_heights[0].len() as f32 / 2.0,
+            self.grid_heights.len() as f32 / 2.0,
+        )
+    }
+}
+
+#[cfg(test)]
+impl Map {
+    /// A map for tests, given as rows of heights from north to south with the climbable
+    /// squares listed separately. The player starts in the north-west corner and the flag is
+    /// in the south-east corner.
+    pub fn from_heights(grid_heights: &[&[u8]], climbable: &[(u8, u8)]) -> Self {
+        let grid_heights: Vec<Vec<u8>> = grid_heights.iter().map(|row| row.to_vec()).collect();
+        let mut grid_climbable: Vec<Vec<bool>> = grid_heights
+            .iter()
+            .map(|row| vec![false; row.len()])
+            .collect();
+        for &(x, y) in climbable {
+            grid_climbable[y as usize][x as usize] = true;
+        }
+        let flag_pos = (
+            grid_heights[0].len() as u8 - 1,
+            grid_heights.len() as u8 - 1,
+        );
+        Self::new(
+            grid_heights,
+            grid_climbable,
+            (0, 0),
+            flag_pos,
+            None,
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+}
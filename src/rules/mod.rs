@@ -0,0 +1,752 @@
+//! The rules of the climb, kept free of Bevy so they can be run headless by the solver and
+//! tests. The ECS systems turn key presses into [`Action`]s, apply them to the current
+//! [`Puzzle`] and then mirror its state into entities.
+
+use std::{collections::BTreeSet, fmt, mem};
+
+use serde::{Deserialize, Serialize};
+
+pub use self::{direction::*, equipment::*, map::*, player::*};
+
+mod direction;
+mod equipment;
+mod map;
+mod player;
+
+/// A single key press the player can make during a level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Move(CardinalDirection),
+    UseLadder,
+    PlaceRope,
+    PlaceRune,
+    DrinkPotion,
+    Undo,
+    /// Take the last undone action again
+    Redo,
+}
+
+/// What a successful action did, so the game can play the matching sound or effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// Turned to face another way without moving
+    Turned,
+    Moved,
+    /// Moved, then got pulled back by a rune whose countdown ran out
+    Teleported,
+    PlacedLadder,
+    PickedUpLadder,
+    PlacedRope,
+    PlacedRune,
+    DrankPotion,
+    Undone,
+}
+
+/// Why an action isn't allowed. The state is left unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleError {
+    /// The player is at the flag, so the level is over
+    LevelComplete,
+    /// There is nothing to walk or climb onto in that direction
+    Blocked,
+    NotEnoughStamina,
+    /// The cave can't be entered until its gem has been picked up
+    NeedsGem,
+    /// Ropes and runes can only be placed while standing
+    NotStanding,
+    NoLadderPlacement,
+    NoRopePlacement,
+    RopeAlreadyPlaced,
+    OutOfLadders,
+    OutOfRopes,
+    OutOfPotions,
+    OutOfRunes,
+    NothingToUndo,
+    NothingToRedo,
+    /// The equipment is picked before setting off and can't be changed after
+    AlreadySetOff,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            RuleError::LevelComplete => "the level is already complete",
+            RuleError::Blocked => "there is nowhere to go that way",
+            RuleError::NotEnoughStamina => "not enough stamina",
+            RuleError::NeedsGem => "the cave needs its gem",
+            RuleError::NotStanding => "only possible while standing",
+            RuleError::NoLadderPlacement => "nowhere to place a ladder",
+            RuleError::NoRopePlacement => "nowhere to tie a rope",
+            RuleError::RopeAlreadyPlaced => "there is already a rope here",
+            RuleError::OutOfLadders => "no ladders left",
+            RuleError::OutOfRopes => "no ropes left",
+            RuleError::OutOfPotions => "no potions left",
+            RuleError::OutOfRunes => "no runes left",
+            RuleError::NothingToUndo => "nothing to undo",
+            RuleError::NothingToRedo => "nothing to redo",
+            RuleError::AlreadySetOff => "the equipment can't be changed once the climb has started",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Something the player did during a level, kept so that it can be replayed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Step {
+    /// Equipment picked before setting off
+    Loadout(Inventory),
+    /// An action along with what came of it, including the ones the rules turned down
+    Action(Action, Result<Outcome, RuleError>),
+}
+
+/// Everything about a level in progress that can change as it is played
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PuzzleState {
+    pub player: Player,
+    pub inventory: Inventory,
+    pub equipment: PlacedEquipment,
+    /// Runes on the ground, in the order they were placed
+    pub runes: Vec<Rune>,
+    /// Colours of the gems picked up, which open the caves locked with them
+    pub gems: BTreeSet<GemColour>,
+    pub has_scale: bool,
+    /// Moves made so far, not counting turning on the spot
+    pub moves: u16,
+}
+
+impl PuzzleState {
+    /// The state at the start of a level, before any equipment has been picked
+    pub fn new(map: &Map, stamina: u16) -> Self {
+        let mut state = Self {
+            player: Player {
+                stamina,
+                grid_pos_x: map.player_start_pos.0,
+                grid_pos_y: map.player_start_pos.1,
+                state: PlayerState::Standing(CardinalDirection::South),
+            },
+            inventory: Inventory::default(),
+            equipment: PlacedEquipment::default(),
+            runes: Vec::new(),
+            gems: BTreeSet::new(),
+            has_scale: false,
+            moves: 0,
+        };
+        state.collect(map);
+        state
+    }
+
+    pub fn at_flag(&self, map: &Map) -> bool {
+        (self.player.grid_pos_x, self.player.grid_pos_y) == map.flag_pos
+    }
+
+    /// Stamina it would cost to move in a direction, turning to face it first if needed, or
+    /// `None` if the move can't be made
+    pub fn move_cost(&self, map: &Map, direction: CardinalDirection) -> Option<u16> {
+        let mut player = self.player.clone();
+        if let PlayerState::Standing(_) = player.state {
+            player.state = PlayerState::Standing(direction);
+        }
+        player
+            .go(direction, map, &self.equipment, &self.gems)
+            .map(|moved| player.stamina - moved.stamina)
+    }
+
+    /// The state after taking an action. Undoing and redoing need the history kept by
+    /// [`Puzzle`], so there is never anything to undo or redo here.
+    pub fn apply(&self, map: &Map, action: Action) -> Result<(Self, Outcome), RuleError> {
+        if self.at_flag(map) {
+            return Err(RuleError::LevelComplete);
+        }
+        let mut next = self.clone();
+        let outcome = match action {
+            Action::Move(direction) => {
+                let moved = self
+                    .player
+                    .go(direction, map, &self.equipment, &self.gems)
+                    .ok_or_else(|| self.why_blocked(map, direction))?;
+                let turned = self.player.has_direction_changed(&moved);
+                next.player = moved;
+                // turning on the spot doesn't count as a move
+                if turned {
+                    return Ok((next, Outcome::Turned));
+                }
+                next.moves += 1;
+                // count down every rune. Any that reach 0 pull the player back to them
+                let mut teleport = None;
+                next.runes.retain_mut(|rune| {
+                    rune.countdown -= 1;
+                    if rune.countdown == 0 {
+                        teleport = Some(rune.clone());
+                        false
+                    } else {
+                        true
+                    }
+                });
+                let outcome = if let Some(rune) = teleport {
+                    next.player = Player {
+                        stamina: rune.stamina,
+                        grid_pos_x: rune.x,
+                        grid_pos_y: rune.y,
+                        state: PlayerState::Standing(CardinalDirection::South),
+                    };
+                    Outcome::Teleported
+                } else {
+                    Outcome::Moved
+                };
+                next.collect(map);
+                outcome
+            }
+            Action::UseLadder => {
+                match ladder_placement(map, &self.player).ok_or(RuleError::NoLadderPlacement)? {
+                    LadderPlacement::Vertical(key) => toggle_ladder(
+                        &mut next.equipment.vertical_ladders,
+                        key,
+                        &mut next.inventory,
+                    )?,
+                    LadderPlacement::Horizontal(key) => toggle_ladder(
+                        &mut next.equipment.horizontal_ladders,
+                        key,
+                        &mut next.inventory,
+                    )?,
+                }
+            }
+            Action::PlaceRope => {
+                if !matches!(self.player.state, PlayerState::Standing(_)) {
+                    return Err(RuleError::NotStanding);
+                }
+                let key = rope_placement(map, &self.player).ok_or(RuleError::NoRopePlacement)?;
+                if next.equipment.ropes.contains(&key) {
+                    return Err(RuleError::RopeAlreadyPlaced);
+                }
+                if next.inventory.rope_count == 0 {
+                    return Err(RuleError::OutOfRopes);
+                }
+                next.inventory.rope_count -= 1;
+                next.equipment.ropes.insert(key);
+                Outcome::PlacedRope
+            }
+            Action::PlaceRune => {
+                if !matches!(self.player.state, PlayerState::Standing(_)) {
+                    return Err(RuleError::NotStanding);
+                }
+                if next.inventory.rewind_count == 0 {
+                    return Err(RuleError::OutOfRunes);
+                }
+                next.inventory.rewind_count -= 1;
+                next.runes.push(Rune {
+                    x: self.player.grid_pos_x,
+                    y: self.player.grid_pos_y,
+                    countdown: RUNE_COUNTDOWN,
+                    stamina: self.player.stamina,
+                });
+                Outcome::PlacedRune
+            }
+            Action::DrinkPotion => {
+                if next.inventory.potion_count == 0 {
+                    return Err(RuleError::OutOfPotions);
+                }
+                next.inventory.potion_count -= 1;
+                next.player.stamina += POTION_STAMINA;
+                Outcome::DrankPotion
+            }
+            Action::Undo => return Err(RuleError::NothingToUndo),
+            Action::Redo => return Err(RuleError::NothingToRedo),
+        };
+        Ok((next, outcome))
+    }
+
+    /// Pick up anything at the player's position
+    fn collect(&mut self, map: &Map) {
+        let (x, y) = (self.player.grid_pos_x, self.player.grid_pos_y);
+        if let Some(gem) = map.gems.iter().find(|gem| gem.pos == (x, y)) {
+            self.gems.insert(gem.colour);
+        }
+        if map.scale_pos == Some((x, y)) {
+            let mut at_scale = true;
+            if let PlayerState::Climbing(climb_state) = &self.player.state {
+                at_scale = map.grid_heights[y as usize][x as usize] == climb_state.elevation - 1;
+            }
+            self.has_scale |= at_scale;
+        }
+    }
+
+    /// Work out why a move in this direction isn't allowed
+    fn why_blocked(&self, map: &Map, direction: CardinalDirection) -> RuleError {
+        let rested = Player {
+            stamina: u16::MAX,
+            ..self.player.clone()
+        };
+        if rested
+            .go(direction, map, &self.equipment, &self.gems)
+            .is_some()
+        {
+            RuleError::NotEnoughStamina
+        } else if rested
+            .go(direction, map, &self.equipment, &GemColour::ALL.into())
+            .is_some()
+        {
+            RuleError::NeedsGem
+        } else {
+            RuleError::Blocked
+        }
+    }
+}
+
+/// Picks the ladder up if it is already placed, otherwise places one from the inventory
+fn toggle_ladder<K: Ord>(
+    placed: &mut BTreeSet<K>,
+    key: K,
+    inventory: &mut Inventory,
+) -> Result<Outcome, RuleError> {
+    if placed.remove(&key) {
+        inventory.ladder_count += 1;
+        Ok(Outcome::PickedUpLadder)
+    } else if inventory.ladder_count > 0 {
+        inventory.ladder_count -= 1;
+        placed.insert(key);
+        Ok(Outcome::PlacedLadder)
+    } else {
+        Err(RuleError::OutOfLadders)
+    }
+}
+
+/// A level being played: its map, where things stand now and the history that undo and redo
+/// step through
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    pub map: Map,
+    pub state: PuzzleState,
+    /// Each state that was left, along with the action that left it
+    history: Vec<(PuzzleState, Action)>,
+    /// Undone actions, the most recently undone last
+    redo: Vec<Action>,
+    /// Everything done since the level started, for recording a replay
+    steps: Vec<Step>,
+}
+
+impl Puzzle {
+    pub fn new(map: Map, stamina: u16) -> Self {
+        Self {
+            state: PuzzleState::new(&map, stamina),
+            map,
+            history: Vec::new(),
+            redo: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Everything done so far, in order
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Set the equipment carried. This is picked before the first move, or after undoing
+    /// everything back to the start, and forgets what there was to redo.
+    pub fn set_loadout(&mut self, inventory: Inventory) -> Result<(), RuleError> {
+        if self.can_undo() {
+            return Err(RuleError::AlreadySetOff);
+        }
+        self.state.inventory = inventory;
+        self.redo.clear();
+        self.steps.push(Step::Loadout(inventory));
+        Ok(())
+    }
+
+    /// Take an action, remembering the state before it so that it can be undone. Turning on
+    /// the spot isn't remembered, just like it doesn't count as a move.
+    ///
+    /// Redoing takes the undone action again through the rules rather than restoring a saved
+    /// state, so it gives the same outcome (runes counting down, teleporting and all) as the
+    /// first time. Anything else that changes the state clears what there was to redo.
+    pub fn apply(&mut self, action: Action) -> Result<Outcome, RuleError> {
+        let result = self.resolve(action);
+        self.steps.push(Step::Action(action, result));
+        result
+    }
+
+    fn resolve(&mut self, action: Action) -> Result<Outcome, RuleError> {
+        match action {
+            Action::Undo => {
+                let (state, action) = self.history.pop().ok_or(RuleError::NothingToUndo)?;
+                self.state = state;
+                self.redo.push(action);
+                Ok(Outcome::Undone)
+            }
+            Action::Redo => {
+                let action = *self.redo.last().ok_or(RuleError::NothingToRedo)?;
+                let outcome = self.take(action)?;
+                self.redo.pop();
+                Ok(outcome)
+            }
+            action => {
+                let outcome = self.take(action)?;
+                if self.redo.last() == Some(&action) && outcome != Outcome::Turned {
+                    // same as what was undone, so the rest can still be redone
+                    self.redo.pop();
+                } else {
+                    self.redo.clear();
+                }
+                Ok(outcome)
+            }
+        }
+    }
+
+    fn take(&mut self, action: Action) -> Result<Outcome, RuleError> {
+        let (state, outcome) = self.state.apply(&self.map, action)?;
+        let previous = mem::replace(&mut self.state, state);
+        if outcome != Outcome::Turned {
+            self.history.push((previous, action));
+        }
+        Ok(outcome)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.state.at_flag(&self.map)
+    }
+
+    /// The states left so far, oldest first
+    pub fn earlier_states(&self) -> impl Iterator<Item = &PuzzleState> {
+        self.history.iter().map(|(state, _)| state)
+    }
+
+    /// Whether there is a move to undo. Until there is, the equipment is still being picked.
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use CardinalDirection::*;
+
+    #[test]
+    fn undo_steps_back_through_moves_but_not_turns() {
+        let map = Map::from_heights(&[&[1, 1, 1], &[1, 1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 5);
+        assert_eq!(puzzle.apply(Action::Undo), Err(RuleError::NothingToUndo));
+
+        assert_eq!(puzzle.apply(Action::Move(East)), Ok(Outcome::Turned));
+        assert_eq!(puzzle.apply(Action::Undo), Err(RuleError::NothingToUndo));
+
+        let before = puzzle.state.clone();
+        assert_eq!(puzzle.apply(Action::Move(East)), Ok(Outcome::Moved));
+        assert_eq!(puzzle.state.player.grid_pos_x, 1);
+        assert_eq!(puzzle.state.player.stamina, 4);
+        assert_eq!(puzzle.state.moves, 1);
+        assert_eq!(puzzle.apply(Action::Undo), Ok(Outcome::Undone));
+        assert_eq!(puzzle.state, before);
+    }
+
+    #[test]
+    fn failed_moves_explain_themselves() {
+        let map = Map::from_heights(&[&[1, 1], &[1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 1);
+        assert_eq!(puzzle.apply(Action::Move(North)), Ok(Outcome::Turned));
+        assert_eq!(puzzle.apply(Action::Move(North)), Err(RuleError::Blocked));
+        assert_eq!(puzzle.apply(Action::Move(South)), Ok(Outcome::Turned));
+        assert_eq!(puzzle.apply(Action::Move(South)), Ok(Outcome::Moved));
+        assert_eq!(puzzle.apply(Action::Move(East)), Ok(Outcome::Turned));
+        assert_eq!(
+            puzzle.apply(Action::Move(East)),
+            Err(RuleError::NotEnoughStamina)
+        );
+    }
+
+    #[test]
+    fn nothing_more_happens_at_the_flag() {
+        let map = Map::from_heights(&[&[1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 5);
+        puzzle.apply(Action::Move(East)).unwrap();
+        assert!(!puzzle.is_complete());
+        assert_eq!(puzzle.apply(Action::Move(East)), Ok(Outcome::Moved));
+        assert!(puzzle.is_complete());
+        assert_eq!(
+            puzzle.apply(Action::Move(West)),
+            Err(RuleError::LevelComplete)
+        );
+        // going back a step is still allowed
+        assert_eq!(puzzle.apply(Action::Undo), Ok(Outcome::Undone));
+        assert!(!puzzle.is_complete());
+    }
+
+    #[test]
+    fn the_cave_opens_once_the_gem_is_picked_up() {
+        let mut map = Map::from_heights(&[&[1, 1, 1], &[1, 1, 1], &[1, 1, 1]], &[]);
+        map.caves = vec![CaveData {
+            first_pos: (0, 1),
+            second_pos: (2, 1),
+            entrance: North,
+            lock: CaveLock::AnyGem,
+        }];
+        map.gems = vec![Gem {
+            pos: (1, 0),
+            colour: GemColour::Green,
+        }];
+        let mut puzzle = Puzzle::new(map, 20);
+        assert!(puzzle.state.gems.is_empty());
+        for action in [Action::Move(South), Action::Move(North)] {
+            puzzle.apply(action).unwrap();
+        }
+        assert_eq!(puzzle.apply(Action::Move(North)), Err(RuleError::NeedsGem));
+
+        for action in [
+            Action::Move(East),
+            Action::Move(East),
+            Action::Move(North),
+            Action::Move(North),
+        ] {
+            puzzle.apply(action).unwrap();
+        }
+        assert_eq!(puzzle.state.gems, BTreeSet::from([GemColour::Green]));
+        assert_eq!(puzzle.apply(Action::Undo), Ok(Outcome::Undone));
+        assert!(puzzle.state.gems.is_empty());
+        puzzle.apply(Action::Move(North)).unwrap();
+
+        for action in [
+            Action::Move(South),
+            Action::Move(South),
+            Action::Move(West),
+            Action::Move(West),
+            Action::Move(North),
+        ] {
+            puzzle.apply(action).unwrap();
+        }
+        assert_eq!(puzzle.apply(Action::Move(North)), Ok(Outcome::Moved));
+        let player = &puzzle.state.player;
+        assert_eq!((player.grid_pos_x, player.grid_pos_y), (2, 1));
+
+        // coming back is a move like any other, so it can be undone
+        assert_eq!(puzzle.apply(Action::Move(North)), Ok(Outcome::Moved));
+        let player = &puzzle.state.player;
+        assert_eq!((player.grid_pos_x, player.grid_pos_y), (0, 1));
+        assert_eq!(puzzle.apply(Action::Undo), Ok(Outcome::Undone));
+        assert_eq!(puzzle.state.player.grid_pos_x, 2);
+    }
+
+    #[test]
+    fn each_pair_of_caves_needs_its_own_gem() {
+        let mut map = Map::from_heights(&[&[1, 1, 1, 1], &[1, 1, 1, 1]], &[]);
+        map.player_start_pos = (1, 1);
+        map.caves = vec![
+            CaveData {
+                first_pos: (1, 1),
+                second_pos: (0, 0),
+                entrance: West,
+                lock: CaveLock::Open,
+            },
+            CaveData {
+                first_pos: (0, 1),
+                second_pos: (3, 0),
+                entrance: South,
+                lock: CaveLock::Gem(GemColour::Yellow),
+            },
+        ];
+        map.gems = vec![
+            Gem {
+                pos: (1, 0),
+                colour: GemColour::Red,
+            },
+            Gem {
+                pos: (2, 1),
+                colour: GemColour::Yellow,
+            },
+        ];
+        let mut puzzle = Puzzle::new(map, 20);
+        for action in [Action::Move(West), Action::Move(West)] {
+            puzzle.apply(action).unwrap();
+        }
+        assert_eq!(puzzle.state.player.grid_pos_y, 0);
+        for action in [
+            Action::Move(East),
+            Action::Move(East),
+            Action::Move(West),
+            Action::Move(West),
+            Action::Move(South),
+            Action::Move(South),
+        ] {
+            puzzle.apply(action).unwrap();
+        }
+        // the red gem doesn't open the yellow caves
+        assert_eq!(puzzle.state.gems, BTreeSet::from([GemColour::Red]));
+        assert_eq!(puzzle.apply(Action::Move(South)), Err(RuleError::NeedsGem));
+
+        for action in [
+            Action::Move(East),
+            Action::Move(East),
+            Action::Move(East),
+            Action::Move(West),
+            Action::Move(West),
+            Action::Move(West),
+            Action::Move(South),
+            Action::Move(South),
+        ] {
+            puzzle.apply(action).unwrap();
+        }
+        assert_eq!(puzzle.apply(Action::Move(South)), Ok(Outcome::Moved));
+        let player = &puzzle.state.player;
+        assert_eq!((player.grid_pos_x, player.grid_pos_y), (3, 0));
+    }
+
+    #[test]
+    fn runes_pull_the_player_back() {
+        let map = Map::from_heights(&[&[1, 1, 1, 1, 1, 1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 10);
+        puzzle.apply(Action::Move(East)).unwrap();
+        assert_eq!(puzzle.apply(Action::PlaceRune), Err(RuleError::OutOfRunes));
+        puzzle.state.inventory.rewind_count = 1;
+        assert_eq!(puzzle.apply(Action::PlaceRune), Ok(Outcome::PlacedRune));
+
+        for _ in 1..RUNE_COUNTDOWN {
+            assert_eq!(puzzle.apply(Action::Move(East)), Ok(Outcome::Moved));
+        }
+        assert_eq!(puzzle.state.runes[0].countdown, 1);
+        assert_eq!(puzzle.apply(Action::Move(East)), Ok(Outcome::Teleported));
+        let player = &puzzle.state.player;
+        assert_eq!((player.grid_pos_x, player.stamina), (0, 10));
+        assert!(puzzle.state.runes.is_empty());
+
+        // undoing the teleport puts the rune back down
+        puzzle.apply(Action::Undo).unwrap();
+        assert_eq!(puzzle.state.runes[0].countdown, 1);
+        assert_eq!(puzzle.state.player.grid_pos_x, 4);
+    }
+
+    #[test]
+    fn ladders_are_placed_and_picked_up() {
+        let mut map = Map::from_heights(&[&[3, 3], &[1, 1]], &[]);
+        map.player_start_pos = (0, 1);
+        let mut puzzle = Puzzle::new(map, 10);
+        puzzle.apply(Action::Move(North)).unwrap();
+        assert_eq!(
+            puzzle.apply(Action::UseLadder),
+            Err(RuleError::OutOfLadders)
+        );
+
+        puzzle.state.inventory.ladder_count = 1;
+        assert_eq!(puzzle.apply(Action::UseLadder), Ok(Outcome::PlacedLadder));
+        assert_eq!(puzzle.state.inventory.ladder_count, 0);
+        assert_eq!(puzzle.apply(Action::UseLadder), Ok(Outcome::PickedUpLadder));
+        assert_eq!(puzzle.state.inventory.ladder_count, 1);
+
+        puzzle.apply(Action::UseLadder).unwrap();
+        for _ in 0..3 {
+            puzzle.apply(Action::Move(North)).unwrap();
+        }
+        let player = &puzzle.state.player;
+        assert_eq!((player.grid_pos_y, player.stamina), (0, 8));
+    }
+
+    #[test]
+    fn potions_restore_stamina() {
+        let map = Map::from_heights(&[&[1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 2);
+        assert_eq!(
+            puzzle.apply(Action::DrinkPotion),
+            Err(RuleError::OutOfPotions)
+        );
+        puzzle.state.inventory.potion_count = 1;
+        assert_eq!(puzzle.apply(Action::DrinkPotion), Ok(Outcome::DrankPotion));
+        assert_eq!(puzzle.state.player.stamina, 2 + POTION_STAMINA);
+        assert_eq!(puzzle.state.inventory.potion_count, 0);
+    }
+
+    #[test]
+    fn the_loadout_is_fixed_once_the_climb_starts() {
+        let map = Map::from_heights(&[&[1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 2);
+        let one_potion = Inventory {
+            potion_count: 1,
+            ..Default::default()
+        };
+        assert_eq!(puzzle.set_loadout(one_potion), Ok(()));
+        assert_eq!(puzzle.apply(Action::DrinkPotion), Ok(Outcome::DrankPotion));
+        assert_eq!(
+            puzzle.set_loadout(one_potion),
+            Err(RuleError::AlreadySetOff)
+        );
+        assert_eq!(puzzle.state.inventory.potion_count, 0);
+        assert_eq!(puzzle.steps().len(), 2);
+
+        // undoing back to the start picks the equipment again, so the potion can't be redone
+        puzzle.apply(Action::Undo).unwrap();
+        assert_eq!(puzzle.set_loadout(Inventory::default()), Ok(()));
+        assert_eq!(puzzle.apply(Action::Redo), Err(RuleError::NothingToRedo));
+    }
+
+    #[test]
+    fn redo_takes_undone_actions_again() {
+        let map = Map::from_heights(&[&[1, 1, 1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 10);
+        assert_eq!(puzzle.apply(Action::Redo), Err(RuleError::NothingToRedo));
+        puzzle.state.inventory.potion_count = 1;
+        for action in [
+            Action::Move(East),
+            Action::Move(East),
+            Action::DrinkPotion,
+            Action::Move(East),
+        ] {
+            puzzle.apply(action).unwrap();
+        }
+        let end = puzzle.state.clone();
+
+        for _ in 0..3 {
+            puzzle.apply(Action::Undo).unwrap();
+        }
+        assert_eq!(puzzle.state.inventory.potion_count, 1);
+        assert_eq!(puzzle.apply(Action::Redo), Ok(Outcome::Moved));
+        assert_eq!(puzzle.apply(Action::Redo), Ok(Outcome::DrankPotion));
+        assert_eq!(puzzle.apply(Action::Redo), Ok(Outcome::Moved));
+        assert_eq!(puzzle.state, end);
+        assert_eq!(puzzle.apply(Action::Redo), Err(RuleError::NothingToRedo));
+    }
+
+    #[test]
+    fn redo_goes_off_runes_again() {
+        let map = Map::from_heights(&[&[1, 1, 1, 1, 1, 1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 10);
+        puzzle.state.inventory.rewind_count = 1;
+        puzzle.apply(Action::Move(East)).unwrap();
+        puzzle.apply(Action::PlaceRune).unwrap();
+        for _ in 0..RUNE_COUNTDOWN {
+            puzzle.apply(Action::Move(East)).unwrap();
+        }
+        let teleported = puzzle.state.clone();
+
+        for _ in 0..=RUNE_COUNTDOWN {
+            puzzle.apply(Action::Undo).unwrap();
+        }
+        assert!(puzzle.state.runes.is_empty());
+        assert_eq!(puzzle.state.inventory.rewind_count, 1);
+        assert_eq!(puzzle.apply(Action::Redo), Ok(Outcome::PlacedRune));
+        for _ in 1..RUNE_COUNTDOWN {
+            assert_eq!(puzzle.apply(Action::Redo), Ok(Outcome::Moved));
+        }
+        assert_eq!(puzzle.apply(Action::Redo), Ok(Outcome::Teleported));
+        assert_eq!(puzzle.state, teleported);
+    }
+
+    #[test]
+    fn a_different_action_clears_redo() {
+        let map = Map::from_heights(&[&[1, 1, 1], &[1, 1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 10);
+        for action in [Action::Move(East), Action::Move(East), Action::Move(East)] {
+            puzzle.apply(action).unwrap();
+        }
+        puzzle.apply(Action::Undo).unwrap();
+        puzzle.apply(Action::Undo).unwrap();
+
+        // taking the same step again keeps the one after it
+        puzzle.apply(Action::Move(East)).unwrap();
+        puzzle.apply(Action::Undo).unwrap();
+        puzzle.apply(Action::Move(East)).unwrap();
+        assert_eq!(puzzle.apply(Action::Redo), Ok(Outcome::Moved));
+
+        puzzle.apply(Action::Undo).unwrap();
+        assert_eq!(puzzle.apply(Action::Move(South)), Ok(Outcome::Turned));
+        assert_eq!(puzzle.apply(Action::Redo), Err(RuleError::NothingToRedo));
+    }
+}
@@ -0,0 +1,761 @@
+use std::collections::BTreeSet;
+
+use super::{Alignment, CardinalDirection, GemColour, HorizontalLadderKey, Map, PlacedEquipment};
+
+const MOVE_STAMINA: u16 = 1;
+const CLIMB_UP_STAMINA: u16 = 4;
+const CLIMB_SIDEWAYS_STAMINA: u16 = 2;
+const CLIMB_DOWN_STAMINA: u16 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PlayerState {
+    Standing(CardinalDirection),
+    Climbing(ClimbingState),
+    StandingOnLadder(LadderState),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClimbingState {
+    pub direction: CardinalDirection,
+    pub elevation: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LadderState {
+    pub direction: CardinalDirection,
+    pub elevation: u8,
+    pub alignment: Alignment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Player {
+    pub stamina: u16,
+    pub grid_pos_x: u8,
+    pub grid_pos_y: u8,
+    pub state: PlayerState,
+}
+impl Player {
+    pub fn go(
+        &self,
+        direction: CardinalDirection,
+        map: &Map,
+        equipment: &PlacedEquipment,
+        gems: &BTreeSet<GemColour>,
+    ) -> Option<Self> {
+        let heights = &map.grid_heights;
+        let x = self.grid_pos_x as usize;
+        let y = self.grid_pos_y as usize;
+
+        // check if changing direction
+        if let PlayerState::Standing(facing) = self.state {
+            if facing != direction {
+                return Some(Self {
+                    stamina: self.stamina,
+                    grid_pos_x: self.grid_pos_x,
+                    grid_pos_y: self.grid_pos_y,
+                    state: PlayerState::Standing(direction),
+                });
+            }
+        }
+
+        // walking into a cave comes out of the other end, even at the edge of the map
+        if matches!(self.state, PlayerState::Standing(_)) {
+            if let Some(cave) = map
+                .cave_at((self.grid_pos_x, self.grid_pos_y))
+                .filter(|cave| cave.entrance == direction)
+            {
+                if !cave.lock.opens_with(gems) {
+                    return None;
+                }
+                let (exit_x, exit_y) = cave.other_end((self.grid_pos_x, self.grid_pos_y))?;
+                // still facing the same way, so going back is the same key again
+                return self.stamina.checked_sub(MOVE_STAMINA).map(|stamina| Self {
+                    stamina,
+                    grid_pos_x: exit_x,
+                    grid_pos_y: exit_y,
+                    state: PlayerState::Standing(direction),
+                });
+            }
+        }
+
+        // check if moving out of bounds
+        if matches!(self.state, PlayerState::Standing(_))
+            || matches!(self.state, PlayerState::StandingOnLadder(_))
+        {
+            match direction {
+                CardinalDirection::North => {
+                    if y == 0 {
+                        return None;
+                    }
+                }
+                CardinalDirection::East => {
+                    if x == heights[0].len() - 1 {
+                        return None;
+                    }
+                }
+                CardinalDirection::South => {
+                    if y == heights.len() - 1 {
+                        return None;
+                    }
+                }
+                CardinalDirection::West => {
+                    if x == 0 {
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let current_elevation = heights[y][x];
+        match &self.state {
+            PlayerState::Standing(_) => {
+                let (new_x, new_y) = match direction {
+                    CardinalDirection::North => (x, y - 1),
+                    CardinalDirection::East => (x + 1, y),
+                    CardinalDirection::South => (x, y + 1),
+                    CardinalDirection::West => (x - 1, y),
+                };
+                // equal elevation
+                if heights[new_y][new_x] == current_elevation {
+                    self.stamina.checked_sub(MOVE_STAMINA).map(|stamina| Self {
+                        stamina,
+                        grid_pos_x: new_x as u8,
+                        grid_pos_y: new_y as u8,
+                        state: PlayerState::Standing(direction),
+                    })
+                } else if heights[new_y][new_x] > current_elevation {
+                    // elevation rises -> check if wall is climbable or is a ladder/rope
+                    if map.grid_climbable[new_y][new_x]
+                        || equipment.is_ladder_or_rope(
+                            self.grid_pos_x,
+                            self.grid_pos_y,
+                            current_elevation + 1,
+                            direction,
+                        )
+                    {
+                        // cling to wall
+                        Some(Self {
+                            stamina: self.stamina,
+                            grid_pos_x: self.grid_pos_x,
+                            grid_pos_y: self.grid_pos_y,
+                            state: PlayerState::Climbing(ClimbingState {
+                                direction,
+                                elevation: current_elevation + 1,
+                            }),
+                        })
+                    } else {
+                        None
+                    }
+                } else if equipment.horizontal_ladders.contains(&HorizontalLadderKey {
+                    x: new_x as u8,
+                    y: new_y as u8,
+                    height: current_elevation,
+                    alignment: direction.into(),
+                }) {
+                    // elevation drops -> check if there is a horizontal ladder
+                    self.stamina.checked_sub(MOVE_STAMINA).map(|stamina| Self {
+                        stamina,
+                        grid_pos_x: new_x as u8,
+                        grid_pos_y: new_y as u8,
+                        state: PlayerState::StandingOnLadder(LadderState {
+                            direction,
+                            elevation: current_elevation,
+                            alignment: direction.into(),
+                        }),
+                    })
+                } else if map.grid_climbable[self.grid_pos_y as usize][self.grid_pos_x as usize]
+                    || equipment.is_ladder_or_rope(
+                        new_x as u8,
+                        new_y as u8,
+                        current_elevation,
+                        direction.reverse(),
+                    )
+                {
+                    // climbing down
+                    let cost = if equipment.is_ladder_or_rope(
+                        new_x as u8,
+                        new_y as u8,
+                        current_elevation,
+                        direction.reverse(),
+                    ) {
+                        1
+                    } else {
+                        CLIMB_DOWN_STAMINA
+                    };
+                    self.stamina.checked_sub(cost).map(|stamina| Self {
+                        stamina,
+                        grid_pos_x: new_x as u8,
+                        grid_pos_y: new_y as u8,
+                        state: PlayerState::Climbing(ClimbingState {
+                            direction: direction.reverse(),
+                            elevation: current_elevation,
+                        }),
+                    })
+                } else {
+                    None
+                }
+            }
+            PlayerState::Climbing(climb_state) => match direction {
+                CardinalDirection::North => {
+                    let (x_offset, y_offset) = match &climb_state.direction {
+                        CardinalDirection::North => (0, -1),
+                        CardinalDirection::East => (1, 0),
+                        CardinalDirection::South => (0, 1),
+                        CardinalDirection::West => (-1, 0),
+                    };
+                    // can't climb on boundary so conversion to unsigned is safe
+                    let next_x = (x as i16 + x_offset) as usize;
+                    let next_y = (y as i16 + y_offset) as usize;
+                    let cost = if equipment.is_ladder_or_rope(
+                        self.grid_pos_x,
+                        self.grid_pos_y,
+                        climb_state.elevation,
+                        climb_state.direction,
+                    ) {
+                        1
+                    } else {
+                        CLIMB_UP_STAMINA
+                    };
+                    if cost == 1 || map.grid_climbable[next_y][next_x] {
+                        if heights[next_y][next_x] == climb_state.elevation {
+                            // climb on top
+                            self.stamina.checked_sub(cost).map(|stamina| Self {
+                                stamina,
+                                grid_pos_x: (next_x) as u8,
+                                grid_pos_y: (next_y) as u8,
+                                state: PlayerState::Standing(climb_state.direction),
+                            })
+                        } else {
+                            // climb up
+                            self.stamina.checked_sub(cost).map(|stamina| Self {
+                                stamina,
+                                grid_pos_x: self.grid_pos_x,
+                                grid_pos_y: self.grid_pos_y,
+                                state: PlayerState::Climbing(ClimbingState {
+                                    direction: climb_state.direction,
+                                    elevation: climb_state.elevation + 1,
+                                }),
+                            })
+                        }
+                    } else {
+                        None
+                    }
+                }
+                CardinalDirection::East => {
+                    let (x_offset, y_offset) = match &climb_state.direction {
+                        CardinalDirection::North => (1, 0),
+                        CardinalDirection::East => (0, 1),
+                        CardinalDirection::South => (-1, 0),
+                        CardinalDirection::West => (0, -1),
+                    };
+                    // grid square moving to
+                    let next_x = x as i16 + x_offset;
+                    let next_y = y as i16 + y_offset;
+                    if next_x < 0
+                        || next_x as usize >= heights[0].len()
+                        || next_y < 0
+                        || next_y as usize >= heights.len()
+                    {
+                        // out of bounds
+                        return None;
+                    }
+                    let (x_offset, y_offset) = match &climb_state.direction {
+                        CardinalDirection::North => (1, -1),
+                        CardinalDirection::East => (1, 1),
+                        CardinalDirection::South => (-1, 1),
+                        CardinalDirection::West => (-1, -1),
+                    };
+                    // grid square that will be clung to
+                    let next_wall_x = x as i16 + x_offset;
+                    let next_wall_y = y as i16 + y_offset;
+                    if next_wall_x < 0
+                        || next_wall_x as usize >= heights[0].len()
+                        || next_wall_y < 0
+                        || next_wall_y as usize >= heights.len()
+                    {
+                        // out of bounds
+                        return None;
+                    }
+                    if !map.grid_climbable[next_wall_y as usize][next_wall_x as usize] {
+                        // not climbable
+                        return None;
+                    }
+                    if heights[next_y as usize][next_x as usize] >= climb_state.elevation
+                        || heights[next_wall_y as usize][next_wall_x as usize]
+                            < climb_state.elevation
+                    {
+                        // no valid climb spot to the east
+                        None
+                    } else {
+                        // move east
+                        self.stamina
+                            .checked_sub(CLIMB_SIDEWAYS_STAMINA)
+                            .map(|stamina| Self {
+                                stamina,
+                                grid_pos_x: next_x as u8,
+                                grid_pos_y: next_y as u8,
+                                state: PlayerState::Climbing(ClimbingState {
+                                    direction: climb_state.direction,
+                                    elevation: climb_state.elevation,
+                                }),
+                            })
+                    }
+                }
+                CardinalDirection::South => {
+                    if current_elevation + 1 == climb_state.elevation {
+                        // dismount wall
+                        Some(Self {
+                            stamina: self.stamina,
+                            grid_pos_x: self.grid_pos_x,
+                            grid_pos_y: self.grid_pos_y,
+                            state: PlayerState::Standing(climb_state.direction),
+                        })
+                    } else {
+                        // climb down
+                        let cost = if equipment.is_ladder_or_rope(
+                            self.grid_pos_x,
+                            self.grid_pos_y,
+                            climb_state.elevation,
+                            climb_state.direction,
+                        ) {
+                            1
+                        } else {
+                            CLIMB_DOWN_STAMINA
+                        };
+                        if let Some(stamina) = self.stamina.checked_sub(cost) {
+                            climb_state.elevation.checked_sub(1).map(|elevation| Self {
+                                stamina,
+                                grid_pos_x: self.grid_pos_x,
+                                grid_pos_y: self.grid_pos_y,
+                                state: PlayerState::Climbing(ClimbingState {
+                                    direction: climb_state.direction,
+                                    elevation,
+                                }),
+                            })
+                        } else {
+                            None
+                        }
+                    }
+                }
+                CardinalDirection::West => {
+                    let (x_offset, y_offset) = match &climb_state.direction {
+                        CardinalDirection::North => (-1, 0),
+                        CardinalDirection::East => (0, -1),
+                        CardinalDirection::South => (1, 0),
+                        CardinalDirection::West => (0, 1),
+                    };
+                    // grid square moving to
+                    let next_x = x as i16 + x_offset;
+                    let next_y = y as i16 + y_offset;
+                    if next_x < 0
+                        || next_x as usize >= heights[0].len()
+                        || next_y < 0
+                        || next_y as usize >= heights.len()
+                    {
+                        // out of bounds
+                        return None;
+                    }
+                    let (x_offset, y_offset) = match &climb_state.direction {
+                        CardinalDirection::North => (-1, -1),
+                        CardinalDirection::East => (1, -1),
+                        CardinalDirection::South => (1, 1),
+                        CardinalDirection::West => (-1, 1),
+                    };
+                    // grid square that will be clung to
+                    let next_wall_x = x as i16 + x_offset;
+                    let next_wall_y = y as i16 + y_offset;
+                    if next_wall_x < 0
+                        || next_wall_x as usize >= heights[0].len()
+                        || next_wall_y < 0
+                        || next_wall_y as usize >= heights.len()
+                    {
+                        // out of bounds
+                        return None;
+                    }
+                    if !map.grid_climbable[next_wall_y as usize][next_wall_x as usize] {
+                        // not climbable
+                        return None;
+                    }
+                    if heights[next_y as usize][next_x as usize] >= climb_state.elevation
+                        || heights[next_wall_y as usize][next_wall_x as usize]
+                            < climb_state.elevation
+                    {
+                        // no valid climb spot to the west
+                        None
+                    } else {
+                        // move west
+                        self.stamina
+                            .checked_sub(CLIMB_SIDEWAYS_STAMINA)
+                            .map(|stamina| Self {
+                                stamina,
+                                grid_pos_x: next_x as u8,
+                                grid_pos_y: next_y as u8,
+                                state: PlayerState::Climbing(ClimbingState {
+                                    direction: climb_state.direction,
+                                    elevation: climb_state.elevation,
+                                }),
+                            })
+                    }
+                }
+            },
+            PlayerState::StandingOnLadder(ladder_state) => {
+                let (new_x, new_y) = match direction {
+                    CardinalDirection::North => (x, y - 1),
+                    CardinalDirection::East => (x + 1, y),
+                    CardinalDirection::South => (x, y + 1),
+                    CardinalDirection::West => (x - 1, y),
+                };
+                #[allow(clippy::comparison_chain)]
+                if heights[new_y][new_x] == ladder_state.elevation {
+                    {
+                        // move off ladder
+                        self.stamina.checked_sub(MOVE_STAMINA).map(|stamina| Self {
+                            stamina,
+                            grid_pos_x: new_x as u8,
+                            grid_pos_y: new_y as u8,
+                            state: PlayerState::Standing(direction),
+                        })
+                    }
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// The direction the player is facing
+    pub fn direction(&self) -> CardinalDirection {
+        match &self.state {
+            PlayerState::Standing(direction) => *direction,
+            PlayerState::Climbing(climb_state) => climb_state.direction,
+            PlayerState::StandingOnLadder(ladder_state) => ladder_state.direction,
+        }
+    }
+
+    pub fn has_direction_changed(&self, other: &Player) -> bool {
+        if let PlayerState::Standing(dir1) = self.state {
+            if let PlayerState::Standing(dir2) = other.state {
+                return dir1 != dir2;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{CaveData, CaveLock, RopeKey, VerticalLadderKey};
+
+    fn standing(x: u8, y: u8, direction: CardinalDirection, stamina: u16) -> Player {
+        Player {
+            stamina,
+            grid_pos_x: x,
+            grid_pos_y: y,
+            state: PlayerState::Standing(direction),
+        }
+    }
+
+    fn climbing(x: u8, y: u8, direction: CardinalDirection, elevation: u8, stamina: u16) -> Player {
+        Player {
+            stamina,
+            grid_pos_x: x,
+            grid_pos_y: y,
+            state: PlayerState::Climbing(ClimbingState {
+                direction,
+                elevation,
+            }),
+        }
+    }
+
+    /// A cliff two high along the north edge, with a climbable face in the middle
+    fn cliff() -> Map {
+        Map::from_heights(&[&[3, 3, 3], &[1, 1, 1]], &[(1, 0), (2, 0)])
+    }
+
+    fn go(player: &Player, direction: CardinalDirection, map: &Map) -> Option<Player> {
+        player.go(
+            direction,
+            map,
+            &PlacedEquipment::default(),
+            &BTreeSet::new(),
+        )
+    }
+
+    #[test]
+    fn turning_costs_nothing() {
+        let map = Map::from_heights(&[&[1, 1], &[1, 1]], &[]);
+        let player = standing(0, 0, CardinalDirection::South, 5);
+        assert_eq!(
+            go(&player, CardinalDirection::East, &map),
+            Some(standing(0, 0, CardinalDirection::East, 5))
+        );
+    }
+
+    #[test]
+    fn walking_on_flat_ground() {
+        let map = Map::from_heights(&[&[1, 1], &[1, 1]], &[]);
+        let player = standing(0, 0, CardinalDirection::South, 5);
+        assert_eq!(
+            go(&player, CardinalDirection::South, &map),
+            Some(standing(0, 1, CardinalDirection::South, 4))
+        );
+        let tired = standing(0, 0, CardinalDirection::South, 0);
+        assert_eq!(go(&tired, CardinalDirection::South, &map), None);
+    }
+
+    #[test]
+    fn walking_off_the_map() {
+        let map = Map::from_heights(&[&[1, 1], &[1, 1]], &[]);
+        for (x, y, direction) in [
+            (0, 0, CardinalDirection::North),
+            (0, 0, CardinalDirection::West),
+            (1, 1, CardinalDirection::South),
+            (1, 1, CardinalDirection::East),
+        ] {
+            let player = standing(x, y, direction, 5);
+            assert_eq!(go(&player, direction, &map), None, "{:?}", direction);
+        }
+    }
+
+    #[test]
+    fn climbing_up_a_cliff() {
+        let map = cliff();
+        let player = standing(1, 1, CardinalDirection::North, 10);
+        let player = go(&player, CardinalDirection::North, &map).unwrap();
+        assert_eq!(player, climbing(1, 1, CardinalDirection::North, 2, 10));
+        let player = go(&player, CardinalDirection::North, &map).unwrap();
+        assert_eq!(
+            player,
+            climbing(1, 1, CardinalDirection::North, 3, 10 - CLIMB_UP_STAMINA)
+        );
+        let player = go(&player, CardinalDirection::North, &map).unwrap();
+        assert_eq!(
+            player,
+            standing(1, 0, CardinalDirection::North, 10 - 2 * CLIMB_UP_STAMINA)
+        );
+    }
+
+    #[test]
+    fn climbing_needs_a_climbable_wall_and_stamina() {
+        let map = cliff();
+        let player = standing(0, 1, CardinalDirection::North, 10);
+        assert_eq!(go(&player, CardinalDirection::North, &map), None);
+        let tired = climbing(1, 1, CardinalDirection::North, 2, CLIMB_UP_STAMINA - 1);
+        assert_eq!(go(&tired, CardinalDirection::North, &map), None);
+    }
+
+    #[test]
+    fn climbing_down_and_letting_go() {
+        let map = cliff();
+        let player = climbing(1, 1, CardinalDirection::North, 3, 10);
+        let player = go(&player, CardinalDirection::South, &map).unwrap();
+        assert_eq!(
+            player,
+            climbing(1, 1, CardinalDirection::North, 2, 10 - CLIMB_DOWN_STAMINA)
+        );
+        // one above the ground -> step off the wall for free
+        let player = go(&player, CardinalDirection::South, &map).unwrap();
+        assert_eq!(
+            player,
+            standing(1, 1, CardinalDirection::North, 10 - CLIMB_DOWN_STAMINA)
+        );
+    }
+
+    #[test]
+    fn climbing_down_from_the_top() {
+        let map = cliff();
+        let player = standing(1, 0, CardinalDirection::South, 10);
+        assert_eq!(
+            go(&player, CardinalDirection::South, &map),
+            Some(climbing(
+                1,
+                1,
+                CardinalDirection::North,
+                3,
+                10 - CLIMB_DOWN_STAMINA
+            ))
+        );
+        // the top of the west end isn't climbable
+        let player = standing(0, 0, CardinalDirection::South, 10);
+        assert_eq!(go(&player, CardinalDirection::South, &map), None);
+    }
+
+    #[test]
+    fn climbing_sideways() {
+        let map = cliff();
+        let player = climbing(1, 1, CardinalDirection::North, 2, 10);
+        assert_eq!(
+            go(&player, CardinalDirection::East, &map),
+            Some(climbing(
+                2,
+                1,
+                CardinalDirection::North,
+                2,
+                10 - CLIMB_SIDEWAYS_STAMINA
+            ))
+        );
+        // the wall to the west isn't climbable
+        assert_eq!(go(&player, CardinalDirection::West, &map), None);
+        // and there is no more wall past the east edge
+        let player = climbing(2, 1, CardinalDirection::North, 2, 10);
+        assert_eq!(go(&player, CardinalDirection::East, &map), None);
+    }
+
+    #[test]
+    fn ladders_and_ropes_make_climbing_cheap() {
+        // not climbable at all without equipment
+        let map = Map::from_heights(&[&[3], &[1]], &[]);
+        let mut ladder = PlacedEquipment::default();
+        ladder.vertical_ladders.insert(VerticalLadderKey {
+            x: 0,
+            y: 0,
+            height: 1,
+            direction: CardinalDirection::North,
+        });
+        let mut rope = PlacedEquipment::default();
+        rope.ropes.insert(RopeKey {
+            x: 0,
+            y: 0,
+            direction: CardinalDirection::South,
+        });
+        for equipment in [ladder, rope] {
+            let mut player = standing(0, 1, CardinalDirection::North, 10);
+            for _ in 0..3 {
+                player = player
+                    .go(CardinalDirection::North, &map, &equipment, &BTreeSet::new())
+                    .unwrap();
+            }
+            assert_eq!(player, standing(0, 0, CardinalDirection::North, 8));
+
+            let mut player = standing(0, 0, CardinalDirection::South, 10);
+            for _ in 0..3 {
+                player = player
+                    .go(CardinalDirection::South, &map, &equipment, &BTreeSet::new())
+                    .unwrap();
+            }
+            assert_eq!(player, standing(0, 1, CardinalDirection::North, 8));
+        }
+    }
+
+    #[test]
+    fn crossing_a_horizontal_ladder() {
+        let map = Map::from_heights(&[&[1, 0, 1], &[1, 1, 1]], &[]);
+        let mut equipment = PlacedEquipment::default();
+        equipment.horizontal_ladders.insert(HorizontalLadderKey {
+            x: 1,
+            y: 0,
+            height: 1,
+            alignment: Alignment::Xaxis,
+        });
+        let player = standing(0, 0, CardinalDirection::East, 10);
+        assert_eq!(go(&player, CardinalDirection::East, &map), None);
+
+        let on_ladder = player
+            .go(CardinalDirection::East, &map, &equipment, &BTreeSet::new())
+            .unwrap();
+        assert_eq!(
+            on_ladder.state,
+            PlayerState::StandingOnLadder(LadderState {
+                direction: CardinalDirection::East,
+                elevation: 1,
+                alignment: Alignment::Xaxis,
+            })
+        );
+        assert_eq!((on_ladder.grid_pos_x, on_ladder.grid_pos_y), (1, 0));
+        // the edge of the map is no way off a ladder
+        assert_eq!(
+            on_ladder.go(CardinalDirection::North, &map, &equipment, &BTreeSet::new()),
+            None
+        );
+        assert_eq!(
+            on_ladder.go(CardinalDirection::East, &map, &equipment, &BTreeSet::new()),
+            Some(standing(2, 0, CardinalDirection::East, 8))
+        );
+    }
+
+    #[test]
+    fn caves_need_their_gem() {
+        let mut map = Map::from_heights(&[&[1, 1, 1], &[1, 1, 1]], &[]);
+        map.caves = vec![CaveData {
+            first_pos: (0, 1),
+            second_pos: (2, 1),
+            entrance: CardinalDirection::North,
+            lock: CaveLock::Gem(GemColour::Blue),
+        }];
+        let equipment = PlacedEquipment::default();
+        let red = BTreeSet::from([GemColour::Red]);
+        let blue = BTreeSet::from([GemColour::Blue]);
+
+        let player = standing(0, 1, CardinalDirection::North, 5);
+        assert_eq!(
+            player.go(CardinalDirection::North, &map, &equipment, &red),
+            None
+        );
+        assert_eq!(
+            player.go(CardinalDirection::North, &map, &equipment, &blue),
+            Some(standing(2, 1, CardinalDirection::North, 4))
+        );
+
+        // and back again the same way
+        let player = standing(2, 1, CardinalDirection::North, 5);
+        assert_eq!(
+            player.go(CardinalDirection::North, &map, &equipment, &blue),
+            Some(standing(0, 1, CardinalDirection::North, 4))
+        );
+    }
+
+    #[test]
+    fn caves_can_face_any_way() {
+        let mut map = Map::from_heights(&[&[1, 1, 1], &[1, 1, 1]], &[]);
+        map.caves = vec![
+            CaveData {
+                first_pos: (0, 0),
+                second_pos: (2, 1),
+                entrance: CardinalDirection::East,
+                lock: CaveLock::Open,
+            },
+            CaveData {
+                first_pos: (2, 0),
+                second_pos: (0, 1),
+                entrance: CardinalDirection::West,
+                lock: CaveLock::AnyGem,
+            },
+        ];
+        let equipment = PlacedEquipment::default();
+        let gems = BTreeSet::new();
+
+        // walking past the mouth the wrong way is just a walk
+        let player = standing(0, 0, CardinalDirection::South, 5);
+        assert_eq!(
+            player.go(CardinalDirection::South, &map, &equipment, &gems),
+            Some(standing(0, 1, CardinalDirection::South, 4))
+        );
+        let player = standing(0, 0, CardinalDirection::East, 5);
+        assert_eq!(
+            player.go(CardinalDirection::East, &map, &equipment, &gems),
+            Some(standing(2, 1, CardinalDirection::East, 4))
+        );
+        // even into the edge of the map
+        let player = standing(2, 1, CardinalDirection::East, 5);
+        assert_eq!(
+            player.go(CardinalDirection::East, &map, &equipment, &gems),
+            Some(standing(0, 0, CardinalDirection::East, 4))
+        );
+
+        let player = standing(0, 1, CardinalDirection::West, 5);
+        assert_eq!(
+            player.go(CardinalDirection::West, &map, &equipment, &gems),
+            None
+        );
+        assert_eq!(
+            player.go(
+                CardinalDirection::West,
+                &map,
+                &equipment,
+                &BTreeSet::from([GemColour::Yellow])
+            ),
+            Some(standing(2, 0, CardinalDirection::West, 4))
+        );
+    }
+}
@@ -0,0 +1,412 @@
+use std::{fmt, io, ops::Deref};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    level_manager::LevelManager,
+    rating::Finish,
+    rules::Puzzle,
+    states::{editor::EditorLevel, level::level_transition, GameState},
+};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaveGame::load(default_store("save")))
+            .add_systems(
+                OnEnter(GameState::LevelTransition),
+                // playtesting an edited level doesn't count towards the campaign
+                record_progress
+                    .before(level_transition)
+                    .run_if(not(resource_exists::<EditorLevel>())),
+            );
+    }
+}
+
+/// Campaign progress that is kept between sessions
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    /// The level Continue starts from
+    pub current_level: usize,
+    /// Indexed by the level's position in the campaign
+    pub levels: Vec<LevelRecord>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelRecord {
+    pub completed: bool,
+    pub scale: bool,
+    /// Most stamina left over when reaching the flag
+    pub best_stamina: Option<u16>,
+    /// Fewest moves taken to reach the flag
+    pub best_moves: Option<u16>,
+    /// Most stars a finish has been given
+    pub stars: u8,
+}
+
+impl SaveData {
+    /// Whether there is anything to continue from
+    pub fn has_progress(&self) -> bool {
+        self.current_level > 0 || self.levels.iter().any(|record| record.completed)
+    }
+
+    /// Remember a level being finished, keeping the best of each result, and move on to the
+    /// next one unless a later level has already been reached
+    pub fn complete_level(&mut self, index: usize, finish: Finish, stars: u8) {
+        if self.levels.len() <= index {
+            self.levels.resize_with(index + 1, Default::default);
+        }
+        let record = &mut self.levels[index];
+        record.completed = true;
+        record.scale |= finish.scale;
+        record.best_stamina = Some(
+            record
+                .best_stamina
+                .map_or(finish.stamina_left, |best| best.max(finish.stamina_left)),
+        );
+        record.best_moves = Some(
+            record
+                .best_moves
+                .map_or(finish.moves, |best| best.min(finish.moves)),
+        );
+        record.stars = record.stars.max(stars);
+        self.current_level = self.current_level.max(index + 1);
+    }
+
+    /// Number of scales collected in the levels before the given one
+    pub fn scales_before(&self, index: usize) -> u8 {
+        self.levels
+            .iter()
+            .take(index)
+            .filter(|record| record.scale)
+            .count() as u8
+    }
+
+    /// Levels open up one at a time as the one before is finished
+    pub fn is_unlocked(&self, index: usize) -> bool {
+        index <= self.current_level
+            || self
+                .levels
+                .get(index - 1)
+                .is_some_and(|record| record.completed)
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Syntax(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// The browser didn't allow access to local storage
+    #[cfg(target_arch = "wasm32")]
+    StorageUnavailable,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{}", e),
+            SaveError::Syntax(e) => write!(f, "invalid save file: {}", e),
+            SaveError::Serialize(e) => write!(f, "couldn't write save data: {}", e),
+            #[cfg(target_arch = "wasm32")]
+            SaveError::StorageUnavailable => write!(f, "local storage isn't available"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(value: io::Error) -> Self {
+        SaveError::Io(value)
+    }
+}
+
+/// Somewhere to keep the save between sessions
+pub trait SaveStore: Send + Sync {
+    /// The saved contents, or `None` if nothing has been saved yet
+    fn read(&self) -> Result<Option<String>, SaveError>;
+    fn write(&mut self, contents: &str) -> Result<(), SaveError>;
+}
+
+/// Keeps the save for as long as the game is running. Used by tests, and when there is
+/// nowhere else to put it.
+#[derive(Debug, Default)]
+pub struct MemoryStore(pub Option<String>);
+
+impl SaveStore for MemoryStore {
+    fn read(&self) -> Result<Option<String>, SaveError> {
+        Ok(self.0.clone())
+    }
+
+    fn write(&mut self, contents: &str) -> Result<(), SaveError> {
+        self.0 = Some(contents.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::file::{data_dir, FileStore};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod file {
+    use std::{
+        env, fs, io,
+        path::{Path, PathBuf},
+    };
+
+    use super::{SaveError, SaveStore};
+
+    /// A file in the platform's data directory
+    #[derive(Debug)]
+    pub struct FileStore {
+        pub path: PathBuf,
+    }
+
+    /// The game's own directory in the platform's data directory
+    pub fn data_dir() -> Option<PathBuf> {
+        let data_dir = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            env::var_os("HOME")
+                .map(|home| Path::new(&home).join("Library").join("Application Support"))
+        } else {
+            env::var_os("XDG_DATA_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        }?;
+        Some(data_dir.join("dragonscale-peaks"))
+    }
+
+    impl FileStore {
+        pub fn in_data_dir(name: &str) -> Option<Self> {
+            Some(Self {
+                path: data_dir()?.join(name).with_extension("ron"),
+            })
+        }
+    }
+
+    impl SaveStore for FileStore {
+        fn read(&self) -> Result<Option<String>, SaveError> {
+            match fs::read_to_string(&self.path) {
+                Ok(contents) => Ok(Some(contents)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        }
+
+        fn write(&mut self, contents: &str) -> Result<(), SaveError> {
+            if let Some(directory) = self.path.parent() {
+                fs::create_dir_all(directory)?;
+            }
+            // write the whole file before replacing the old one, so a crash can't leave half a save
+            let temporary = self.path.with_extension("ron.tmp");
+            fs::write(&temporary, contents)?;
+            fs::rename(&temporary, &self.path)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use self::local_storage::LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+mod local_storage {
+    use super::{SaveError, SaveStore};
+
+    /// An item in the browser's local storage
+    #[derive(Debug)]
+    pub struct LocalStorage {
+        key: String,
+    }
+
+    impl LocalStorage {
+        pub fn new(name: &str) -> Self {
+            Self {
+                key: format!("dragonscale-peaks.{}", name),
+            }
+        }
+    }
+
+    fn storage() -> Result<web_sys::Storage, SaveError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or(SaveError::StorageUnavailable)
+    }
+
+    impl SaveStore for LocalStorage {
+        fn read(&self) -> Result<Option<String>, SaveError> {
+            storage()?
+                .get_item(&self.key)
+                .map_err(|_| SaveError::StorageUnavailable)
+        }
+
+        fn write(&mut self, contents: &str) -> Result<(), SaveError> {
+            storage()?
+                .set_item(&self.key, contents)
+                .map_err(|_| SaveError::StorageUnavailable)
+        }
+    }
+}
+
+/// Where the game keeps something by the given name, like `save` for the campaign progress
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_store(name: &str) -> Box<dyn SaveStore> {
+    match FileStore::in_data_dir(name) {
+        Some(store) => Box::new(store),
+        None => {
+            warn!("No data directory found, {} won't be kept", name);
+            Box::<MemoryStore>::default()
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn default_store(name: &str) -> Box<dyn SaveStore> {
+    Box::new(LocalStorage::new(name))
+}
+
+/// Data kept between sessions, along with where it is kept
+#[derive(Resource)]
+pub struct Stored<T> {
+    pub data: T,
+    store: Box<dyn SaveStore>,
+}
+
+/// The campaign progress
+pub type SaveGame = Stored<SaveData>;
+
+impl<T: Default + Serialize + DeserializeOwned> Stored<T> {
+    /// Read the data from the store, starting afresh if there isn't any or it can't be read
+    pub fn load(store: Box<dyn SaveStore>) -> Self {
+        let data = store
+            .read()
+            .and_then(|contents| {
+                contents.map_or(Ok(T::default()), |contents| {
+                    ron::from_str(&contents).map_err(SaveError::Syntax)
+                })
+            })
+            .unwrap_or_else(|e| {
+                warn!("Couldn't load saved data: {}", e);
+                T::default()
+            });
+        Self { data, store }
+    }
+
+    pub fn save(&mut self) -> Result<(), SaveError> {
+        let contents = ron::ser::to_string_pretty(&self.data, ron::ser::PrettyConfig::default())
+            .map_err(SaveError::Serialize)?;
+        self.store.write(&contents)
+    }
+}
+
+impl<T> Deref for Stored<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data
+    }
+}
+
+/// Runs as a finished level is left, while the puzzle still holds how it ended
+fn record_progress(
+    puzzle: Res<Puzzle>,
+    level_manager: Res<LevelManager>,
+    mut save_game: ResMut<SaveGame>,
+) {
+    // generated levels aren't part of the campaign
+    if level_manager.generated.is_some() {
+        return;
+    }
+    let index = level_manager.current;
+    if puzzle.is_complete() {
+        let finish = Finish::of(&puzzle);
+        let stars = finish.stars(level_manager.get_current_level().par.as_ref());
+        save_game.data.complete_level(index, finish, stars);
+    } else {
+        // skipped with F1, which moves on without counting as finishing the level
+        save_game.data.current_level = save_game.data.current_level.max(index + 1);
+    }
+    if let Err(e) = save_game.save() {
+        warn!("Couldn't save progress: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finish(stamina_left: u16, moves: u16, scale: bool) -> Finish {
+        Finish {
+            moves,
+            stamina_left,
+            scale,
+        }
+    }
+
+    #[test]
+    fn nothing_saved_yet() {
+        let save_game = SaveGame::load(Box::<MemoryStore>::default());
+        assert_eq!(save_game.data, SaveData::default());
+        assert!(!save_game.data.has_progress());
+    }
+
+    #[test]
+    fn progress_survives_a_restart() {
+        let mut save_game = SaveGame::load(Box::<MemoryStore>::default());
+        save_game.data.complete_level(0, finish(4, 10, true), 2);
+        save_game.data.complete_level(1, finish(2, 12, false), 1);
+        save_game.save().unwrap();
+
+        let contents = save_game.store.read().unwrap();
+        let reloaded = SaveGame::load(Box::new(MemoryStore(contents)));
+        assert_eq!(reloaded.data, save_game.data);
+        assert_eq!(reloaded.data.current_level, 2);
+        assert_eq!(reloaded.data.scales_before(2), 1);
+    }
+
+    #[test]
+    fn replaying_keeps_the_best_result() {
+        let mut data = SaveData::default();
+        data.complete_level(2, finish(5, 12, true), 2);
+        data.complete_level(2, finish(7, 14, false), 2);
+        data.complete_level(2, finish(1, 10, false), 1);
+        let record = &data.levels[2];
+        assert_eq!(record.best_stamina, Some(7));
+        assert_eq!(record.best_moves, Some(10));
+        assert_eq!(record.stars, 2);
+        assert!(record.scale);
+        assert!(!data.levels[0].completed);
+    }
+
+    #[test]
+    fn finishing_a_level_unlocks_the_next() {
+        let mut data = SaveData::default();
+        assert!(data.is_unlocked(0));
+        assert!(!data.is_unlocked(1));
+        data.complete_level(0, finish(3, 8, false), 1);
+        assert!(data.is_unlocked(1));
+        assert!(!data.is_unlocked(2));
+
+        // replaying an earlier level keeps the later ones open, and Continue where it was
+        for index in 1..4 {
+            data.complete_level(index, finish(3, 8, false), 1);
+        }
+        data.complete_level(0, finish(3, 8, false), 1);
+        assert_eq!(data.current_level, 4);
+        assert!(data.is_unlocked(4));
+        assert!(!data.is_unlocked(5));
+    }
+
+    #[test]
+    fn unreadable_saves_start_afresh() {
+        let save_game = SaveGame::load(Box::new(MemoryStore(Some("not a save".to_string()))));
+        assert_eq!(save_game.data, SaveData::default());
+    }
+}
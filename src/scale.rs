@@ -3,9 +3,8 @@ use bevy_kira_audio::{AudioChannel, AudioControl};
 
 use crate::{
     audio::{AudioAssets, SoundChannel},
-    level_manager::LevelManager,
-    player::{Player, PlayerHistory, PlayerHistoryEvent, PlayerState},
-    states::level::DespawnOnTransition,
+    rules::Puzzle,
+    states::{level::DespawnOnTransition, loading::ModelAssets},
     util::Spin,
 };
 
@@ -40,41 +39,34 @@ pub fn rotation(mut query: Query<(&mut Transform, &Spin)>, time: Res<Time>) {
     }
 }
 
-pub fn check_if_at_scale(
+/// Mirror whether the scale has been picked up, putting it back if that gets undone
+pub fn sync_scale(
     mut commands: Commands,
-    player: Query<&Player>,
-    mut player_history: ResMut<PlayerHistory>,
-    level_manager: Res<LevelManager>,
+    puzzle: Res<Puzzle>,
     scale_entities: Query<Entity, With<Scale>>,
     mut scale_counter: ResMut<ScaleCounter>,
+    model_assets: Res<ModelAssets>,
     sound_channel: Res<AudioChannel<SoundChannel>>,
     audio_assets: Res<AudioAssets>,
 ) {
-    let map = &level_manager.get_current_level().map;
+    let map = &puzzle.map;
     if let Some((x, y)) = map.scale_pos {
-        let player = player
-            .get_single()
-            .expect("There should only be one player");
-
-        let mut at_scale = player.grid_pos_x == x && player.grid_pos_y == y;
-        if let PlayerState::Climbing(climb_state) = &player.state {
-            at_scale &= map.grid_heights[y as usize][x as usize] == climb_state.elevation - 1;
-        }
-
-        if at_scale {
+        if puzzle.state.has_scale {
             for entity in scale_entities.iter() {
                 // should only be a single iteration
                 commands.entity(entity).despawn_recursive();
                 sound_channel.play(audio_assets.pickup.clone());
-                if let Some(PlayerHistoryEvent::PlayerMove(old_player)) = player_history.0.pop() {
-                    player_history
-                        .0
-                        .push(PlayerHistoryEvent::PlayerMoveToScale(old_player));
-                } else {
-                    warn!("Failed to add scale pickup event to history");
-                }
                 scale_counter.0 += 1;
             }
+        } else if scale_entities.is_empty() {
+            spawn_scale(
+                &mut commands,
+                x,
+                y,
+                map.grid_heights[y as usize][x as usize],
+                model_assets.scale.clone(),
+            );
+            scale_counter.0 -= 1;
         }
     }
 }
@@ -0,0 +1,199 @@
+//! The player's settings for sound, the window, graphics and language, kept between sessions and
+//! applied whenever they change.
+
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowMode},
+};
+use bevy_kira_audio::{AudioChannel, AudioControl};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::{MusicChannel, SoundChannel},
+    save::{default_store, Stored},
+};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = Settings::load(default_store("settings"));
+        app.insert_resource(settings.data.msaa())
+            .insert_resource(settings)
+            .add_systems(
+                Update,
+                (apply_volumes, apply_window_mode, apply_graphics)
+                    .run_if(resource_changed::<Settings>()),
+            );
+    }
+}
+
+/// The settings, kept between sessions
+pub type Settings = Stored<SettingsData>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SettingsData {
+    /// From 0 for silent to 1 for full volume
+    pub music_volume: f64,
+    pub sound_volume: f64,
+    pub fullscreen: bool,
+    /// Samples taken per pixel for anti-aliasing, 1 for none
+    pub msaa_samples: u32,
+    pub shadows: bool,
+    /// The code of the language to show text in, such as `en`
+    pub language: String,
+}
+
+impl Default for SettingsData {
+    fn default() -> Self {
+        Self {
+            music_volume: 1.0,
+            sound_volume: 1.0,
+            fullscreen: false,
+            msaa_samples: 4,
+            shadows: true,
+            language: "en".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Volume {
+    Music,
+    Sound,
+}
+
+/// How much the volume buttons turn a volume up or down
+pub const VOLUME_STEP: f64 = 0.1;
+
+/// The anti-aliasing that can be picked. WebGL2 only does 4 samples or none.
+#[cfg(not(target_arch = "wasm32"))]
+pub const MSAA_SAMPLES: &[u32] = &[1, 2, 4, 8];
+#[cfg(target_arch = "wasm32")]
+pub const MSAA_SAMPLES: &[u32] = &[1, 4];
+
+impl SettingsData {
+    pub fn volume(&self, volume: Volume) -> f64 {
+        match volume {
+            Volume::Music => self.music_volume,
+            Volume::Sound => self.sound_volume,
+        }
+    }
+
+    /// Set a volume, kept between silent and full
+    pub fn set_volume(&mut self, volume: Volume, value: f64) {
+        let value = value.clamp(0.0, 1.0);
+        match volume {
+            Volume::Music => self.music_volume = value,
+            Volume::Sound => self.sound_volume = value,
+        }
+    }
+
+    /// Turn a volume up or down by a step, landing on a whole step
+    pub fn step_volume(&mut self, volume: Volume, steps: f64) {
+        let stepped = (self.volume(volume) / VOLUME_STEP + steps).round() * VOLUME_STEP;
+        self.set_volume(volume, stepped);
+    }
+
+    /// The anti-aliasing to use, falling back to the default for a sample count that can't be
+    /// picked here, such as one saved on another platform
+    pub fn msaa(&self) -> Msaa {
+        if !MSAA_SAMPLES.contains(&self.msaa_samples) {
+            return Msaa::Sample4;
+        }
+        match self.msaa_samples {
+            1 => Msaa::Off,
+            2 => Msaa::Sample2,
+            8 => Msaa::Sample8,
+            _ => Msaa::Sample4,
+        }
+    }
+
+    /// Go on to the next anti-aliasing that can be picked, wrapping back round to none
+    pub fn next_msaa(&mut self) {
+        let current = self.msaa().samples();
+        self.msaa_samples = MSAA_SAMPLES
+            .iter()
+            .copied()
+            .find(|&samples| samples > current)
+            .unwrap_or(MSAA_SAMPLES[0]);
+    }
+}
+
+fn apply_volumes(
+    settings: Res<Settings>,
+    music_channel: Res<AudioChannel<MusicChannel>>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+) {
+    music_channel.set_volume(settings.data.music_volume);
+    sound_channel.set_volume(settings.data.sound_volume);
+}
+
+fn apply_window_mode(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let mode = if settings.data.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    for mut window in &mut windows {
+        // leave the window alone unless it needs to change
+        if window.mode != mode {
+            window.mode = mode;
+        }
+    }
+}
+
+/// Lights spawned later read the shadow setting themselves
+fn apply_graphics(
+    settings: Res<Settings>,
+    mut msaa: ResMut<Msaa>,
+    mut lights: Query<&mut DirectionalLight>,
+) {
+    let wanted = settings.data.msaa();
+    if *msaa != wanted {
+        *msaa = wanted;
+    }
+    for mut light in &mut lights {
+        light.shadows_enabled = settings.data.shadows;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volumes_step_and_stay_in_range() {
+        let mut data = SettingsData::default();
+        data.step_volume(Volume::Music, 1.0);
+        assert_eq!(data.music_volume, 1.0);
+        data.set_volume(Volume::Sound, 0.73);
+        data.step_volume(Volume::Sound, -1.0);
+        assert!((data.sound_volume - 0.6).abs() < 1e-9);
+        for _ in 0..10 {
+            data.step_volume(Volume::Sound, -1.0);
+        }
+        assert_eq!(data.sound_volume, 0.0);
+    }
+
+    #[test]
+    fn anti_aliasing_cycles_through_what_can_be_picked() {
+        let mut data = SettingsData::default();
+        let mut seen = vec![data.msaa_samples];
+        for _ in 1..MSAA_SAMPLES.len() {
+            data.next_msaa();
+            seen.push(data.msaa_samples);
+        }
+        data.next_msaa();
+        assert_eq!(data.msaa_samples, 4);
+        seen.sort();
+        assert_eq!(seen, MSAA_SAMPLES);
+
+        data.msaa_samples = 3;
+        assert_eq!(data.msaa(), Msaa::Sample4);
+    }
+}
@@ -0,0 +1,726 @@
+//! Headless A* search over the puzzle rules, guided by a lower bound on the moves left, used to
+//! check that levels can be completed and to find the shortest way of doing so.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+};
+
+use crate::{
+    rules::{
+        Action, CardinalDirection, CaveData, Inventory, Map, PlayerState, PuzzleState,
+        LADDER_WEIGHT, POTION_STAMINA, POTION_WEIGHT, REWIND_WEIGHT, ROPE_WEIGHT, RUNE_COUNTDOWN,
+    },
+    states::level::Level,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// Reach the flag
+    Flag,
+    /// Reach the flag after collecting the scale (same as `Flag` on levels without one)
+    FlagWithScale,
+}
+
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub loadout: Inventory,
+    pub actions: Vec<Action>,
+    pub collects_scale: bool,
+}
+
+fn covers(inventory: &Inventory, other: &Inventory) -> bool {
+    inventory.ladder_count >= other.ladder_count
+        && inventory.rope_count >= other.rope_count
+        && inventory.potion_count >= other.potion_count
+        && inventory.rewind_count >= other.rewind_count
+}
+
+/// The parts of a state where having more is never worse
+#[derive(Debug, Clone)]
+struct Resources {
+    stamina: u16,
+    inventory: Inventory,
+    // stamina stored in each rune, in the order they were placed
+    rune_stamina: Vec<u16>,
+}
+
+impl Resources {
+    fn covers(&self, other: &Resources) -> bool {
+        self.stamina >= other.stamina
+            && covers(&self.inventory, &other.inventory)
+            && self
+                .rune_stamina
+                .iter()
+                .zip(other.rune_stamina.iter())
+                .all(|(a, b)| a >= b)
+    }
+}
+
+/// Separate a state into where it is and what it has left
+fn split(state: &PuzzleState) -> (PuzzleState, Resources) {
+    let resources = Resources {
+        stamina: state.player.stamina,
+        inventory: state.inventory,
+        rune_stamina: state.runes.iter().map(|rune| rune.stamina).collect(),
+    };
+    let mut key = state.clone();
+    key.player.stamina = 0;
+    key.inventory = Inventory::default();
+    key.moves = 0;
+    for rune in key.runes.iter_mut() {
+        rune.stamina = 0;
+    }
+    (key, resources)
+}
+
+/// Every combination of unlocked equipment that fits in the level's weight budget
+pub fn loadouts(level: &Level) -> Vec<Inventory> {
+    let budget = level.weight_budget;
+    let max = |unlocked: bool, weight: u8| if unlocked { budget / weight } else { 0 };
+    let mut loadouts = Vec::new();
+    for ladders in 0..=max(level.ladder_unlocked, LADDER_WEIGHT) {
+        for ropes in 0..=max(level.rope_unlocked, ROPE_WEIGHT) {
+            for potions in 0..=max(level.potion_unlocked, POTION_WEIGHT) {
+                for runes in 0..=max(level.rewind_unlocked, REWIND_WEIGHT) {
+                    let weight = ladders * LADDER_WEIGHT
+                        + ropes * ROPE_WEIGHT
+                        + potions * POTION_WEIGHT
+                        + runes * REWIND_WEIGHT;
+                    if weight <= budget {
+                        loadouts.push(Inventory {
+                            ladder_count: ladders,
+                            rope_count: ropes,
+                            potion_count: potions,
+                            rewind_count: runes,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    loadouts
+}
+
+const ACTIONS: [Action; 8] = [
+    Action::Move(CardinalDirection::North),
+    Action::Move(CardinalDirection::East),
+    Action::Move(CardinalDirection::South),
+    Action::Move(CardinalDirection::West),
+    Action::UseLadder,
+    Action::PlaceRope,
+    Action::PlaceRune,
+    Action::DrinkPotion,
+];
+
+/// Find the shortest sequence of key presses (over every possible loadout) that completes the
+/// level, or `None` if it can't be done.
+pub fn solve(level: &Level, goal: Goal) -> Option<Solution> {
+    solve_within(level, goal, usize::MAX)
+}
+
+/// Like [`solve`], but giving up with `None` once more than `most_nodes` nodes have been found
+pub fn solve_within(level: &Level, goal: Goal, most_nodes: usize) -> Option<Solution> {
+    let map = &level.map;
+    let mut search = Search::new(map, goal);
+    for loadout in loadouts(level) {
+        let mut state = PuzzleState::new(map, level.stamina_budget);
+        state.inventory = loadout;
+        search.visit(state, None, 0);
+    }
+    match search.run(map, most_nodes) {
+        Ending::Solved(index) => Some(search.rebuild(index)),
+        Ending::Impossible | Ending::GaveUp => None,
+    }
+}
+
+/// Why a level can no longer be finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadEnd {
+    /// No stamina left, and nothing that could give any back
+    OutOfStamina,
+    /// The flag is further away than the stamina left could take the climber, even walking
+    /// straight there
+    TooFar,
+    /// There is stamina left, but no way to the flag that it and the equipment can pay for
+    NoWay,
+}
+
+impl DeadEnd {
+    /// The key of the explanation shown to the player in the locale files
+    pub fn explanation_key(&self) -> &'static str {
+        match self {
+            DeadEnd::OutOfStamina => "failure.out_of_stamina",
+            DeadEnd::TooFar => "failure.too_far",
+            DeadEnd::NoWay => "failure.no_way",
+        }
+    }
+}
+
+/// Searches made during play give up after this many nodes, rather than keep the player waiting
+const MOST_NODES_IN_PLAY: usize = 100_000;
+
+/// Search for the quickest way to the flag from where things stand, with the equipment left
+fn search_from(map: &Map, state: &PuzzleState) -> (Search, Ending) {
+    let mut search = Search::new(map, Goal::Flag);
+    search.visit(state.clone(), None, 0);
+    let ending = search.run(map, MOST_NODES_IN_PLAY);
+    (search, ending)
+}
+
+/// Whether the level can no longer be finished from here, whatever is done with the equipment
+/// left. `None` if it still can be, or if that takes too long to find out.
+pub fn dead_end(map: &Map, state: &PuzzleState) -> Option<DeadEnd> {
+    let (search, ending) = search_from(map, state);
+    match ending {
+        Ending::Solved(_) | Ending::GaveUp => None,
+        Ending::Impossible => Some(
+            if state.player.stamina == 0
+                && state.inventory.potion_count == 0
+                && state.runes.is_empty()
+            {
+                DeadEnd::OutOfStamina
+            } else if search.estimate(state).is_none() {
+                DeadEnd::TooFar
+            } else {
+                DeadEnd::NoWay
+            },
+        ),
+    }
+}
+
+/// What to do next to get to the flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hint {
+    /// The first action of the quickest way there
+    Next(Action),
+    /// There's no way there from here, but there is after undoing this many moves
+    Undo(usize),
+    /// There's no way there even from the start, with the equipment picked
+    Restart,
+    /// The search took too long to tell
+    Unknown,
+}
+
+/// Work out a hint from where things stand, given the states left so far (oldest first) to
+/// look back through if the level can't be finished from here
+pub fn hint(map: &Map, state: &PuzzleState, earlier: &[PuzzleState]) -> Hint {
+    match search_from(map, state) {
+        (search, Ending::Solved(index)) => {
+            return match search.actions(index).first() {
+                Some(&action) => Hint::Next(action),
+                // already at the flag
+                None => Hint::Unknown,
+            };
+        }
+        (_, Ending::GaveUp) => return Hint::Unknown,
+        (_, Ending::Impossible) => {}
+    }
+    for (undos, state) in earlier.iter().rev().enumerate() {
+        match search_from(map, state).1 {
+            Ending::Solved(_) => return Hint::Undo(undos + 1),
+            Ending::GaveUp => return Hint::Unknown,
+            Ending::Impossible => {}
+        }
+    }
+    Hint::Restart
+}
+
+/// The cheapest walk from where things stand to a square, as the moves to press. Walks that cost
+/// less stamina come first, then ones with fewer presses. Equipment isn't used, though ladders
+/// and ropes already in place are. `None` if the square can't be reached, or would take too long
+/// to look for.
+pub fn route(map: &Map, from: &PuzzleState, to: (u8, u8)) -> Option<Vec<CardinalDirection>> {
+    cheapest_walks(map, from, |state, _| {
+        (state.player.grid_pos_x, state.player.grid_pos_y) == to
+    })
+}
+
+/// Somewhere the climber can get to: on top of a square, or clinging to the wall on one side of
+/// it at some elevation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Spot {
+    Square(u8, u8),
+    Face {
+        x: u8,
+        y: u8,
+        direction: CardinalDirection,
+        elevation: u8,
+    },
+}
+
+/// The least stamina it takes to get to every spot that can be walked or climbed to from where
+/// things stand, without using more equipment. Spots missing from the map can't be reached.
+pub fn reachable(map: &Map, from: &PuzzleState) -> HashMap<Spot, u16> {
+    let mut costs = HashMap::new();
+    cheapest_walks(map, from, |state, spent| {
+        let player = &state.player;
+        let spot = match &player.state {
+            PlayerState::Standing(_) => Spot::Square(player.grid_pos_x, player.grid_pos_y),
+            PlayerState::Climbing(climbing) => Spot::Face {
+                x: player.grid_pos_x,
+                y: player.grid_pos_y,
+                direction: climbing.direction,
+                elevation: climbing.elevation,
+            },
+            // hanging over a drop, rather than on a square of its own
+            PlayerState::StandingOnLadder(_) => return false,
+        };
+        costs.entry(spot).or_insert(spent);
+        false
+    });
+    costs
+}
+
+/// Go through the states that can be walked to, cheapest first, until `stop` is given one it
+/// wants along with the stamina spent getting there. Returns the moves to that state.
+fn cheapest_walks(
+    map: &Map,
+    from: &PuzzleState,
+    mut stop: impl FnMut(&PuzzleState, u16) -> bool,
+) -> Option<Vec<CardinalDirection>> {
+    const MOST_NODES: usize = 20_000;
+    // every state found, with the one it was reached from
+    let mut nodes: Vec<(PuzzleState, Option<(usize, CardinalDirection)>)> = Vec::new();
+    let mut visited = HashSet::new();
+    let mut queue = BinaryHeap::new();
+    let mut start = from.clone();
+    start.moves = 0;
+    visited.insert(start.clone());
+    nodes.push((start, None));
+    queue.push(Reverse((0, 0, 0)));
+
+    while let Some(Reverse((spent, presses, index))) = queue.pop() {
+        if stop(&nodes[index].0, spent) {
+            let mut moves = Vec::new();
+            let mut index = index;
+            while let Some((parent, direction)) = nodes[index].1 {
+                moves.push(direction);
+                index = parent;
+            }
+            moves.reverse();
+            return Some(moves);
+        }
+        if nodes.len() > MOST_NODES {
+            return None;
+        }
+        for direction in [
+            CardinalDirection::North,
+            CardinalDirection::East,
+            CardinalDirection::South,
+            CardinalDirection::West,
+        ] {
+            let Ok((mut next, _)) = nodes[index].0.apply(map, Action::Move(direction)) else {
+                continue;
+            };
+            next.moves = 0;
+            if visited.insert(next.clone()) {
+                let spent = from.player.stamina.saturating_sub(next.player.stamina);
+                queue.push(Reverse((spent, presses + 1, nodes.len())));
+                nodes.push((next, Some((index, direction))));
+            }
+        }
+    }
+    None
+}
+
+/// Number of steps between every pair of squares, ignoring heights and stamina. Every step (and
+/// every trip through a cave) takes at least one move and one stamina, so these are lower
+/// bounds for both.
+struct Distances {
+    width: usize,
+    squares: Vec<(u8, u8)>,
+    /// Going through only the caves that are open from the start
+    walking: Vec<u16>,
+    /// Going through every cave
+    through_cave: Vec<u16>,
+    /// Where the gems are, when there are caves that need them
+    gems: Vec<(u8, u8)>,
+}
+
+impl Distances {
+    fn new(map: &Map) -> Self {
+        let width = map.width();
+        let squares: Vec<(u8, u8)> = (0..map.height())
+            .flat_map(|y| (0..width).map(move |x| (x as u8, y as u8)))
+            .collect();
+        Self {
+            width,
+            walking: all_pairs(map, &squares, |cave| cave.lock.is_open()),
+            through_cave: all_pairs(map, &squares, |_| true),
+            gems: if map.caves.iter().all(|cave| cave.lock.is_open()) {
+                Vec::new()
+            } else {
+                map.gems.iter().map(|gem| gem.pos).collect()
+            },
+            squares,
+        }
+    }
+
+    fn lookup(&self, table: &[u16], from: (u8, u8), to: (u8, u8)) -> u16 {
+        let index = |(x, y): (u8, u8)| y as usize * self.width + x as usize;
+        table[index(from) * self.squares.len() + index(to)]
+    }
+
+    /// Steps from one square to another, picking up a gem on the way if the caves need one.
+    /// Once any gem has been found every cave is counted as open, which can only make the
+    /// distance shorter.
+    fn get(&self, from: (u8, u8), to: (u8, u8), has_gem: bool) -> u16 {
+        if has_gem {
+            return self.lookup(&self.through_cave, from, to);
+        }
+        self.gems
+            .iter()
+            .map(|&gem_pos| {
+                self.lookup(&self.walking, from, gem_pos)
+                    + self.lookup(&self.through_cave, gem_pos, to)
+            })
+            .fold(self.lookup(&self.walking, from, to), u16::min)
+    }
+
+    /// Steps from one square to another via a square in between
+    fn via(&self, from: (u8, u8), middle: (u8, u8), to: (u8, u8), has_gem: bool) -> u16 {
+        if has_gem {
+            return self.get(from, middle, true) + self.get(middle, to, true);
+        }
+        self.gems
+            .iter()
+            .map(|&gem_pos| {
+                self.lookup(&self.walking, from, gem_pos)
+                    + self.lookup(&self.through_cave, gem_pos, middle)
+                    + self.lookup(&self.through_cave, middle, to)
+            })
+            .fold(
+                self.lookup(&self.walking, from, middle) + self.get(middle, to, false),
+                u16::min,
+            )
+    }
+}
+
+fn all_pairs(map: &Map, squares: &[(u8, u8)], passable: impl Fn(&CaveData) -> bool) -> Vec<u16> {
+    let width = map.width();
+    let mut steps = vec![u16::MAX; squares.len() * squares.len()];
+    for (start_index, start) in squares.iter().enumerate() {
+        let steps = &mut steps[start_index * squares.len()..][..squares.len()];
+        steps[start_index] = 0;
+        let mut queue = VecDeque::from([*start]);
+        while let Some((x, y)) = queue.pop_front() {
+            let distance = steps[y as usize * width + x as usize];
+            let mut neighbours: Vec<(u8, u8)> = [
+                CardinalDirection::North,
+                CardinalDirection::East,
+                CardinalDirection::South,
+                CardinalDirection::West,
+            ]
+            .into_iter()
+            .filter_map(|direction| map.step(x, y, direction, 1))
+            .collect();
+            neighbours.extend(
+                map.caves
+                    .iter()
+                    .filter(|cave| passable(cave))
+                    .filter_map(|cave| cave.other_end((x, y))),
+            );
+            for (next_x, next_y) in neighbours {
+                let next = &mut steps[next_y as usize * width + next_x as usize];
+                if *next == u16::MAX {
+                    *next = distance + 1;
+                    queue.push_back((next_x, next_y));
+                }
+            }
+        }
+    }
+    steps
+}
+
+/// How a search came to an end
+enum Ending {
+    /// Found the node that completes the level in the fewest moves
+    Solved(usize),
+    /// Ran out of nodes to look at
+    Impossible,
+    /// Looked at too many nodes to carry on
+    GaveUp,
+}
+
+struct Search {
+    distances: Distances,
+    flag_pos: (u8, u8),
+    // only set when the scale has to be collected
+    scale_pos: Option<(u8, u8)>,
+    // every state found so far, with the index of the state it was reached from
+    nodes: Vec<(PuzzleState, Option<(usize, Action)>)>,
+    // stamina and equipment left for each position found, along with the number of moves it
+    // took. Nodes that are no better off than one found earlier are dropped
+    visited: HashMap<PuzzleState, Vec<(Resources, u16)>>,
+    // ordered by the fewest total moves the node could still complete the level in
+    queue: BinaryHeap<(Reverse<u16>, u16, usize)>,
+}
+
+impl Search {
+    fn new(map: &Map, goal: Goal) -> Self {
+        Self {
+            distances: Distances::new(map),
+            flag_pos: map.flag_pos,
+            scale_pos: map.scale_pos.filter(|_| goal == Goal::FlagWithScale),
+            nodes: Vec::new(),
+            visited: HashMap::new(),
+            queue: BinaryHeap::new(),
+        }
+    }
+
+    /// Take nodes off the queue, closest to completing the level first, until one does
+    fn run(&mut self, map: &Map, most_nodes: usize) -> Ending {
+        while let Some((_, moves, index)) = self.queue.pop() {
+            if self.nodes.len() > most_nodes {
+                return Ending::GaveUp;
+            }
+            let state = self.nodes[index].0.clone();
+            if state.at_flag(map) {
+                // reaching the flag ends the level
+                if state.has_scale || self.scale_pos.is_none() {
+                    return Ending::Solved(index);
+                }
+                continue;
+            }
+            for action in ACTIONS {
+                if let Ok((next, _)) = state.apply(map, action) {
+                    self.visit(next, Some((index, action)), moves + 1);
+                }
+            }
+        }
+        Ending::Impossible
+    }
+
+    /// A lower bound on the moves left to complete the level from this node, or `None` if it
+    /// can't be completed
+    fn estimate(&self, node: &PuzzleState) -> Option<u16> {
+        let distances = &self.distances;
+        let has_gem = !node.gems.is_empty();
+        let position = (node.player.grid_pos_x, node.player.grid_pos_y);
+        // the walk to the flag has to start either here or at a rune, with enough stamina
+        let potion_stamina = node.inventory.potion_count as u16 * POTION_STAMINA;
+        if node.player.stamina + potion_stamina < distances.get(position, self.flag_pos, has_gem)
+            && node.runes.iter().all(|rune| {
+                rune.stamina + potion_stamina
+                    < distances.get((rune.x, rune.y), self.flag_pos, has_gem)
+            })
+        {
+            return None;
+        }
+
+        // moves to get to a square, either by walking or being pulled back by a rune
+        let reach = |to: (u8, u8)| {
+            node.runes
+                .iter()
+                .map(|rune| rune.countdown as u16 + distances.get((rune.x, rune.y), to, has_gem))
+                .fold(distances.get(position, to, has_gem), u16::min)
+        };
+        let Some(scale_pos) = self.scale_pos.filter(|_| !node.has_scale) else {
+            return Some(reach(self.flag_pos));
+        };
+
+        if node.runes.is_empty() && node.inventory.rewind_count == 0 {
+            let walk = distances.via(position, scale_pos, self.flag_pos, has_gem);
+            if node.player.stamina + potion_stamina < walk {
+                return None;
+            }
+            return Some(walk);
+        }
+        // walk to the scale and then the flag, maybe getting pulled back by a rune after the
+        // scale. Assume a gem has been found by then, since it could be on the way
+        let mut scale_to_flag = distances.get(scale_pos, self.flag_pos, true);
+        for rune in node.runes.iter() {
+            scale_to_flag =
+                scale_to_flag.min(1 + distances.get((rune.x, rune.y), self.flag_pos, true));
+        }
+        let mut estimate = reach(scale_pos) + scale_to_flag;
+        if node.inventory.rewind_count > 0 {
+            // or drop a rune close enough to the scale to be pulled back to it afterwards
+            for &rune_pos in distances.squares.iter() {
+                let rune_to_scale = distances.get(rune_pos, scale_pos, true);
+                if rune_to_scale < RUNE_COUNTDOWN as u16 {
+                    estimate = estimate.min(
+                        reach(rune_pos)
+                            + rune_to_scale
+                            + 1
+                            + distances.get(rune_pos, self.flag_pos, true),
+                    );
+                }
+            }
+        }
+        Some(estimate)
+    }
+
+    fn visit(&mut self, node: PuzzleState, parent: Option<(usize, Action)>, moves: u16) {
+        let Some(estimate) = self.estimate(&node) else {
+            return;
+        };
+        let (key, resources) = split(&node);
+        let found = self.visited.entry(key).or_default();
+        if found
+            .iter()
+            .any(|(other, other_moves)| other.covers(&resources) && *other_moves <= moves)
+        {
+            return;
+        }
+        found.retain(|(other, other_moves)| !(resources.covers(other) && moves <= *other_moves));
+        found.push((resources, moves));
+        // prefer nodes further along when the estimates are equal
+        self.queue
+            .push((Reverse(moves + estimate), moves, self.nodes.len()));
+        self.nodes.push((node, parent));
+    }
+
+    /// The actions that led to a node
+    fn actions(&self, mut index: usize) -> Vec<Action> {
+        let mut actions = Vec::new();
+        while let Some((parent, action)) = self.nodes[index].1 {
+            actions.push(action);
+            index = parent;
+        }
+        actions.reverse();
+        actions
+    }
+
+    fn rebuild(&self, mut index: usize) -> Solution {
+        let collects_scale = self.nodes[index].0.has_scale;
+        let mut actions = Vec::new();
+        // the search starts out carrying as much as possible, so only count what actually gets used
+        let mut least = self.nodes[index].0.inventory;
+        while let Some((parent, action)) = self.nodes[index].1 {
+            actions.push(action);
+            index = parent;
+            let inventory = &self.nodes[index].0.inventory;
+            least = Inventory {
+                ladder_count: least.ladder_count.min(inventory.ladder_count),
+                rope_count: least.rope_count.min(inventory.rope_count),
+                potion_count: least.potion_count.min(inventory.potion_count),
+                rewind_count: least.rewind_count.min(inventory.rewind_count),
+            };
+        }
+        actions.reverse();
+        let carried = self.nodes[index].0.inventory;
+        Solution {
+            loadout: Inventory {
+                ladder_count: carried.ladder_count - least.ladder_count,
+                rope_count: carried.rope_count - least.rope_count,
+                potion_count: carried.potion_count - least.potion_count,
+                rewind_count: carried.rewind_count - least.rewind_count,
+            },
+            actions,
+            collects_scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{level_manager::read_campaign, rating::Par};
+
+    /// Where the moves end up, or `None` if one of them can't be made
+    fn walk(map: &Map, mut state: PuzzleState, moves: &[CardinalDirection]) -> Option<PuzzleState> {
+        for &direction in moves {
+            state = state.apply(map, Action::Move(direction)).ok()?.0;
+        }
+        Some(state)
+    }
+
+    #[test]
+    fn routes_go_around_what_costs_more() {
+        // climbing the 3 costs more than walking round it
+        let map = Map::from_heights(&[&[1, 3, 1], &[1, 1, 1], &[1, 1, 1]], &[(1, 0)]);
+        let start = PuzzleState::new(&map, 20);
+        let moves = route(&map, &start, (2, 0)).unwrap();
+        let end = walk(&map, start.clone(), &moves).unwrap();
+        assert_eq!((end.player.grid_pos_x, end.player.grid_pos_y), (2, 0));
+        assert_eq!(end.player.stamina, 16);
+        assert_eq!(route(&map, &start, (0, 0)), Some(Vec::new()));
+    }
+
+    #[test]
+    fn unreachable_squares_have_no_route() {
+        let map = Map::from_heights(&[&[1, 9, 1]], &[]);
+        let start = PuzzleState::new(&map, 20);
+        assert_eq!(route(&map, &start, (2, 0)), None);
+        assert_eq!(route(&map, &start, (1, 0)), None);
+    }
+
+    #[test]
+    fn dead_ends_are_found_and_explained() {
+        let map = Map::from_heights(&[&[1, 1, 1]], &[]);
+        let with_stamina = |stamina| PuzzleState::new(&map, stamina);
+        assert_eq!(dead_end(&map, &with_stamina(5)), None);
+        assert_eq!(
+            dead_end(&map, &with_stamina(0)),
+            Some(DeadEnd::OutOfStamina)
+        );
+        assert_eq!(dead_end(&map, &with_stamina(1)), Some(DeadEnd::TooFar));
+
+        // a potion still gets there
+        let mut state = with_stamina(0);
+        state.inventory.potion_count = 1;
+        assert_eq!(dead_end(&map, &state), None);
+
+        let map = Map::from_heights(&[&[1, 9, 1]], &[]);
+        assert_eq!(
+            dead_end(&map, &PuzzleState::new(&map, 20)),
+            Some(DeadEnd::NoWay)
+        );
+    }
+
+    #[test]
+    fn hints_point_the_way_or_back() {
+        let map = Map::from_heights(&[&[1, 1, 1], &[1, 1, 1]], &[]);
+        let start = PuzzleState::new(&map, 3);
+        // facing south, and the flag is in the south east corner
+        assert_eq!(
+            hint(&map, &start, &[]),
+            Hint::Next(Action::Move(CardinalDirection::South))
+        );
+
+        // stepping back leaves too little stamina, so that step has to be undone
+        let east = walk(&map, start.clone(), &[CardinalDirection::East; 2]).unwrap();
+        let back = walk(&map, east.clone(), &[CardinalDirection::West; 2]).unwrap();
+        assert_eq!(back.player.stamina, 1);
+        assert_eq!(hint(&map, &back, &[start.clone(), east]), Hint::Undo(1));
+        assert_eq!(hint(&map, &PuzzleState::new(&map, 2), &[]), Hint::Restart);
+    }
+
+    #[test]
+    fn reach_is_the_cheapest_cost_of_each_spot() {
+        let map = Map::from_heights(&[&[1, 3, 1], &[1, 1, 1], &[1, 1, 1]], &[(1, 0)]);
+        let costs = reachable(&map, &PuzzleState::new(&map, 20));
+        assert_eq!(costs[&Spot::Square(0, 0)], 0);
+        assert_eq!(costs[&Spot::Square(2, 0)], 4);
+        // on top of the 3: two climbs up after clinging on for free
+        assert_eq!(costs[&Spot::Square(1, 0)], 8);
+        let face = Spot::Face {
+            x: 0,
+            y: 0,
+            direction: CardinalDirection::East,
+            elevation: 2,
+        };
+        assert_eq!(costs[&face], 0);
+
+        // too tired to get past the first climb
+        let costs = reachable(&map, &PuzzleState::new(&map, 3));
+        assert!(!costs.contains_key(&Spot::Square(1, 0)));
+        assert!(costs.contains_key(&Spot::Square(2, 1)));
+    }
+
+    #[test]
+    fn every_campaign_level_can_be_finished_at_its_par() {
+        let manifest =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/levels/main.campaign.ron");
+        for level in read_campaign(&manifest).unwrap() {
+            let solution = solve(&level, Goal::Flag)
+                .unwrap_or_else(|| panic!("{} can't be finished", level.name));
+            // a par that is out of date gives the wrong stars
+            assert_eq!(level.par, Par::of(&level, &solution), "{}", level.name);
+        }
+    }
+}
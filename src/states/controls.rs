@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    controls::{Control, Controls, KeyBindings, KeyCombo},
+    localization::Localization,
+    ui::{
+        constants::{GREY, UI_YELLOW, UI_YELLOW_HOVER},
+        UiRoot,
+    },
+};
+
+use super::{loading::FontAssets, GameState};
+
+pub struct ControlsScreenPlugin;
+
+impl Plugin for ControlsScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(GameState::Controls), setup_controls_screen)
+            .add_systems(
+                Update,
+                (
+                    button_system,
+                    capture_key,
+                    update_binding_text.run_if(
+                        resource_changed::<Controls>().or_else(resource_changed::<Rebinding>()),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Controls)),
+            )
+            .add_systems(OnExit(GameState::Controls), cleanup_controls_screen);
+    }
+}
+
+#[derive(Component)]
+enum ControlsButton {
+    /// Wait for a key to bind to this control
+    Bind(Control),
+    Reset,
+    Back,
+}
+
+#[derive(Component)]
+struct ControlsRoot;
+
+/// The control waiting for a key press, if any
+#[derive(Debug, Default, Resource)]
+struct Rebinding(Option<Control>);
+
+fn setup_controls_screen(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    controls: Res<Controls>,
+    localization: Res<Localization>,
+) {
+    let text_style = |font_size: f32| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(20.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(ControlsRoot)
+        .insert(UiRoot)
+        .insert(Name::new("Controls UI"))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                localization.get("controls.instructions"),
+                text_style(20.0),
+            ));
+            // two controls to a row, each with its name and a button showing what it's bound to
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        display: Display::Grid,
+                        grid_template_columns: vec![
+                            GridTrack::px(180.0),
+                            GridTrack::px(240.0),
+                            GridTrack::px(180.0),
+                            GridTrack::px(240.0),
+                        ],
+                        row_gap: Val::Px(4.0),
+                        column_gap: Val::Px(10.0),
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for control in Control::ALL {
+                        parent.spawn(TextBundle::from_section(
+                            localization.get(control.name_key()),
+                            text_style(20.0),
+                        ));
+                        parent
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    height: Val::Px(30.0),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..Default::default()
+                                },
+                                background_color: GREY.into(),
+                                ..Default::default()
+                            })
+                            .insert(ControlsButton::Bind(control))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    binding_text(&controls, control),
+                                    text_style(20.0),
+                                ));
+                            });
+                    }
+                });
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(20.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (key, button) in [
+                        ("controls.reset", ControlsButton::Reset),
+                        ("common.back", ControlsButton::Back),
+                    ] {
+                        parent
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(220.0),
+                                    height: Val::Px(60.0),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..Default::default()
+                                },
+                                background_color: UI_YELLOW.into(),
+                                ..Default::default()
+                            })
+                            .insert(button)
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    localization.get(key),
+                                    text_style(40.0),
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+fn binding_text(bindings: &KeyBindings, control: Control) -> String {
+    let combos = bindings.combos(control);
+    if combos.is_empty() {
+        return "-".to_string();
+    }
+    combos
+        .iter()
+        .map(KeyCombo::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn button_system(
+    mut interaction_query: Query<
+        (&ControlsButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut rebinding: ResMut<Rebinding>,
+    mut controls: ResMut<Controls>,
+    mut next_state: ResMut<NextState<GameState>>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    for (button, interaction, mut color) in &mut interaction_query {
+        let idle = match button {
+            ControlsButton::Bind(_) => GREY,
+            ControlsButton::Reset | ControlsButton::Back => UI_YELLOW,
+        };
+        match *interaction {
+            Interaction::Pressed => {
+                sound_channel.play(audio_assets.pop.clone());
+                match button {
+                    ControlsButton::Bind(control) => {
+                        rebinding.0 = Some(*control);
+                    }
+                    ControlsButton::Reset => {
+                        rebinding.0 = None;
+                        controls.data = KeyBindings::default();
+                        if let Err(e) = controls.save() {
+                            warn!("Couldn't save controls: {}", e);
+                        }
+                    }
+                    ControlsButton::Back => {
+                        next_state.set(GameState::MainMenu);
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = UI_YELLOW_HOVER.into();
+            }
+            Interaction::None => {
+                *color = idle.into();
+            }
+        }
+    }
+}
+
+/// Bind the next key pressed while waiting for one. Escape cancels, or goes back to the menu
+/// when nothing is waiting.
+fn capture_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut controls: ResMut<Controls>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(control) = rebinding.0 else {
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            next_state.set(GameState::MainMenu);
+        }
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        rebinding.0 = None;
+        return;
+    }
+    // modifiers are held for the key they go with, rather than bound on their own
+    let Some(&key) = keyboard_input
+        .get_just_pressed()
+        .find(|&&key| !KeyCombo::is_modifier(key))
+    else {
+        return;
+    };
+    controls
+        .data
+        .rebind(control, KeyCombo::pressed(key, &keyboard_input));
+    if let Err(e) = controls.save() {
+        warn!("Couldn't save controls: {}", e);
+    }
+    rebinding.0 = None;
+}
+
+fn update_binding_text(
+    buttons: Query<(&ControlsButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+    controls: Res<Controls>,
+    rebinding: Res<Rebinding>,
+    localization: Res<Localization>,
+) {
+    for (button, children) in &buttons {
+        let ControlsButton::Bind(control) = button else {
+            continue;
+        };
+        let mut text = text_query.get_mut(children[0]).unwrap();
+        text.sections[0].value = if rebinding.0 == Some(*control) {
+            localization.get("controls.waiting")
+        } else {
+            binding_text(&controls, *control)
+        };
+    }
+}
+
+fn cleanup_controls_screen(
+    mut commands: Commands,
+    root: Query<Entity, With<ControlsRoot>>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    commands.entity(root.single()).despawn_recursive();
+    rebinding.0 = None;
+}
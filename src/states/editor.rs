@@ -0,0 +1,801 @@
+use std::{f32::consts::FRAC_PI_2, mem};
+
+use bevy::{
+    prelude::*, render::camera::ScalingMode, tasks::AsyncComputeTaskPool, window::PrimaryWindow,
+};
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    camera::MainCamera,
+    cave::{spawn_cave, spawn_gem, tint_gems},
+    controls::{Control, ControlInput, Controls, KeyBindings},
+    equipment::EquipmentEntities,
+    level_manager::{level_to_ron, LevelManager},
+    localization::Localization,
+    map::{pick_cell, spawn_terrain},
+    rating::Par,
+    rules::{CardinalDirection, CaveData, CaveLock, Gem, GemColour, Map},
+    scale::spawn_scale,
+    settings::Settings,
+    solver::{solve, Goal},
+    ui::{constants::UI_YELLOW, UiRoot},
+};
+
+use super::{
+    level::{animate_flag, DespawnOnTransition, Level},
+    loading::{FontAssets, ModelAssets},
+    transition::TransitionManager,
+    GameState, PauseState,
+};
+
+/// Terrain can't be raised any higher than this
+const MAX_HEIGHT: u8 = 20;
+
+/// Where exported levels are written, relative to the working directory
+#[cfg(not(target_arch = "wasm32"))]
+const EXPORT_PATH: &str = "edited_level.ron";
+
+pub struct LevelEditorPlugin;
+
+impl Plugin for LevelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Tool::default())
+            .add_systems(
+                OnEnter(GameState::Editor),
+                (setup_editor_ui, setup_editor_camera, rebuild_view),
+            )
+            .add_systems(
+                Update,
+                (
+                    select_tool,
+                    edit_cells,
+                    edit_budgets,
+                    rotate_camera,
+                    playtest,
+                    export_level,
+                    leave_editor,
+                    animate_flag,
+                    tint_gems,
+                    rebuild_view.run_if(resource_changed::<EditorLevel>()),
+                    update_editor_text.run_if(
+                        resource_changed::<EditorLevel>().or_else(resource_changed::<Tool>()),
+                    ),
+                )
+                    .run_if(in_state(GameState::Editor)),
+            )
+            .add_systems(
+                Update,
+                open_editor
+                    .run_if(in_state(GameState::Level))
+                    .run_if(in_state(PauseState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Editor), cleanup_editor);
+    }
+}
+
+/// The level being edited. While this exists, finishing a level goes back to the editor
+/// instead of on to the next one.
+#[derive(Debug, Resource)]
+pub struct EditorLevel(pub Level);
+
+/// What clicking on the map does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum Tool {
+    #[default]
+    Height,
+    Climbable,
+    Start,
+    Flag,
+    Scale,
+    /// Along with the first mouth of a new pair once it has been picked
+    Cave(Option<(u8, u8)>),
+    CaveEnd,
+    CaveLock,
+    Gem,
+}
+
+impl Tool {
+    const ALL: [Tool; 9] = [
+        Tool::Height,
+        Tool::Climbable,
+        Tool::Start,
+        Tool::Flag,
+        Tool::Scale,
+        Tool::Cave(None),
+        Tool::CaveEnd,
+        Tool::CaveLock,
+        Tool::Gem,
+    ];
+
+    /// The key of the tool's name in the locale files
+    fn name_key(&self) -> &'static str {
+        match self {
+            Tool::Height => "editor.tool.height",
+            Tool::Climbable => "editor.tool.climbable",
+            Tool::Start => "editor.tool.start",
+            Tool::Flag => "editor.tool.flag",
+            Tool::Scale => "editor.tool.scale",
+            Tool::Cave(_) => "editor.tool.cave",
+            Tool::CaveEnd => "editor.tool.cave_end",
+            Tool::CaveLock => "editor.tool.cave_lock",
+            Tool::Gem => "editor.tool.gem",
+        }
+    }
+}
+
+/// Edit the cell at (x, y) with a tool. The primary (left) click raises or places, the secondary
+/// (right) click lowers or removes. Returns whether the level changed; the tool can change too,
+/// when it is halfway through placing something.
+pub fn apply_tool(level: &mut Level, tool: &mut Tool, (x, y): (u8, u8), primary: bool) -> bool {
+    let map = &mut level.map;
+    let cell = (x, y);
+    match tool {
+        Tool::Height => {
+            let height = &mut map.grid_heights[y as usize][x as usize];
+            let new_height = if primary {
+                (*height + 1).min(MAX_HEIGHT)
+            } else {
+                height.saturating_sub(1).max(1)
+            };
+            let changed = new_height != *height;
+            *height = new_height;
+            changed
+        }
+        Tool::Climbable => {
+            let climbable = &mut map.grid_climbable[y as usize][x as usize];
+            let changed = *climbable != primary;
+            *climbable = primary;
+            changed
+        }
+        Tool::Start => primary && replace(&mut map.player_start_pos, cell),
+        Tool::Flag => primary && replace(&mut map.flag_pos, cell),
+        Tool::Scale => replace(&mut map.scale_pos, primary.then_some(cell)),
+        Tool::Cave(first) => {
+            let Some(index) = cave_index(map, cell) else {
+                // a new pair is placed one mouth at a time, on two different squares
+                match first.take() {
+                    Some(first_pos) if primary && first_pos != cell => {
+                        map.caves.push(CaveData {
+                            first_pos,
+                            second_pos: cell,
+                            entrance: CardinalDirection::North,
+                            lock: CaveLock::Open,
+                        });
+                        return true;
+                    }
+                    None if primary => *first = Some(cell),
+                    // clicking the first mouth again, or right clicking, lets go of it
+                    _ => {}
+                }
+                return false;
+            };
+            if primary {
+                let cave = &mut map.caves[index];
+                cave.entrance = cave.entrance.clockwise();
+            } else {
+                map.caves.remove(index);
+            }
+            true
+        }
+        // moves the other end of the pair placed last
+        Tool::CaveEnd => match cave_index(map, cell) {
+            Some(index) if !primary => {
+                map.caves.remove(index);
+                true
+            }
+            Some(_) => false,
+            None => {
+                primary
+                    && map
+                        .caves
+                        .last_mut()
+                        .is_some_and(|cave| replace(&mut cave.second_pos, cell))
+            }
+        },
+        // open, then any gem, then each colour in turn
+        Tool::CaveLock => {
+            let Some(index) = cave_index(map, cell) else {
+                return false;
+            };
+            let lock = &mut map.caves[index].lock;
+            let next = if !primary {
+                CaveLock::Open
+            } else {
+                match *lock {
+                    CaveLock::Open => CaveLock::AnyGem,
+                    CaveLock::AnyGem => CaveLock::Gem(GemColour::ALL[0]),
+                    CaveLock::Gem(colour) => GemColour::ALL
+                        .get(colour as usize + 1)
+                        .map_or(CaveLock::Open, |&next| CaveLock::Gem(next)),
+                }
+            };
+            replace(lock, next)
+        }
+        // there's at most one gem of each colour, so clicking one changes it to a colour not
+        // yet on the map
+        Tool::Gem => {
+            let index = map.gems.iter().position(|gem| gem.pos == cell);
+            if !primary {
+                return index.map(|index| map.gems.remove(index)).is_some();
+            }
+            // going round the colours from the one after the gem's own
+            let start = index.map_or(0, |index| map.gems[index].colour as usize + 1);
+            let unused = (0..GemColour::ALL.len())
+                .map(|offset| GemColour::ALL[(start + offset) % GemColour::ALL.len()])
+                .find(|&colour| !map.gems.iter().any(|gem| gem.colour == colour));
+            match (index, unused) {
+                (Some(index), Some(colour)) => replace(&mut map.gems[index].colour, colour),
+                (None, Some(colour)) => {
+                    map.gems.push(Gem { pos: cell, colour });
+                    true
+                }
+                (_, None) => false,
+            }
+        }
+    }
+}
+
+/// Which pair of caves has a mouth at the cell, if any
+fn cave_index(map: &Map, cell: (u8, u8)) -> Option<usize> {
+    map.caves
+        .iter()
+        .position(|cave| cave.other_end(cell).is_some())
+}
+
+fn replace<T: PartialEq>(target: &mut T, value: T) -> bool {
+    let changed = *target != value;
+    *target = value;
+    changed
+}
+
+#[derive(Component)]
+struct EditorUiRoot;
+
+#[derive(Component)]
+struct EditorText;
+
+/// Start editing the level being played, or go back to editing after a playtest
+fn open_editor(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    transition_manager: Res<TransitionManager>,
+    level_manager: Res<LevelManager>,
+    editor_level: Option<Res<EditorLevel>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F3)
+        && matches!(*transition_manager, TransitionManager::Normal)
+    {
+        if editor_level.is_none() {
+            commands.insert_resource(EditorLevel(level_manager.get_current_level().clone()));
+        }
+        next_state.set(GameState::Editor);
+    }
+}
+
+fn setup_editor_ui(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    editor_level: Res<EditorLevel>,
+    tool: Res<Tool>,
+    localization: Res<Localization>,
+    controls: Res<Controls>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(0.0),
+                top: Val::Px(0.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..Default::default()
+            },
+            background_color: UI_YELLOW.into(),
+            ..Default::default()
+        })
+        .insert(EditorUiRoot)
+        .insert(UiRoot)
+        .insert(Name::new("Editor UI"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    editor_text(&editor_level.0, *tool, &localization, &controls),
+                    TextStyle {
+                        font: font_assets.fira_sans.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(EditorText);
+        });
+}
+
+fn setup_editor_camera(
+    mut commands: Commands,
+    mut cameras: Query<(&mut Transform, &mut Projection, &mut MainCamera)>,
+) {
+    // the same view as when playing the level
+    if let Ok((mut camera_transform, mut camera_projection, mut camera)) = cameras.get_single_mut()
+    {
+        *camera_transform =
+            Transform::from_xyz(3.5, 8.5, 10.0).looking_at(Vec3::new(4.5, 3.5, 0.0), Vec3::Y);
+        *camera_projection = Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::FixedVertical(10.),
+            ..Default::default()
+        });
+        camera.direction = CardinalDirection::North;
+        camera.angle_change = 0.0;
+    }
+    // ladders and ropes left from a level are despawned along with the rest of its scene
+    commands.insert_resource(EquipmentEntities::default());
+}
+
+/// Replace everything in the scene with the level as it is now
+fn rebuild_view(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    entities: Query<Entity, With<DespawnOnTransition>>,
+    model_assets: Res<ModelAssets>,
+    editor_level: Res<EditorLevel>,
+    settings: Res<Settings>,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands
+        .spawn(DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                shadows_enabled: settings.data.shadows,
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(10.0, 20.0, 5.0).looking_at(Vec3::default(), Vec3::Y),
+            ..Default::default()
+        })
+        .insert(DespawnOnTransition);
+
+    let map = &editor_level.0.map;
+    spawn_terrain(&mut commands, &mut materials, &mut meshes, map);
+    let on_top = |(x, y): (u8, u8)| {
+        Transform::from_xyz(
+            x as f32,
+            map.grid_heights[y as usize][x as usize] as f32,
+            y as f32,
+        )
+    };
+    commands
+        .spawn(SceneBundle {
+            scene: model_assets.climber.clone(),
+            transform: on_top(map.player_start_pos),
+            ..Default::default()
+        })
+        .insert(Name::new("Start"))
+        .insert(DespawnOnTransition);
+    commands
+        .spawn(SceneBundle {
+            scene: model_assets.flag.clone(),
+            transform: on_top(map.flag_pos),
+            ..Default::default()
+        })
+        .insert(Name::new("Flag"))
+        .insert(DespawnOnTransition);
+    if let Some((x, y)) = map.scale_pos {
+        let height = map.grid_heights[y as usize][x as usize];
+        spawn_scale(&mut commands, x, y, height, model_assets.scale.clone());
+    }
+    for gem in &map.gems {
+        let (x, y) = gem.pos;
+        let height = map.grid_heights[y as usize][x as usize] as f32 + 0.3;
+        spawn_gem(
+            &mut commands,
+            x,
+            y,
+            height,
+            gem.colour,
+            model_assets.gem.clone(),
+        );
+    }
+    for cave in &map.caves {
+        spawn_cave(&mut commands, map, cave, &model_assets);
+    }
+}
+
+fn update_editor_text(
+    mut texts: Query<&mut Text, With<EditorText>>,
+    editor_level: Res<EditorLevel>,
+    tool: Res<Tool>,
+    localization: Res<Localization>,
+    controls: Res<Controls>,
+) {
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = editor_text(&editor_level.0, *tool, &localization, &controls);
+    }
+}
+
+/// The selected tool, the budgets, and a reminder of the controls
+fn editor_text(
+    level: &Level,
+    tool: Tool,
+    localization: &Localization,
+    controls: &KeyBindings,
+) -> String {
+    let unlocked: Vec<String> = [
+        (level.ladder_unlocked, "equipment.ladder"),
+        (level.rope_unlocked, "equipment.rope"),
+        (level.potion_unlocked, "equipment.potion"),
+        (level.rewind_unlocked, "equipment.rune"),
+    ]
+    .into_iter()
+    .filter(|(unlocked, _)| *unlocked)
+    .map(|(_, key)| localization.get(key))
+    .collect();
+    let tools: Vec<String> = Tool::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, option)| {
+            let name = localization.get(option.name_key());
+            if mem::discriminant(option) == mem::discriminant(&tool) {
+                format!("[{} {}]", index + 1, name)
+            } else {
+                format!("{} {}", index + 1, name)
+            }
+        })
+        .collect();
+    let picking = match tool {
+        Tool::Cave(Some(first)) => {
+            format!(
+                "  {}",
+                localization.format("editor.other_mouth", &[&format!("{:?}", first)])
+            )
+        }
+        _ => String::new(),
+    };
+    let unlocked = if unlocked.is_empty() {
+        localization.get("editor.nothing_unlocked")
+    } else {
+        unlocked.join(", ")
+    };
+    [
+        format!("{}{}", tools.join("  "), picking),
+        localization.format(
+            "editor.budgets",
+            &[&level.stamina_budget, &level.weight_budget],
+        ),
+        localization.format("editor.unlocked", &[&unlocked]),
+        localization.get("editor.clicks"),
+        localization.format("editor.keys", &[&controls.label(Control::Pause)]),
+    ]
+    .join("\n")
+}
+
+fn select_tool(keyboard_input: Res<Input<KeyCode>>, mut tool: ResMut<Tool>) {
+    let keys = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    for (key, option) in keys.into_iter().zip(Tool::ALL) {
+        if keyboard_input.just_pressed(key) && *tool != option {
+            *tool = option;
+        }
+    }
+}
+
+fn edit_cells(
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut tool: ResMut<Tool>,
+    mut editor_level: ResMut<EditorLevel>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    let primary = if mouse_input.just_pressed(MouseButton::Left) {
+        true
+    } else if mouse_input.just_pressed(MouseButton::Right) {
+        false
+    } else {
+        return;
+    };
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), cameras.get_single())
+    else {
+        return;
+    };
+    let Some(ray) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+    else {
+        return;
+    };
+    if let Some(cell) = pick_cell(&editor_level.0.map, ray) {
+        // only mark the level and tool as changed when they were, so the scene isn't rebuilt
+        // for nothing
+        let previous_tool = *tool;
+        if apply_tool(
+            &mut editor_level.bypass_change_detection().0,
+            tool.bypass_change_detection(),
+            cell,
+            primary,
+        ) {
+            editor_level.set_changed();
+            sound_channel.play(audio_assets.pop.clone());
+        }
+        if *tool != previous_tool {
+            tool.set_changed();
+        }
+    }
+}
+
+fn edit_budgets(keyboard_input: Res<Input<KeyCode>>, mut editor_level: ResMut<EditorLevel>) {
+    let pressed = |key| keyboard_input.just_pressed(key);
+    if !keyboard_input.any_just_pressed([
+        KeyCode::Up,
+        KeyCode::Down,
+        KeyCode::Left,
+        KeyCode::Right,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+    ]) {
+        return;
+    }
+    let level = &mut editor_level.0;
+    if pressed(KeyCode::Up) {
+        level.stamina_budget = level.stamina_budget.saturating_add(1);
+    }
+    if pressed(KeyCode::Down) {
+        level.stamina_budget = level.stamina_budget.saturating_sub(1);
+    }
+    if pressed(KeyCode::Right) {
+        level.weight_budget = level.weight_budget.saturating_add(1);
+    }
+    if pressed(KeyCode::Left) {
+        level.weight_budget = level.weight_budget.saturating_sub(1);
+    }
+    level.ladder_unlocked ^= pressed(KeyCode::F5);
+    level.rope_unlocked ^= pressed(KeyCode::F6);
+    level.potion_unlocked ^= pressed(KeyCode::F7);
+    level.rewind_unlocked ^= pressed(KeyCode::F8);
+}
+
+fn rotate_camera(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+    editor_level: Res<EditorLevel>,
+) {
+    let angle = if keyboard_input.just_pressed(KeyCode::Q) {
+        FRAC_PI_2
+    } else if keyboard_input.just_pressed(KeyCode::E) {
+        -FRAC_PI_2
+    } else {
+        return;
+    };
+    let midpoint = editor_level.0.map.midpoint();
+    for mut camera_transform in cameras.iter_mut() {
+        camera_transform.rotate_around(
+            Vec3::new(midpoint.0, 0.0, midpoint.1),
+            Quat::from_rotation_y(angle),
+        );
+    }
+}
+
+/// Play the level as it is, coming back to the editor once it is finished or F3 is pressed
+fn playtest(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut transition_manager: ResMut<TransitionManager>,
+    mut level_manager: ResMut<LevelManager>,
+    editor_level: Res<EditorLevel>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F3)
+        && matches!(*transition_manager, TransitionManager::Normal)
+    {
+        level_manager.playtest = Some(editor_level.0.clone());
+        sound_channel.play(audio_assets.woosh.clone());
+        *transition_manager = TransitionManager::TransitioningOutReload(0.0);
+    }
+}
+
+/// Write the level out in the level file format, and check in the background that it can be
+/// finished
+fn export_level(keyboard_input: Res<Input<KeyCode>>, editor_level: Res<EditorLevel>) {
+    let control_held = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !(control_held && keyboard_input.just_pressed(KeyCode::S)) {
+        return;
+    }
+    let level = editor_level.0.clone();
+    let contents = match level_to_ron(&level) {
+        Ok(contents) => contents,
+        Err(e) => {
+            warn!("Couldn't export the level: {}", e);
+            return;
+        }
+    };
+    info!("Exported level:\n{}", contents);
+    #[cfg(not(target_arch = "wasm32"))]
+    match std::fs::write(EXPORT_PATH, &contents) {
+        Ok(()) => info!("Level written to {}", EXPORT_PATH),
+        Err(e) => warn!("Couldn't write {}: {}", EXPORT_PATH, e),
+    }
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            match solve(&level, Goal::Flag) {
+                Some(solution) => info!(
+                    "The exported level can be finished in {} moves, for a par of {:?}",
+                    solution.actions.len(),
+                    Par::of(&level, &solution)
+                ),
+                None => warn!("The exported level can't be finished"),
+            }
+        })
+        .detach();
+}
+
+fn leave_editor(
+    mut commands: Commands,
+    input: ControlInput,
+    transition_manager: Res<TransitionManager>,
+    mut level_manager: ResMut<LevelManager>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if input.just_pressed(Control::Pause)
+        && matches!(*transition_manager, TransitionManager::Normal)
+    {
+        commands.remove_resource::<EditorLevel>();
+        level_manager.playtest = None;
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn cleanup_editor(
+    mut commands: Commands,
+    root: Query<Entity, With<EditorUiRoot>>,
+    entities: Query<Entity, With<DespawnOnTransition>>,
+) {
+    commands.entity(root.single()).despawn_recursive();
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::Map;
+
+    use super::*;
+
+    fn level() -> Level {
+        Level {
+            name: "Test".to_string(),
+            map: Map::from_heights(&[&[1, 2, 3], &[1, 2, 3]], &[]),
+            stamina_budget: 10,
+            weight_budget: 0,
+            ladder_unlocked: false,
+            rope_unlocked: false,
+            potion_unlocked: false,
+            rewind_unlocked: false,
+            par: None,
+            tutorial: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn heights_stay_on_the_map() {
+        let mut level = level();
+        assert!(apply_tool(&mut level, &mut Tool::Height, (0, 0), true));
+        assert_eq!(level.map.grid_heights[0][0], 2);
+        assert!(!apply_tool(&mut level, &mut Tool::Height, (0, 1), false));
+        assert_eq!(level.map.grid_heights[1][0], 1);
+
+        level.map.grid_heights[0][2] = MAX_HEIGHT;
+        assert!(!apply_tool(&mut level, &mut Tool::Height, (2, 0), true));
+        assert_eq!(level.map.grid_heights[0][2], MAX_HEIGHT);
+    }
+
+    #[test]
+    fn placing_and_removing() {
+        let mut level = level();
+        assert!(apply_tool(&mut level, &mut Tool::Climbable, (1, 1), true));
+        assert!(level.map.grid_climbable[1][1]);
+        assert!(!apply_tool(&mut level, &mut Tool::Climbable, (1, 1), true));
+
+        assert!(apply_tool(&mut level, &mut Tool::Scale, (2, 0), true));
+        assert_eq!(level.map.scale_pos, Some((2, 0)));
+        assert!(apply_tool(&mut level, &mut Tool::Scale, (2, 0), false));
+        assert_eq!(level.map.scale_pos, None);
+
+        // the start can be moved but not removed
+        assert!(apply_tool(&mut level, &mut Tool::Start, (1, 0), true));
+        assert!(!apply_tool(&mut level, &mut Tool::Start, (1, 0), false));
+        assert_eq!(level.map.player_start_pos, (1, 0));
+    }
+
+    #[test]
+    fn caves_are_placed_end_by_end() {
+        let mut level = level();
+        assert!(!apply_tool(&mut level, &mut Tool::CaveEnd, (2, 1), true));
+
+        // a new pair takes a click on each mouth, which can't be the same square
+        let mut tool = Tool::Cave(None);
+        assert!(!apply_tool(&mut level, &mut tool, (0, 1), true));
+        assert_eq!(tool, Tool::Cave(Some((0, 1))));
+        assert!(!apply_tool(&mut level, &mut tool, (0, 1), true));
+        assert_eq!(tool, Tool::Cave(None));
+        assert!(level.map.caves.is_empty());
+        assert!(!apply_tool(&mut level, &mut tool, (0, 1), true));
+        assert!(apply_tool(&mut level, &mut tool, (1, 1), true));
+        assert_eq!(
+            (level.map.caves[0].first_pos, level.map.caves[0].second_pos),
+            ((0, 1), (1, 1))
+        );
+        assert!(apply_tool(&mut level, &mut Tool::CaveEnd, (2, 1), true));
+        assert_eq!(level.map.caves[0].second_pos, (2, 1));
+        assert!(!apply_tool(&mut level, &mut Tool::CaveEnd, (0, 1), true));
+
+        // clicking either end turns the pair
+        assert!(apply_tool(&mut level, &mut tool, (2, 1), true));
+        assert_eq!(level.map.caves[0].entrance, CardinalDirection::East);
+
+        // a second pair can't share a square with the first
+        assert!(!apply_tool(&mut level, &mut tool, (1, 0), true));
+        assert!(apply_tool(&mut level, &mut tool, (0, 0), true));
+        assert!(!apply_tool(&mut level, &mut Tool::CaveEnd, (0, 1), true));
+        assert!(apply_tool(&mut level, &mut Tool::CaveEnd, (2, 0), true));
+        assert_eq!(level.map.caves.len(), 2);
+
+        assert!(apply_tool(&mut level, &mut Tool::CaveEnd, (0, 1), false));
+        assert_eq!(level.map.caves.len(), 1);
+        assert_eq!(level.map.caves[0].first_pos, (1, 0));
+        assert!(!apply_tool(&mut level, &mut tool, (0, 1), false));
+    }
+
+    #[test]
+    fn gems_and_locks_go_through_the_colours() {
+        let mut level = level();
+        assert!(!apply_tool(&mut level, &mut Tool::CaveLock, (0, 0), true));
+        let mut tool = Tool::Cave(None);
+        apply_tool(&mut level, &mut tool, (0, 0), true);
+        assert!(apply_tool(&mut level, &mut tool, (2, 1), true));
+        let locks: Vec<CaveLock> = (0..6)
+            .map(|_| {
+                apply_tool(&mut level, &mut Tool::CaveLock, (0, 0), true);
+                level.map.caves[0].lock
+            })
+            .collect();
+        assert_eq!(
+            locks,
+            [
+                CaveLock::AnyGem,
+                CaveLock::Gem(GemColour::Red),
+                CaveLock::Gem(GemColour::Blue),
+                CaveLock::Gem(GemColour::Green),
+                CaveLock::Gem(GemColour::Yellow),
+                CaveLock::Open,
+            ]
+        );
+
+        // every gem gets a colour of its own
+        assert!(apply_tool(&mut level, &mut Tool::Gem, (1, 0), true));
+        assert!(apply_tool(&mut level, &mut Tool::Gem, (2, 0), true));
+        assert!(apply_tool(&mut level, &mut Tool::Gem, (1, 0), true));
+        let colours: Vec<GemColour> = level.map.gems.iter().map(|gem| gem.colour).collect();
+        assert_eq!(colours, [GemColour::Green, GemColour::Blue]);
+        assert!(apply_tool(&mut level, &mut Tool::Gem, (0, 1), true));
+        assert!(apply_tool(&mut level, &mut Tool::Gem, (1, 1), true));
+        assert!(!apply_tool(&mut level, &mut Tool::Gem, (2, 1), true));
+        assert!(!apply_tool(&mut level, &mut Tool::Gem, (1, 1), true));
+
+        assert!(apply_tool(&mut level, &mut Tool::Gem, (1, 0), false));
+        assert!(!apply_tool(&mut level, &mut Tool::Gem, (1, 0), false));
+        assert_eq!(level.map.gems.len(), 3);
+    }
+}
@@ -1,21 +1,32 @@
-use bevy::{pbr::CascadeShadowConfigBuilder, prelude::*, render::camera::ScalingMode};
+use bevy::{
+    pbr::CascadeShadowConfigBuilder, prelude::*, render::camera::ScalingMode,
+    tasks::AsyncComputeTaskPool,
+};
 use bevy_kira_audio::{AudioChannel, AudioControl};
 
 use crate::{
     audio::{AudioAssets, SoundChannel},
     camera::{camera_rotation, MainCamera},
-    cave::{swap_cave_visibility, HasGem},
+    cave::{swap_cave_visibility, tint_gems, HeldGems},
     clouds::CloudMaterial,
-    equipment::{rewind::RewindRune, Inventory},
-    level_manager::LevelManager,
-    map::{create_map_on_level_load, Map},
-    player::{clear_player_history, PlayerHistory, PlayerHistoryEvent},
+    controls::{Control, ControlInput},
+    equipment::EquipmentEntities,
+    generator::generate,
+    level_manager::{LevelManager, PendingLevel},
+    map::create_map_on_level_load,
+    rating::Par,
+    replay::Playback,
+    rules::{CardinalDirection, Map, Puzzle},
     scale::{rotation, spawn_scale, ScaleCounter},
+    settings::Settings,
+    solver::{solve, Goal},
+    tutorial::TutorialPrompt,
     ui::keys::StaminaCosts,
-    util::CardinalDirection,
 };
 
-use super::{loading::ModelAssets, transition::TransitionManager, GameState};
+use super::{
+    editor::EditorLevel, loading::ModelAssets, transition::TransitionManager, GameState, PauseState,
+};
 
 pub struct LevelPlugin;
 
@@ -23,37 +34,41 @@ impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(StaminaCosts::default())
             .insert_resource(ScaleCounter::default())
-            .insert_resource(HasGem::default())
+            .insert_resource(HeldGems::default())
             .register_type::<LevelManager>()
             .add_systems(
                 OnEnter(GameState::Level),
-                (create_map_on_level_load, setup_scene),
+                (start_puzzle, create_map_on_level_load, setup_scene),
             )
             .add_systems(
                 Update,
                 (
                     animate_flag,
-                    reload_level,
-                    skip_level,
+                    (reload_level, skip_level)
+                        .run_if(not(resource_exists::<Playback>()))
+                        .run_if(in_state(PauseState::Playing)),
+                    solve_current_level,
                     rotation,
                     camera_rotation,
-                    swap_cave_visibility.run_if(resource_changed::<HasGem>()),
+                    swap_cave_visibility.run_if(resource_changed::<HeldGems>()),
+                    tint_gems,
                 )
                     .run_if(in_state(GameState::Level)),
             )
+            .add_systems(OnEnter(GameState::LevelTransition), level_transition)
             .add_systems(
-                OnEnter(GameState::LevelTransition),
-                (level_transition, clear_player_history),
+                Update,
+                start_next_generated_level
+                    .run_if(in_state(GameState::LevelTransition))
+                    .run_if(resource_exists::<PendingLevel>()),
             )
-            .add_systems(
-                OnEnter(GameState::LevelReload),
-                (level_transition, clear_player_history),
-            );
+            .add_systems(OnEnter(GameState::LevelReload), level_transition);
     }
 }
 
-#[derive(Debug, Reflect)]
+#[derive(Debug, Clone)]
 pub struct Level {
+    pub name: String,
     pub map: Map,
     pub stamina_budget: u16,
     pub weight_budget: u8,
@@ -61,11 +76,21 @@ pub struct Level {
     pub rope_unlocked: bool,
     pub potion_unlocked: bool,
     pub rewind_unlocked: bool,
+    pub par: Option<Par>,
+    pub tutorial: Vec<TutorialPrompt>,
 }
 
 #[derive(Component)]
 pub struct DespawnOnTransition;
 
+// the level being played, which the rest of the ECS state mirrors
+impl Resource for Puzzle {}
+
+fn start_puzzle(mut commands: Commands, level_manager: Res<LevelManager>) {
+    let level = level_manager.get_current_level();
+    commands.insert_resource(Puzzle::new(level.map.clone(), level.stamina_budget));
+}
+
 fn setup_scene(
     mut commands: Commands,
     mut cloud_materials: ResMut<Assets<CloudMaterial>>,
@@ -73,6 +98,7 @@ fn setup_scene(
     mut cameras: Query<(&mut Transform, &mut Projection, &mut MainCamera)>,
     model_assets: Res<ModelAssets>,
     level_manager: Res<LevelManager>,
+    settings: Res<Settings>,
 ) {
     // Spawn orthographic camera
     if let Ok((mut camera_transform, mut camera_projection, mut camera)) = cameras.get_single_mut()
@@ -91,7 +117,7 @@ fn setup_scene(
     commands
         .spawn(DirectionalLightBundle {
             directional_light: DirectionalLight {
-                shadows_enabled: true,
+                shadows_enabled: settings.data.shadows,
                 ..Default::default()
             },
             transform: Transform::from_xyz(10.0, 20.0, 5.0).looking_at(Vec3::default(), Vec3::Y),
@@ -162,20 +188,36 @@ pub fn animate_flag(
     }
 }
 
-fn level_transition(
+pub fn level_transition(
     mut commands: Commands,
     mut next_state: ResMut<NextState<GameState>>,
     current_state: Res<State<GameState>>,
     entities: Query<Entity, With<DespawnOnTransition>>,
     mut level_manager: ResMut<LevelManager>,
+    editor_level: Option<Res<EditorLevel>>,
 ) {
     for entity in entities.iter() {
         commands.entity(entity).despawn_recursive();
     }
-    commands.insert_resource(Inventory::default());
-    level_manager.get_current_map_mut().reset();
+    commands.insert_resource(EquipmentEntities::default());
     if matches!(current_state.get(), GameState::LevelTransition) {
-        if level_manager.current + 1 < level_manager.levels.len() {
+        if editor_level.is_some() {
+            // finishing a playtest goes back to the editor
+            next_state.set(GameState::Editor);
+        } else if level_manager.daily.is_some() {
+            // there's only one a day
+            next_state.set(GameState::MainMenu);
+        } else if let Some(generated) = &level_manager.generated {
+            // endless: a new level, a little harder until it can't get any harder, which is
+            // made off the main thread and started once it is ready
+            let (seed, difficulty) = (
+                generated.seed.wrapping_add(1),
+                generated.difficulty.harder(),
+            );
+            commands.insert_resource(PendingLevel::spawn(None, move || {
+                generate(seed, difficulty)
+            }));
+        } else if level_manager.current + 1 < level_manager.levels.len() {
             level_manager.current += 1;
             next_state.set(GameState::Level);
         } else {
@@ -186,43 +228,88 @@ fn level_transition(
     }
 }
 
-fn reload_level(
+/// Go on to the next endless level once it has been made
+fn start_next_generated_level(
     mut commands: Commands,
-    mut transition_manager: ResMut<TransitionManager>,
-    keyboard_input: Res<Input<KeyCode>>,
+    pending_level: Res<PendingLevel>,
     mut level_manager: ResMut<LevelManager>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(level) = pending_level.take() else {
+        return;
+    };
+    commands.remove_resource::<PendingLevel>();
+    level_manager.generated = Some(level);
+    next_state.set(GameState::Level);
+}
+
+fn reload_level(
+    mut transition_manager: ResMut<TransitionManager>,
+    input: ControlInput,
+    puzzle: Res<Puzzle>,
     sound_channel: Res<AudioChannel<SoundChannel>>,
     audio_assets: Res<AudioAssets>,
-    mut player_history: ResMut<PlayerHistory>,
     mut scale_counter: ResMut<ScaleCounter>,
-    rewind_runes: Query<Entity, With<RewindRune>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::R) {
-        // remove ladders/ropes etc.
-        level_manager.get_current_map_mut().reset();
-        // remove rewind runes
-        for entity in rewind_runes.iter() {
-            commands.entity(entity).despawn_recursive();
-        }
-        // go through the history to see if a scale was picked up. If so, decrement scale count
-        for event in player_history.0.drain(..) {
-            if let PlayerHistoryEvent::PlayerMoveToScale(_) = event {
-                scale_counter.0 -= 1;
-            }
-        }
+    if input.just_pressed(Control::Restart)
+        && matches!(*transition_manager, TransitionManager::Normal)
+    {
         sound_channel.play(audio_assets.woosh.clone());
-        *transition_manager = TransitionManager::TransitioningOutReload(0.0);
+        restart_level(&mut transition_manager, &puzzle, &mut scale_counter);
+    }
+}
+
+/// Fade out and start the level again from the beginning
+pub fn restart_level(
+    transition_manager: &mut TransitionManager,
+    puzzle: &Puzzle,
+    scale_counter: &mut ScaleCounter,
+) {
+    // the scale goes back when the level restarts
+    if puzzle.state.has_scale {
+        scale_counter.0 -= 1;
     }
+    *transition_manager = TransitionManager::TransitioningOutReload(0.0);
 }
 
 fn skip_level(
     mut transition_manager: ResMut<TransitionManager>,
-    keyboard_input: Res<Input<KeyCode>>,
+    input: ControlInput,
     sound_channel: Res<AudioChannel<SoundChannel>>,
     audio_assets: Res<AudioAssets>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::F1) {
+    if input.just_pressed(Control::SkipLevel) {
         sound_channel.play(audio_assets.woosh.clone());
         *transition_manager = TransitionManager::TransitioningOut(0.0);
     }
 }
+
+fn solve_current_level(keyboard_input: Res<Input<KeyCode>>, level_manager: Res<LevelManager>) {
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        let number = level_manager.current + 1;
+        let level = level_manager.get_current_level().clone();
+        // the search can take a while on the later levels, so keep it off the main thread
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                for goal in [Goal::Flag, Goal::FlagWithScale] {
+                    match solve(&level, goal) {
+                        Some(solution) => info!(
+                            "Level {} ({:?}): {} moves with {}{}: {:?}",
+                            number,
+                            goal,
+                            solution.actions.len(),
+                            solution.loadout,
+                            if solution.collects_scale {
+                                ", collecting the scale"
+                            } else {
+                                ""
+                            },
+                            solution.actions
+                        ),
+                        None => info!("Level {} ({:?}): no solution", number, goal),
+                    }
+                }
+            })
+            .detach();
+    }
+}
@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    controls::{Control, ControlInput},
+    level_manager::LevelManager,
+    localization::Localization,
+    rating::MOST_STARS,
+    save::SaveGame,
+    scale::ScaleCounter,
+    ui::{
+        constants::{DARK_GREY, GREY, UI_YELLOW, UI_YELLOW_HOVER},
+        UiRoot,
+    },
+};
+
+use super::{loading::FontAssets, transition::TransitionManager, GameState};
+
+pub struct LevelSelectPlugin;
+
+impl Plugin for LevelSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::LevelSelect), setup_level_select)
+            .add_systems(
+                Update,
+                (button_system, back_on_pause).run_if(in_state(GameState::LevelSelect)),
+            )
+            .add_systems(OnExit(GameState::LevelSelect), cleanup_level_select);
+    }
+}
+
+#[derive(Component)]
+enum LevelSelectButton {
+    /// Start the level at this index in the campaign
+    Level(usize),
+    Back,
+}
+
+#[derive(Component)]
+struct LevelSelectRoot;
+
+fn setup_level_select(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    level_manager: Res<LevelManager>,
+    save_game: Res<SaveGame>,
+    localization: Res<Localization>,
+) {
+    let text_style = |font_size: f32| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(20.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(LevelSelectRoot)
+        .insert(UiRoot)
+        .insert(Name::new("Level select UI"))
+        .with_children(|parent| {
+            // one tile per level, four to a row
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        display: Display::Grid,
+                        grid_template_columns: RepeatedGridTrack::px(4, 220.0),
+                        row_gap: Val::Px(10.0),
+                        column_gap: Val::Px(10.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (index, level) in level_manager.levels.iter().enumerate() {
+                        let record = save_game.data.levels.get(index);
+                        let completed = record.is_some_and(|record| record.completed);
+                        let unlocked = save_game.data.is_unlocked(index);
+                        let style = Style {
+                            height: Val::Px(110.0),
+                            padding: UiRect::all(Val::Px(5.0)),
+                            flex_direction: FlexDirection::Column,
+                            justify_content: JustifyContent::SpaceAround,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        };
+                        let mut tile = if unlocked {
+                            let mut tile = parent.spawn(ButtonBundle {
+                                style,
+                                background_color: if completed { UI_YELLOW } else { GREY }.into(),
+                                ..Default::default()
+                            });
+                            tile.insert(LevelSelectButton::Level(index));
+                            tile
+                        } else {
+                            parent.spawn(NodeBundle {
+                                style,
+                                background_color: DARK_GREY.into(),
+                                ..Default::default()
+                            })
+                        };
+                        tile.with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                localization.format("level_select.level", &[&(index + 1)]),
+                                text_style(30.0),
+                            ));
+                            if !unlocked {
+                                parent.spawn(TextBundle::from_section(
+                                    localization.get("level_select.locked"),
+                                    text_style(18.0),
+                                ));
+                                return;
+                            }
+                            parent.spawn(TextBundle::from_section(
+                                level.name.clone(),
+                                text_style(18.0),
+                            ));
+                            let mut status = localization.get(if completed {
+                                "level_select.completed"
+                            } else {
+                                "level_select.not_completed"
+                            });
+                            if level.map.scale_pos.is_some() {
+                                let scale = if record.is_some_and(|record| record.scale) {
+                                    "level_select.scale_collected"
+                                } else {
+                                    "level_select.scale_missing"
+                                };
+                                status = format!("{} - {}", status, localization.get(scale));
+                            }
+                            parent.spawn(TextBundle::from_section(status, text_style(15.0)));
+                            // saves from before stars were given have none
+                            if let Some(record) = record.filter(|record| record.stars > 0) {
+                                parent.spawn(TextBundle::from_section(
+                                    localization
+                                        .format("common.stars", &[&record.stars, &MOST_STARS]),
+                                    text_style(15.0),
+                                ));
+                            }
+                        });
+                    }
+                });
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        width: Val::Px(220.0),
+                        height: Val::Px(60.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    background_color: UI_YELLOW.into(),
+                    ..Default::default()
+                })
+                .insert(LevelSelectButton::Back)
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        localization.get("common.back"),
+                        text_style(40.0),
+                    ));
+                });
+        });
+}
+
+fn button_system(
+    mut interaction_query: Query<
+        (&LevelSelectButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut transition_manager: ResMut<TransitionManager>,
+    mut level_manager: ResMut<LevelManager>,
+    mut scale_counter: ResMut<ScaleCounter>,
+    save_game: Res<SaveGame>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    for (button, interaction, mut color) in &mut interaction_query {
+        // finished levels keep their yellow so they stand out from the ones still to do
+        let idle = match button {
+            LevelSelectButton::Level(index) => {
+                if save_game
+                    .data
+                    .levels
+                    .get(*index)
+                    .is_some_and(|record| record.completed)
+                {
+                    UI_YELLOW
+                } else {
+                    GREY
+                }
+            }
+            LevelSelectButton::Back => UI_YELLOW,
+        };
+        match *interaction {
+            Interaction::Pressed => {
+                if !matches!(*transition_manager, TransitionManager::Normal) {
+                    continue;
+                }
+                match button {
+                    LevelSelectButton::Level(index) => {
+                        level_manager.current = *index;
+                        scale_counter.0 = save_game.data.scales_before(*index);
+                        sound_channel.play(audio_assets.woosh.clone());
+                        *transition_manager = TransitionManager::TransitioningOutReload(0.0);
+                    }
+                    LevelSelectButton::Back => {
+                        sound_channel.play(audio_assets.pop.clone());
+                        next_state.set(GameState::MainMenu);
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = UI_YELLOW_HOVER.into();
+            }
+            Interaction::None => {
+                *color = idle.into();
+            }
+        }
+    }
+}
+
+fn back_on_pause(
+    input: ControlInput,
+    transition_manager: Res<TransitionManager>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if input.just_pressed(Control::Pause)
+        && matches!(*transition_manager, TransitionManager::Normal)
+    {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn cleanup_level_select(mut commands: Commands, root: Query<Entity, With<LevelSelectRoot>>) {
+    commands.entity(root.single()).despawn_recursive();
+}
@@ -1,7 +1,12 @@
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 
-use crate::{audio::AudioAssets, states::transition::TransitionManager};
+use crate::{
+    audio::AudioAssets,
+    level_manager::{Campaign, CampaignLoader},
+    localization::{Locales, LocalesLoader},
+    states::transition::TransitionManager,
+};
 
 use super::GameState;
 
@@ -10,16 +15,22 @@ pub struct LoadingPlugin;
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
         info!("loading...");
-        app.add_loading_state(
-            LoadingState::new(GameState::Loading).continue_to_state(GameState::MainMenu),
-        )
-        .add_collection_to_loading_state::<_, FontAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, ModelAssets>(GameState::Loading)
-        .insert_resource(TransitionManager::Normal)
-        .add_systems(OnEnter(GameState::Loading), setup_loading_screen)
-        .add_systems(OnExit(GameState::Loading), cleanup);
+        app.add_asset::<Campaign>()
+            .init_asset_loader::<CampaignLoader>()
+            .add_asset::<Locales>()
+            .init_asset_loader::<LocalesLoader>()
+            .add_loading_state(
+                LoadingState::new(GameState::Loading).continue_to_state(GameState::MainMenu),
+            )
+            .add_collection_to_loading_state::<_, FontAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, ModelAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, LevelAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, LocaleAssets>(GameState::Loading)
+            .insert_resource(TransitionManager::Normal)
+            .add_systems(OnEnter(GameState::Loading), setup_loading_screen)
+            .add_systems(OnExit(GameState::Loading), cleanup);
     }
 }
 
@@ -75,6 +86,18 @@ pub struct ModelAssets {
     pub gem: Handle<Scene>,
 }
 
+#[derive(AssetCollection, Resource)]
+pub struct LevelAssets {
+    #[asset(path = "levels/main.campaign.ron")]
+    pub campaign: Handle<Campaign>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct LocaleAssets {
+    #[asset(path = "locales/all.locales.ron")]
+    pub locales: Handle<Locales>,
+}
+
 #[derive(Component)]
 pub struct LoadingUiRoot;
 
@@ -4,11 +4,17 @@ use bevy::{prelude::*, render::camera::ScalingMode};
 use bevy_kira_audio::{AudioChannel, AudioControl, AudioTween};
 
 use crate::{
-    audio::{AudioAssets, MusicChannel, SoundChannel, VolumeSettings},
+    audio::{AudioAssets, MusicChannel, SoundChannel},
     camera::{camera_spin, MainCamera},
     clouds::CloudMaterial,
-    level_manager::init_level_manager,
+    daily::{daily_level, today, DailyRecords},
+    generator::{generate, Difficulty},
+    level_manager::{init_level_manager, LevelManager, PendingLevel},
+    localization::Localization,
     post_process::TransitionSettings,
+    save::SaveGame,
+    scale::ScaleCounter,
+    settings::Settings,
     ui::{
         constants::{UI_YELLOW, UI_YELLOW_HOVER},
         UiRoot,
@@ -16,6 +22,7 @@ use crate::{
 };
 
 use super::{
+    editor::EditorLevel,
     level::{animate_flag, DespawnOnTransition},
     loading::{FontAssets, ModelAssets},
     transition::TransitionManager,
@@ -27,49 +34,66 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
+            OnTransition {
+                from: GameState::Loading,
+                to: GameState::MainMenu,
+            },
+            start_music,
+        )
+        .add_systems(
             OnEnter(GameState::MainMenu),
-            (setup_menu, init_level_manager),
+            (setup_menu, setup_menu_scene, init_level_manager),
         )
+        // the level select can also be reached from the pause menu in a level
+        .add_systems(OnEnter(GameState::LevelSelect), setup_menu_scene)
         .add_systems(
             Update,
             (
                 button_system,
-                update_button_volume_text,
-                camera_spin,
-                animate_flag,
+                start_generated_level.run_if(resource_exists::<PendingLevel>()),
             )
                 .run_if(in_state(GameState::MainMenu)),
         )
+        // the mountain stays up behind the level select, controls and settings screens
+        .add_systems(
+            Update,
+            (camera_spin, animate_flag).run_if(
+                in_state(GameState::MainMenu)
+                    .or_else(in_state(GameState::LevelSelect))
+                    .or_else(in_state(GameState::Controls))
+                    .or_else(in_state(GameState::Settings)),
+            ),
+        )
         .add_systems(OnExit(GameState::MainMenu), cleanup_menu);
     }
 }
 
 #[derive(Component)]
 enum MenuButton {
+    Continue,
     Start,
-    Sound,
-    Music,
+    LevelSelect,
+    Daily,
+    Endless,
+    Editor,
+    Controls,
+    Settings,
 }
 
 #[derive(Component)]
 struct MainMenuRoot;
 
+/// The spinning mountain behind the menu screens
+#[derive(Component)]
+struct MenuScene;
+
 fn setup_menu(
     mut commands: Commands,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut cloud_materials: ResMut<Assets<CloudMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
     font_assets: Res<FontAssets>,
-    model_assets: Res<ModelAssets>,
-    audio_assets: Res<AudioAssets>,
-    music_channel: Res<AudioChannel<MusicChannel>>,
+    save_game: Res<SaveGame>,
+    daily_records: Res<DailyRecords>,
+    localization: Res<Localization>,
 ) {
-    music_channel
-        .play(audio_assets.bgm.clone())
-        .looped()
-        .fade_in(AudioTween::linear(Duration::from_secs(3)));
-
-    // draw UI
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -85,25 +109,97 @@ fn setup_menu(
         .insert(MainMenuRoot)
         .insert(UiRoot)
         .with_children(|parent| {
+            if save_game.data.has_progress() {
+                add_button(
+                    parent,
+                    &localization.get("menu.continue"),
+                    MenuButton::Continue,
+                    font_assets.fira_sans.clone(),
+                );
+            }
             add_button(
                 parent,
-                "Start",
+                &localization.get("menu.start"),
                 MenuButton::Start,
                 font_assets.fira_sans.clone(),
             );
             add_button(
                 parent,
-                "Sound",
-                MenuButton::Sound,
+                &localization.get("menu.levels"),
+                MenuButton::LevelSelect,
+                font_assets.fira_sans.clone(),
+            );
+            add_button(
+                parent,
+                &localization.get("menu.daily"),
+                MenuButton::Daily,
                 font_assets.fira_sans.clone(),
             );
+            if let Some(best) = daily_records.data.best.get(&today()) {
+                parent.spawn(
+                    TextBundle::from_section(
+                        localization.format("menu.daily_best", &[&best.stamina_left, &best.moves]),
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_style(Style {
+                        margin: UiRect::left(Val::Px(30.0)),
+                        ..Default::default()
+                    }),
+                );
+            }
             add_button(
                 parent,
-                "Music",
-                MenuButton::Music,
+                &localization.get("menu.endless"),
+                MenuButton::Endless,
+                font_assets.fira_sans.clone(),
+            );
+            add_button(
+                parent,
+                &localization.get("menu.editor"),
+                MenuButton::Editor,
+                font_assets.fira_sans.clone(),
+            );
+            add_button(
+                parent,
+                &localization.get("menu.controls"),
+                MenuButton::Controls,
+                font_assets.fira_sans.clone(),
+            );
+            add_button(
+                parent,
+                &localization.get("menu.settings"),
+                MenuButton::Settings,
                 font_assets.fira_sans.clone(),
             );
         });
+}
+
+fn start_music(audio_assets: Res<AudioAssets>, music_channel: Res<AudioChannel<MusicChannel>>) {
+    music_channel
+        .play(audio_assets.bgm.clone())
+        .looped()
+        .fade_in(AudioTween::linear(Duration::from_secs(3)));
+}
+
+/// The spinning mountain, which carries on while going between the menu screens. It is only
+/// missing when coming back from the editor.
+fn setup_menu_scene(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut cloud_materials: ResMut<Assets<CloudMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut cameras: Query<(&mut Transform, &mut Projection), With<MainCamera>>,
+    menu_scene: Query<(), With<MenuScene>>,
+    model_assets: Res<ModelAssets>,
+    settings: Res<Settings>,
+) {
+    if !menu_scene.is_empty() {
+        return;
+    }
 
     // spawn mountain
     let grid_heights = vec![
@@ -133,28 +229,36 @@ fn setup_menu(
                     ),
                     ..Default::default()
                 })
+                .insert(MenuScene)
                 .insert(DespawnOnTransition);
         }
     }
-    // Spawn orthographic camera
-    commands
-        .spawn(Camera3dBundle {
-            transform: Transform::from_xyz(3.0, 10.0, 10.0)
-                .looking_at(Vec3::new(3.0, 5.5, 0.0), Vec3::Y),
-            projection: Projection::Orthographic(OrthographicProjection {
-                scaling_mode: ScalingMode::FixedVertical(14.),
+    // Spawn orthographic camera, or move the one left by the editor back
+    let camera_transform =
+        Transform::from_xyz(3.0, 10.0, 10.0).looking_at(Vec3::new(3.0, 5.5, 0.0), Vec3::Y);
+    let camera_projection = Projection::Orthographic(OrthographicProjection {
+        scaling_mode: ScalingMode::FixedVertical(14.),
+        ..Default::default()
+    });
+    if let Ok((mut transform, mut projection)) = cameras.get_single_mut() {
+        *transform = camera_transform;
+        *projection = camera_projection;
+    } else {
+        commands
+            .spawn(Camera3dBundle {
+                transform: camera_transform,
+                projection: camera_projection,
                 ..Default::default()
-            }),
-            ..Default::default()
-        })
-        .insert(MainCamera::default())
-        .insert(TransitionSettings::default());
+            })
+            .insert(MainCamera::default())
+            .insert(TransitionSettings::default());
+    }
 
     // Spawn main light source
     commands
         .spawn(DirectionalLightBundle {
             directional_light: DirectionalLight {
-                shadows_enabled: true,
+                shadows_enabled: settings.data.shadows,
                 ..Default::default()
             },
             transform: Transform::from_xyz(10.0, 20.0, 5.0).looking_at(Vec3::default(), Vec3::Y),
@@ -194,11 +298,12 @@ fn setup_menu(
 }
 
 fn add_button(parent: &mut ChildBuilder, text: &str, button: MenuButton, font: Handle<Font>) {
+    // kept short, so that all eight buttons and the daily best fit in under 500px of height
     let button_style = Style {
         width: Val::Px(220.0),
-        height: Val::Px(60.0),
+        height: Val::Px(46.0),
         // center button
-        margin: UiRect::all(Val::Px(30.0)),
+        margin: UiRect::axes(Val::Px(30.0), Val::Px(6.0)),
         // horizontally center child text
         justify_content: JustifyContent::Center,
         // vertically center child text
@@ -215,7 +320,7 @@ fn add_button(parent: &mut ChildBuilder, text: &str, button: MenuButton, font: H
                 text,
                 TextStyle {
                     font,
-                    font_size: 40.0,
+                    font_size: 32.0,
                     color: Color::WHITE,
                 },
             ));
@@ -224,34 +329,74 @@ fn add_button(parent: &mut ChildBuilder, text: &str, button: MenuButton, font: H
 }
 
 fn button_system(
+    mut commands: Commands,
     mut interaction_query: Query<
         (&MenuButton, &Interaction, &mut BackgroundColor),
         (Changed<Interaction>, With<Button>),
     >,
-    mut volume_settings: ResMut<VolumeSettings>,
+    mut next_state: ResMut<NextState<GameState>>,
     mut transition_manager: ResMut<TransitionManager>,
-    music_channel: Res<AudioChannel<MusicChannel>>,
+    mut level_manager: ResMut<LevelManager>,
+    mut scale_counter: ResMut<ScaleCounter>,
+    save_game: Res<SaveGame>,
+    pending_level: Option<Res<PendingLevel>>,
     sound_channel: Res<AudioChannel<SoundChannel>>,
     audio_assets: Res<AudioAssets>,
 ) {
     for (button, interaction, mut color) in &mut interaction_query {
         match *interaction {
+            // the buttons do nothing while a generated level is being made
+            Interaction::Pressed if pending_level.is_some() => {}
             Interaction::Pressed => {
                 *color = UI_YELLOW.into();
                 match button {
+                    MenuButton::Continue => {
+                        // the saved level may be past the end if the campaign was finished
+                        let last_level = level_manager.levels.len() - 1;
+                        level_manager.current = save_game.data.current_level.min(last_level);
+                        scale_counter.0 = save_game.data.scales_before(level_manager.current);
+                        sound_channel.play(audio_assets.woosh.clone());
+                        *transition_manager = TransitionManager::TransitioningOutReload(0.0);
+                    }
                     MenuButton::Start => {
+                        level_manager.current = 0;
+                        scale_counter.0 = 0;
                         sound_channel.play(audio_assets.woosh.clone());
                         *transition_manager = TransitionManager::TransitioningOutReload(0.0);
                     }
-                    MenuButton::Sound => {
-                        volume_settings.toggle_sfx_vol();
-                        sound_channel.set_volume(volume_settings.sfx_vol);
+                    MenuButton::LevelSelect => {
+                        sound_channel.play(audio_assets.pop.clone());
+                        next_state.set(GameState::LevelSelect);
+                    }
+                    MenuButton::Daily => {
+                        let day = today();
+                        commands.insert_resource(PendingLevel::spawn(Some(day), move || {
+                            daily_level(day)
+                        }));
+                        sound_channel.play(audio_assets.pop.clone());
+                    }
+                    MenuButton::Endless => {
+                        // generated levels, starting easy with a fresh seed every time
+                        let seed = rand::random();
+                        commands.insert_resource(PendingLevel::spawn(None, move || {
+                            generate(seed, Difficulty::Easy)
+                        }));
+                        sound_channel.play(audio_assets.pop.clone());
+                    }
+                    MenuButton::Editor => {
+                        commands.insert_resource(EditorLevel(
+                            level_manager.get_current_level().clone(),
+                        ));
+                        sound_channel.play(audio_assets.pop.clone());
+                        next_state.set(GameState::Editor);
+                    }
+                    MenuButton::Controls => {
                         sound_channel.play(audio_assets.pop.clone());
+                        next_state.set(GameState::Controls);
                     }
-                    MenuButton::Music => {
-                        volume_settings.toggle_music_vol();
-                        music_channel.set_volume(volume_settings.music_vol);
+                    MenuButton::Settings => {
                         sound_channel.play(audio_assets.pop.clone());
+                        next_state.set(GameState::Settings);
                     }
                 }
             }
@@ -265,28 +410,25 @@ fn button_system(
     }
 }
 
-fn update_button_volume_text(
-    query: Query<(&MenuButton, &Children)>,
-    mut text_query: Query<&mut Text>,
-    volume_settings: Res<VolumeSettings>,
+/// Play the generated level once it has been made
+fn start_generated_level(
+    mut commands: Commands,
+    pending_level: Res<PendingLevel>,
+    mut transition_manager: ResMut<TransitionManager>,
+    mut level_manager: ResMut<LevelManager>,
+    mut scale_counter: ResMut<ScaleCounter>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
 ) {
-    if !volume_settings.is_changed() {
+    let Some(level) = pending_level.take() else {
         return;
-    }
-    for (button, children) in query.iter() {
-        let mut text = text_query.get_mut(children[0]).unwrap();
-        match button {
-            MenuButton::Sound => {
-                text.sections[0].value =
-                    format!(" Sound: {}% ", (volume_settings.sfx_vol * 100.0).round());
-            }
-            MenuButton::Music => {
-                text.sections[0].value =
-                    format!(" Music: {}% ", (volume_settings.music_vol * 100.0).round());
-            }
-            _ => {}
-        }
-    }
+    };
+    commands.remove_resource::<PendingLevel>();
+    level_manager.generated = Some(level);
+    level_manager.daily = pending_level.daily;
+    scale_counter.0 = 0;
+    sound_channel.play(audio_assets.woosh.clone());
+    *transition_manager = TransitionManager::TransitioningOutReload(0.0);
 }
 
 fn cleanup_menu(mut commands: Commands, root: Query<Entity, With<MainMenuRoot>>) {
@@ -1,8 +1,13 @@
 use bevy::prelude::States;
 
+pub mod controls;
+pub mod editor;
 pub mod level;
+pub mod level_select;
 pub mod loading;
 pub mod menu;
+pub mod pause;
+pub mod settings;
 pub mod transition;
 
 #[derive(Debug, Default, Clone, Eq, PartialEq, Hash, States)]
@@ -10,8 +15,22 @@ pub enum GameState {
     #[default]
     Loading,
     MainMenu,
+    LevelSelect,
     Level,
     LevelTransition,
     LevelReload,
     End,
+    Editor,
+    Controls,
+    Settings,
+}
+
+/// Whether the level being played is paused, and if so which of the pause screens is up. This
+/// stays at `Playing` outside of levels.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash, States)]
+pub enum PauseState {
+    #[default]
+    Playing,
+    Paused,
+    Settings,
 }
@@ -0,0 +1,379 @@
+use bevy::{ecs::system::EntityCommands, prelude::*, ui::FocusPolicy};
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    controls::{Control, ControlInput},
+    equipment::EquipmentEntities,
+    level_manager::LevelManager,
+    localization::{Localization, Localized},
+    rules::Puzzle,
+    scale::ScaleCounter,
+    settings::Settings,
+    ui::{
+        constants::{SKY_BLUE, UI_YELLOW, UI_YELLOW_HOVER},
+        focus::Modal,
+        settings::spawn_settings_panel,
+        UiRoot,
+    },
+};
+
+use super::{
+    editor::EditorLevel,
+    level::{restart_level, DespawnOnTransition},
+    loading::FontAssets,
+    transition::TransitionManager,
+    GameState, PauseState,
+};
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<PauseState>()
+            .add_systems(OnEnter(PauseState::Paused), setup_pause_menu)
+            .add_systems(OnExit(PauseState::Paused), cleanup_pause_screen)
+            .add_systems(OnEnter(PauseState::Settings), setup_pause_settings)
+            .add_systems(OnExit(PauseState::Settings), cleanup_pause_screen)
+            .add_systems(
+                Update,
+                (toggle_pause, button_system).run_if(in_state(GameState::Level)),
+            )
+            // the pause menu is the only way straight out of a level to these
+            .add_systems(
+                OnTransition {
+                    from: GameState::Level,
+                    to: GameState::MainMenu,
+                },
+                leave_level,
+            )
+            .add_systems(
+                OnTransition {
+                    from: GameState::Level,
+                    to: GameState::LevelSelect,
+                },
+                leave_level,
+            );
+    }
+}
+
+#[derive(Component)]
+enum PauseButton {
+    Resume,
+    Restart,
+    Settings,
+    LevelSelect,
+    Quit,
+    /// Back from the settings to the pause menu
+    Back,
+}
+
+#[derive(Component)]
+struct PauseRoot;
+
+/// Escape pauses, and backs out of the pause screens one at a time
+fn toggle_pause(
+    input: ControlInput,
+    transition_manager: Res<TransitionManager>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if !input.just_pressed(Control::Pause)
+        || !matches!(*transition_manager, TransitionManager::Normal)
+    {
+        return;
+    }
+    next_pause_state.set(match pause_state.get() {
+        PauseState::Playing => PauseState::Paused,
+        PauseState::Paused => PauseState::Playing,
+        PauseState::Settings => PauseState::Paused,
+    });
+}
+
+/// The dimmed screen the pause panels sit on, which keeps clicks off the level's buttons
+fn spawn_pause_root<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    name: &str,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut root = commands.spawn(NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.4).into(),
+        focus_policy: FocusPolicy::Block,
+        z_index: ZIndex::Global(10),
+        ..Default::default()
+    });
+    root.insert(PauseRoot)
+        .insert(Modal)
+        .insert(UiRoot)
+        .insert(Name::new(name.to_string()));
+    root
+}
+
+fn panel() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Val::Px(20.0)),
+            row_gap: Val::Px(12.0),
+            ..Default::default()
+        },
+        background_color: SKY_BLUE.into(),
+        ..Default::default()
+    }
+}
+
+fn add_button(
+    parent: &mut ChildBuilder,
+    localization: &Localization,
+    key: &str,
+    button: PauseButton,
+    font: Handle<Font>,
+) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                width: Val::Px(260.0),
+                height: Val::Px(56.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            background_color: UI_YELLOW.into(),
+            ..Default::default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            parent.spawn(localization.text(
+                Localized::new(key),
+                TextStyle {
+                    font,
+                    font_size: 36.0,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+fn setup_pause_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    level_manager: Res<LevelManager>,
+    localization: Res<Localization>,
+) {
+    let font = font_assets.fira_sans.clone();
+    spawn_pause_root(&mut commands, "Pause UI").with_children(|parent| {
+        parent.spawn(panel()).with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                localization.get("pause.title"),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 45.0,
+                    color: Color::WHITE,
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                level_manager.get_current_level().name.clone(),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+            ));
+            for (key, button) in [
+                ("pause.resume", PauseButton::Resume),
+                ("pause.restart", PauseButton::Restart),
+                ("menu.settings", PauseButton::Settings),
+                ("pause.level_select", PauseButton::LevelSelect),
+                ("pause.quit", PauseButton::Quit),
+            ] {
+                add_button(parent, &localization, key, button, font.clone());
+            }
+        });
+    });
+}
+
+fn setup_pause_settings(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+) {
+    let font = font_assets.fira_sans.clone();
+    spawn_pause_root(&mut commands, "Pause settings UI").with_children(|parent| {
+        parent.spawn(panel()).with_children(|parent| {
+            parent.spawn(localization.text(
+                Localized::new("settings.title"),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 45.0,
+                    color: Color::WHITE,
+                },
+            ));
+            spawn_settings_panel(parent, &font, &settings.data, &localization);
+            add_button(
+                parent,
+                &localization,
+                "common.back",
+                PauseButton::Back,
+                font.clone(),
+            );
+        });
+    });
+}
+
+fn button_system(
+    mut interaction_query: Query<
+        (&PauseButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut transition_manager: ResMut<TransitionManager>,
+    mut scale_counter: ResMut<ScaleCounter>,
+    puzzle: Res<Puzzle>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    for (button, interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                if !matches!(*transition_manager, TransitionManager::Normal) {
+                    continue;
+                }
+                match button {
+                    PauseButton::Resume => {
+                        sound_channel.play(audio_assets.pop.clone());
+                        next_pause_state.set(PauseState::Playing);
+                    }
+                    PauseButton::Restart => {
+                        sound_channel.play(audio_assets.woosh.clone());
+                        restart_level(&mut transition_manager, &puzzle, &mut scale_counter);
+                        next_pause_state.set(PauseState::Playing);
+                    }
+                    PauseButton::Settings => {
+                        sound_channel.play(audio_assets.pop.clone());
+                        next_pause_state.set(PauseState::Settings);
+                    }
+                    PauseButton::LevelSelect => {
+                        sound_channel.play(audio_assets.pop.clone());
+                        next_pause_state.set(PauseState::Playing);
+                        next_state.set(GameState::LevelSelect);
+                    }
+                    PauseButton::Quit => {
+                        sound_channel.play(audio_assets.pop.clone());
+                        next_pause_state.set(PauseState::Playing);
+                        next_state.set(GameState::MainMenu);
+                    }
+                    PauseButton::Back => {
+                        sound_channel.play(audio_assets.pop.clone());
+                        next_pause_state.set(PauseState::Paused);
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = UI_YELLOW_HOVER.into();
+            }
+            Interaction::None => {
+                *color = UI_YELLOW.into();
+            }
+        }
+    }
+}
+
+/// Runs after the level's own `OnExit` systems, which still need to know what was being played.
+/// This does what `level_transition` does on the way to another level, and also ends a playtest or
+/// a run of generated levels.
+fn leave_level(
+    mut commands: Commands,
+    entities: Query<Entity, With<DespawnOnTransition>>,
+    mut level_manager: ResMut<LevelManager>,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.insert_resource(EquipmentEntities::default());
+    commands.remove_resource::<EditorLevel>();
+    level_manager.generated = None;
+    level_manager.daily = None;
+    level_manager.playtest = None;
+}
+
+fn cleanup_pause_screen(mut commands: Commands, root: Query<Entity, With<PauseRoot>>) {
+    for entity in root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rules::{Inventory, Map},
+        testing::{connect_gamepad, level_app, tap, tap_button},
+    };
+
+    fn pause_state(app: &App) -> PauseState {
+        app.world.resource::<State<PauseState>>().get().clone()
+    }
+
+    #[test]
+    fn pausing_holds_up_the_puzzle() {
+        // a cliff to tie a rope over, with room to walk at the bottom
+        let mut app = level_app(Map::from_heights(&[&[3, 1, 1]], &[]), 10);
+        app.world
+            .resource_mut::<Puzzle>()
+            .set_loadout(Inventory {
+                rope_count: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        tap(&mut app, &[KeyCode::D]);
+        let facing_the_cliff = app.world.resource::<Puzzle>().state.clone();
+
+        tap(&mut app, &[KeyCode::Escape]);
+        assert_eq!(pause_state(&app), PauseState::Paused);
+        for keys in [
+            &[KeyCode::D][..],
+            &[KeyCode::Key2],
+            &[KeyCode::ControlLeft, KeyCode::Z],
+            &[KeyCode::R],
+        ] {
+            tap(&mut app, keys);
+        }
+        assert_eq!(app.world.resource::<Puzzle>().state, facing_the_cliff);
+        assert!(app.world.resource::<EquipmentEntities>().ropes.is_empty());
+        assert!(matches!(
+            *app.world.resource::<TransitionManager>(),
+            TransitionManager::Normal
+        ));
+
+        tap(&mut app, &[KeyCode::Escape]);
+        assert_eq!(pause_state(&app), PauseState::Playing);
+        tap(&mut app, &[KeyCode::Key2]);
+        assert_eq!(app.world.resource::<EquipmentEntities>().ropes.len(), 1);
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Z]);
+        assert_eq!(app.world.resource::<Puzzle>().state, facing_the_cliff);
+        tap(&mut app, &[KeyCode::R]);
+        assert!(matches!(
+            *app.world.resource::<TransitionManager>(),
+            TransitionManager::TransitioningOutReload(_)
+        ));
+    }
+
+    #[test]
+    fn start_on_a_gamepad_pauses() {
+        let mut app = level_app(Map::from_heights(&[&[1, 1]], &[]), 5);
+        let pad = connect_gamepad(&mut app);
+        for expected in [PauseState::Paused, PauseState::Playing] {
+            tap_button(&mut app, pad, GamepadButtonType::Start);
+            assert_eq!(pause_state(&app), expected);
+        }
+    }
+}
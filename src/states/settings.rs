@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    controls::{Control, ControlInput},
+    localization::{Localization, Localized},
+    settings::Settings,
+    ui::{
+        constants::{SKY_BLUE, UI_YELLOW, UI_YELLOW_HOVER},
+        settings::spawn_settings_panel,
+        UiRoot,
+    },
+};
+
+use super::{loading::FontAssets, GameState};
+
+pub struct SettingsScreenPlugin;
+
+impl Plugin for SettingsScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Settings), setup_settings_screen)
+            .add_systems(
+                Update,
+                (back_button, back_on_pause).run_if(in_state(GameState::Settings)),
+            )
+            .add_systems(OnExit(GameState::Settings), cleanup_settings_screen);
+    }
+}
+
+#[derive(Component)]
+struct SettingsRoot;
+
+#[derive(Component)]
+struct BackButton;
+
+fn setup_settings_screen(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(SettingsRoot)
+        .insert(UiRoot)
+        .insert(Name::new("Settings UI"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(20.0)),
+                        row_gap: Val::Px(20.0),
+                        ..Default::default()
+                    },
+                    background_color: SKY_BLUE.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(localization.text(
+                        Localized::new("settings.title"),
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 45.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                    spawn_settings_panel(
+                        parent,
+                        &font_assets.fira_sans,
+                        &settings.data,
+                        &localization,
+                    );
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                width: Val::Px(220.0),
+                                height: Val::Px(60.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            background_color: UI_YELLOW.into(),
+                            ..Default::default()
+                        })
+                        .insert(BackButton)
+                        .with_children(|parent| {
+                            parent.spawn(localization.text(
+                                Localized::new("common.back"),
+                                TextStyle {
+                                    font: font_assets.fira_sans.clone(),
+                                    font_size: 40.0,
+                                    color: Color::WHITE,
+                                },
+                            ));
+                        });
+                });
+        });
+}
+
+fn back_button(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<BackButton>),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                sound_channel.play(audio_assets.pop.clone());
+                next_state.set(GameState::MainMenu);
+            }
+            Interaction::Hovered => {
+                *color = UI_YELLOW_HOVER.into();
+            }
+            Interaction::None => {
+                *color = UI_YELLOW.into();
+            }
+        }
+    }
+}
+
+fn back_on_pause(input: ControlInput, mut next_state: ResMut<NextState<GameState>>) {
+    if input.just_pressed(Control::Pause) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn cleanup_settings_screen(mut commands: Commands, root: Query<Entity, With<SettingsRoot>>) {
+    commands.entity(root.single()).despawn_recursive();
+}
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-use crate::{post_process::TransitionSettings, ui::UiRoot, util::cubic_ease_in_out};
+use crate::{
+    level_manager::PendingLevel, post_process::TransitionSettings, ui::UiRoot,
+    util::cubic_ease_in_out,
+};
 
 use super::GameState;
 
@@ -17,10 +20,13 @@ pub fn update_transition_manager(
     mut transition_manager: ResMut<TransitionManager>,
     mut transition_settings: Query<&mut TransitionSettings>,
     mut state: ResMut<NextState<GameState>>,
+    pending_level: Option<Res<PendingLevel>>,
     time: Res<Time>,
 ) {
     match transition_manager.as_mut() {
         TransitionManager::Normal => {}
+        // stay faded out while the next level is still being made
+        TransitionManager::TransitioningIn(_) if pending_level.is_some() => {}
         TransitionManager::TransitioningOut(p) => {
             if *p >= 1.0 {
                 state.set(GameState::LevelTransition);
@@ -0,0 +1,272 @@
+//! Counts of what was done in each level, for this session and kept between sessions, which are
+//! shown at the end of the campaign.
+
+use std::ops::AddAssign;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level_manager::LevelManager,
+    replay::Playback,
+    rules::{Outcome, Puzzle, Step},
+    save::{default_store, Stored},
+    states::{editor::EditorLevel, GameState, PauseState},
+};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Stats::load(default_store("stats")))
+            .init_resource::<SessionStats>()
+            .init_resource::<AttemptClock>()
+            .add_systems(OnEnter(GameState::Level), start_clock)
+            .add_systems(
+                Update,
+                tick_clock
+                    .run_if(in_state(GameState::Level))
+                    .run_if(in_state(PauseState::Playing))
+                    .run_if(|puzzle: Res<Puzzle>| !puzzle.is_complete()),
+            )
+            // watching a replay or playtesting an edited level doesn't count
+            .add_systems(
+                OnExit(GameState::Level),
+                record_attempt
+                    .run_if(not(resource_exists::<Playback>()))
+                    .run_if(not(resource_exists::<EditorLevel>())),
+            )
+            .add_systems(
+                OnTransition {
+                    from: GameState::Level,
+                    to: GameState::LevelReload,
+                },
+                count_restart
+                    .run_if(not(resource_exists::<Playback>()))
+                    .run_if(not(resource_exists::<EditorLevel>())),
+            );
+    }
+}
+
+/// What was done over some number of attempts at levels
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Counts {
+    /// Moves made, including ones later undone but not turning on the spot
+    pub moves: u32,
+    pub undos: u32,
+    pub restarts: u32,
+    /// Time spent climbing, not counting after reaching the flag
+    pub seconds: f64,
+    pub ladders: u32,
+    pub ropes: u32,
+    pub runes: u32,
+    pub potions: u32,
+    /// Levels finished with the scale
+    pub scales: u32,
+    /// Levels finished
+    pub finishes: u32,
+}
+
+impl Counts {
+    /// Count up the steps of an attempt at a level. Only actions that the rules allowed count.
+    pub fn of_steps(steps: &[Step]) -> Self {
+        let mut counts = Counts::default();
+        for step in steps {
+            let Step::Action(_, Ok(outcome)) = step else {
+                continue;
+            };
+            match outcome {
+                Outcome::Moved | Outcome::Teleported => counts.moves += 1,
+                Outcome::PlacedLadder => counts.ladders += 1,
+                Outcome::PlacedRope => counts.ropes += 1,
+                Outcome::PlacedRune => counts.runes += 1,
+                Outcome::DrankPotion => counts.potions += 1,
+                Outcome::Undone => counts.undos += 1,
+                Outcome::Turned | Outcome::PickedUpLadder => {}
+            }
+        }
+        counts
+    }
+
+    /// Pieces of equipment placed or drunk
+    pub fn equipment(&self) -> u32 {
+        self.ladders + self.ropes + self.runes + self.potions
+    }
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Self) {
+        self.moves += other.moves;
+        self.undos += other.undos;
+        self.restarts += other.restarts;
+        self.seconds += other.seconds;
+        self.ladders += other.ladders;
+        self.ropes += other.ropes;
+        self.runes += other.runes;
+        self.potions += other.potions;
+        self.scales += other.scales;
+        self.finishes += other.finishes;
+    }
+}
+
+/// Counts for every level ever played, kept between sessions
+pub type Stats = Stored<StatsData>;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsData {
+    /// Indexed by the level's position in the campaign
+    pub levels: Vec<Counts>,
+    /// Generated levels, which aren't part of the campaign
+    pub generated: Counts,
+}
+
+impl StatsData {
+    /// Add to a campaign level's counts, or to the generated levels' if there is no index
+    pub fn record(&mut self, index: Option<usize>, counts: Counts) {
+        match index {
+            Some(index) => {
+                if self.levels.len() <= index {
+                    self.levels.resize_with(index + 1, Default::default);
+                }
+                self.levels[index] += counts;
+            }
+            None => self.generated += counts,
+        }
+    }
+
+    pub fn overall(&self) -> Counts {
+        let mut overall = self.generated;
+        for counts in &self.levels {
+            overall += *counts;
+        }
+        overall
+    }
+}
+
+/// Counts for every level played since the game started
+#[derive(Debug, Default, Resource)]
+pub struct SessionStats(pub Counts);
+
+/// Seconds spent on the attempt at the level being played
+#[derive(Debug, Default, Resource)]
+pub struct AttemptClock(f64);
+
+/// Time as hours, minutes and seconds, leaving out the hours if there aren't any
+pub fn clock_time(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+fn start_clock(mut clock: ResMut<AttemptClock>) {
+    clock.0 = 0.0;
+}
+
+fn tick_clock(time: Res<Time>, mut clock: ResMut<AttemptClock>) {
+    clock.0 += time.delta_seconds_f64();
+}
+
+/// Index of the campaign level being played, or `None` for a generated one
+fn campaign_index(level_manager: &LevelManager) -> Option<usize> {
+    level_manager
+        .generated
+        .is_none()
+        .then_some(level_manager.current)
+}
+
+fn save_stats(stats: &mut Stats) {
+    if let Err(e) = stats.save() {
+        warn!("Couldn't save the stats: {}", e);
+    }
+}
+
+/// Runs as the level is left, however it is left, while the puzzle still holds the attempt
+fn record_attempt(
+    puzzle: Res<Puzzle>,
+    clock: Res<AttemptClock>,
+    level_manager: Res<LevelManager>,
+    mut stats: ResMut<Stats>,
+    mut session: ResMut<SessionStats>,
+) {
+    let mut counts = Counts::of_steps(puzzle.steps());
+    counts.seconds = clock.0;
+    if puzzle.is_complete() {
+        counts.finishes = 1;
+        counts.scales = u32::from(puzzle.state.has_scale);
+    }
+    stats.data.record(campaign_index(&level_manager), counts);
+    session.0 += counts;
+    save_stats(&mut stats);
+}
+
+fn count_restart(
+    level_manager: Res<LevelManager>,
+    mut stats: ResMut<Stats>,
+    mut session: ResMut<SessionStats>,
+) {
+    let restart = Counts {
+        restarts: 1,
+        ..Default::default()
+    };
+    stats.data.record(campaign_index(&level_manager), restart);
+    session.0 += restart;
+    save_stats(&mut stats);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Action, CardinalDirection::*, Map, RuleError};
+
+    #[test]
+    fn steps_are_counted_when_they_come_off() {
+        let map = Map::from_heights(&[&[1, 1, 1]], &[]);
+        let mut puzzle = Puzzle::new(map, 5);
+        // the first press only turns to face east
+        for _ in 0..3 {
+            puzzle.apply(Action::Move(East)).unwrap();
+        }
+        puzzle.apply(Action::Undo).unwrap();
+        puzzle.apply(Action::Redo).unwrap();
+        assert_eq!(
+            puzzle.apply(Action::DrinkPotion),
+            Err(RuleError::LevelComplete)
+        );
+        let counts = Counts::of_steps(puzzle.steps());
+        assert_eq!(counts.moves, 3);
+        assert_eq!(counts.undos, 1);
+        assert_eq!(counts.equipment(), 0);
+    }
+
+    #[test]
+    fn counts_add_up_per_level_and_overall() {
+        let mut data = StatsData::default();
+        let moves = |moves| Counts {
+            moves,
+            seconds: 1.5,
+            ..Default::default()
+        };
+        data.record(Some(2), moves(4));
+        data.record(Some(2), moves(6));
+        data.record(None, moves(1));
+        assert_eq!(data.levels.len(), 3);
+        assert_eq!(data.levels[2].moves, 10);
+        assert_eq!(data.levels[0], Counts::default());
+        let overall = data.overall();
+        assert_eq!(overall.moves, 11);
+        assert_eq!(overall.seconds, 4.5);
+    }
+
+    #[test]
+    fn times_read_as_clocks() {
+        assert_eq!(clock_time(59.9), "0:59");
+        assert_eq!(clock_time(754.0), "12:34");
+        assert_eq!(clock_time(3723.0), "1:02:03");
+    }
+}
@@ -0,0 +1,140 @@
+//! Apps for the tests of systems that read the controls, with the game's own plugins, and ways
+//! of pressing keys and gamepad buttons in them.
+
+use bevy::{
+    input::{
+        gamepad::{
+            GamepadButtonChangedEvent, GamepadConnection, GamepadConnectionEvent, GamepadEvent,
+            GamepadInfo,
+        },
+        keyboard::KeyboardInput,
+        ButtonState, InputPlugin,
+    },
+    prelude::*,
+};
+use bevy_asset_loader::prelude::*;
+use bevy_kira_audio::AudioChannel;
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    clouds::CloudMaterial,
+    controls::{Controls, ControlsPlugin},
+    equipment::EquipmentPlugin,
+    level_manager::LevelManager,
+    localization::Localization,
+    player::PlayerPlugin,
+    rules::Map,
+    save::{MemoryStore, SaveGame},
+    settings::Settings,
+    states::{
+        level::{Level, LevelPlugin},
+        loading::{FontAssets, ModelAssets, TextureAssets},
+        pause::PausePlugin,
+        transition::TransitionManager,
+        GameState,
+    },
+    ui::{
+        equipment::{InfoUiRoot, PickingUiRoot},
+        focus::Focus,
+    },
+};
+
+/// An app with the default bindings, kept in memory rather than on disk
+pub fn input_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(InputPlugin)
+        .insert_resource(Controls::load(Box::<MemoryStore>::default()))
+        .add_plugins(ControlsPlugin);
+    app
+}
+
+/// An app playing a level on the map with the plugins that play it in the game, which nothing
+/// has been picked or done in yet. Nothing is drawn, and the models are only handles.
+pub fn level_app(map: Map, stamina_budget: u16) -> App {
+    let level = Level {
+        name: "Test".to_string(),
+        map,
+        stamina_budget,
+        weight_budget: 0,
+        ladder_unlocked: false,
+        rope_unlocked: false,
+        potion_unlocked: false,
+        rewind_unlocked: false,
+        par: None,
+        tutorial: Vec::new(),
+    };
+    let mut app = input_app();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .add_asset::<Mesh>()
+        .add_asset::<StandardMaterial>()
+        .add_asset::<CloudMaterial>()
+        .add_state::<GameState>()
+        .insert_resource(SaveGame::load(Box::<MemoryStore>::default()))
+        .insert_resource(Settings::load(Box::<MemoryStore>::default()))
+        .insert_resource(LevelManager {
+            levels: vec![level],
+            ..Default::default()
+        })
+        .init_resource::<TransitionManager>()
+        .init_resource::<Focus>()
+        .init_resource::<Localization>()
+        .init_resource::<AudioChannel<SoundChannel>>()
+        .init_collection::<AudioAssets>()
+        .init_collection::<FontAssets>()
+        .init_collection::<ModelAssets>()
+        .init_collection::<TextureAssets>()
+        .add_plugins((LevelPlugin, PlayerPlugin, EquipmentPlugin, PausePlugin));
+    // the equipment picker is shown until the first move, as the UI would have it
+    app.world.spawn((PickingUiRoot, Visibility::Visible));
+    app.world.spawn((InfoUiRoot, Visibility::Hidden));
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Level);
+    app.update();
+    app
+}
+
+fn send_keys(app: &mut App, key_codes: &[KeyCode], state: ButtonState) {
+    for &key_code in key_codes {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key_code),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+    app.update();
+}
+
+/// Press the keys together, then let them all go
+pub fn tap(app: &mut App, key_codes: &[KeyCode]) {
+    send_keys(app, key_codes, ButtonState::Pressed);
+    send_keys(app, key_codes, ButtonState::Released);
+}
+
+/// Plug in a gamepad, which is the only one
+pub fn connect_gamepad(app: &mut App) -> Gamepad {
+    let pad = Gamepad::new(0);
+    app.world
+        .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+            pad,
+            GamepadConnection::Connected(GamepadInfo {
+                name: "Test pad".to_string(),
+            }),
+        )));
+    app.update();
+    pad
+}
+
+/// Press a gamepad button, then let it go
+pub fn tap_button(app: &mut App, pad: Gamepad, button_type: GamepadButtonType) {
+    for value in [1.0, 0.0] {
+        app.world
+            .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                pad,
+                button_type,
+                value,
+            )));
+        app.update();
+    }
+}
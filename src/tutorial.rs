@@ -0,0 +1,318 @@
+//! Tutorial prompts that levels can list, each shown once something happens in the level for the
+//! first time and then remembered between sessions so it isn't shown again.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level_manager::LevelManager,
+    replay::Playback,
+    rules::{Action, CardinalDirection, Outcome, PlayerState, Puzzle, PuzzleState, Step},
+    save::{default_store, Stored},
+    states::GameState,
+};
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TutorialProgress::load(default_store("tutorial")))
+            .add_systems(
+                OnEnter(GameState::Level),
+                // watching a replay shouldn't use up the prompts
+                start_tutorial.run_if(not(resource_exists::<Playback>())),
+            )
+            .add_systems(
+                Update,
+                check_triggers
+                    .run_if(in_state(GameState::Level))
+                    .run_if(resource_exists::<Tutorial>())
+                    .run_if(resource_changed::<Puzzle>()),
+            )
+            .add_systems(OnExit(GameState::Level), end_tutorial);
+    }
+}
+
+/// When a prompt is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trigger {
+    /// As soon as the level starts
+    Start,
+    /// Standing next to a face that can be climbed
+    NextToClimbable,
+    PlaceLadder,
+    PlaceRope,
+    PlaceRune,
+    DrinkPotion,
+    /// Pulled back by a rune whose countdown ran out
+    Teleport,
+    /// Going through a cave to the other end
+    EnterCave,
+    PickUpGem,
+    PickUpScale,
+    /// Stamina down to this much or less
+    LowStamina(u16),
+}
+
+/// A prompt as listed in a level file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TutorialPrompt {
+    pub trigger: Trigger,
+    /// The key of the prompt's text in the locale files. Prompts with the same text are only
+    /// seen once, whichever level they are in.
+    pub text: String,
+}
+
+impl Trigger {
+    /// Whether the trigger goes off now that the puzzle has changed from `before`, which is
+    /// `None` when the level has just started
+    pub fn fires(&self, puzzle: &Puzzle, before: Option<&PuzzleState>) -> bool {
+        let state = &puzzle.state;
+        let last_outcome = match puzzle.steps().last() {
+            Some(Step::Action(_, Ok(outcome))) => Some(*outcome),
+            _ => None,
+        };
+        match self {
+            Trigger::Start => before.is_none(),
+            Trigger::NextToClimbable => next_to_climbable(puzzle, state),
+            Trigger::PlaceLadder => last_outcome == Some(Outcome::PlacedLadder),
+            Trigger::PlaceRope => last_outcome == Some(Outcome::PlacedRope),
+            Trigger::PlaceRune => last_outcome == Some(Outcome::PlacedRune),
+            Trigger::DrinkPotion => last_outcome == Some(Outcome::DrankPotion),
+            Trigger::Teleport => last_outcome == Some(Outcome::Teleported),
+            Trigger::EnterCave => before.is_some_and(|before| went_through_cave(puzzle, before)),
+            Trigger::PickUpGem => before.is_some_and(|before| state.gems.len() > before.gems.len()),
+            Trigger::PickUpScale => {
+                state.has_scale && !before.is_some_and(|before| before.has_scale)
+            }
+            Trigger::LowStamina(stamina) => state.player.stamina <= *stamina,
+        }
+    }
+}
+
+fn next_to_climbable(puzzle: &Puzzle, state: &PuzzleState) -> bool {
+    let map = &puzzle.map;
+    let player = &state.player;
+    if !matches!(player.state, PlayerState::Standing(_)) {
+        return false;
+    }
+    let (x, y) = (player.grid_pos_x, player.grid_pos_y);
+    let elevation = map.grid_heights[y as usize][x as usize];
+    [
+        CardinalDirection::North,
+        CardinalDirection::East,
+        CardinalDirection::South,
+        CardinalDirection::West,
+    ]
+    .into_iter()
+    .any(|direction| {
+        map.step(x, y, direction, 1).is_some_and(|(x, y)| {
+            map.grid_climbable[y as usize][x as usize]
+                && map.grid_heights[y as usize][x as usize] > elevation
+        })
+    })
+}
+
+/// Whether the last move went into one end of a pair of caves and came out of the other
+fn went_through_cave(puzzle: &Puzzle, before: &PuzzleState) -> bool {
+    if !matches!(
+        puzzle.steps().last(),
+        Some(Step::Action(Action::Move(_), Ok(_)))
+    ) {
+        return false;
+    }
+    let from = (before.player.grid_pos_x, before.player.grid_pos_y);
+    let to = (
+        puzzle.state.player.grid_pos_x,
+        puzzle.state.player.grid_pos_y,
+    );
+    puzzle
+        .map
+        .cave_at(from)
+        .is_some_and(|cave| cave.other_end(from) == Some(to))
+}
+
+/// The prompts that have been seen, kept between sessions
+pub type TutorialProgress = Stored<TutorialData>;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TutorialData {
+    /// The text keys of the prompts dismissed so far
+    pub seen: BTreeSet<String>,
+}
+
+/// The prompts of the level being played
+#[derive(Debug, Default, Resource)]
+pub struct Tutorial {
+    /// Prompts that haven't been seen, waiting for their trigger
+    waiting: Vec<TutorialPrompt>,
+    /// Prompts whose trigger went off, the one on screen first
+    shown: VecDeque<TutorialPrompt>,
+    /// The state when the triggers were last checked
+    before: Option<PuzzleState>,
+}
+
+impl Tutorial {
+    pub fn new(prompts: &[TutorialPrompt], seen: &BTreeSet<String>) -> Self {
+        Self {
+            waiting: prompts
+                .iter()
+                .filter(|prompt| !seen.contains(&prompt.text))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// The prompt on screen, if any
+    pub fn current(&self) -> Option<&TutorialPrompt> {
+        self.shown.front()
+    }
+
+    /// Queue up every waiting prompt whose trigger has gone off
+    pub fn check(&mut self, puzzle: &Puzzle) {
+        let before = self.before.replace(puzzle.state.clone());
+        let (fired, waiting): (Vec<_>, Vec<_>) = self
+            .waiting
+            .drain(..)
+            .partition(|prompt| prompt.trigger.fires(puzzle, before.as_ref()));
+        self.waiting = waiting;
+        self.shown.extend(fired);
+    }
+
+    /// Put the prompt on screen away, remembering that it has been seen
+    pub fn dismiss(&mut self, seen: &mut BTreeSet<String>) {
+        if let Some(prompt) = self.shown.pop_front() {
+            seen.insert(prompt.text);
+        }
+    }
+}
+
+fn start_tutorial(
+    mut commands: Commands,
+    level_manager: Res<LevelManager>,
+    progress: Res<TutorialProgress>,
+) {
+    let level = level_manager.get_current_level();
+    commands.insert_resource(Tutorial::new(&level.tutorial, &progress.data.seen));
+}
+
+fn check_triggers(puzzle: Res<Puzzle>, mut tutorial: ResMut<Tutorial>) {
+    tutorial.check(&puzzle);
+}
+
+fn end_tutorial(mut commands: Commands) {
+    commands.remove_resource::<Tutorial>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rules::{CaveData, CaveLock, Gem, GemColour, Inventory, Map};
+
+    use CardinalDirection::*;
+
+    fn prompt(trigger: Trigger, text: &str) -> TutorialPrompt {
+        TutorialPrompt {
+            trigger,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn prompts_show_in_turn_and_only_until_seen() {
+        // a wall to the east of the second square
+        let map = Map::from_heights(&[&[1, 1, 3], &[1, 1, 1]], &[(2, 0)]);
+        let mut puzzle = Puzzle::new(map, 10);
+        let prompts = [
+            prompt(Trigger::Start, "start"),
+            prompt(Trigger::NextToClimbable, "climb"),
+            prompt(Trigger::PlaceLadder, "ladder"),
+            prompt(Trigger::LowStamina(2), "tired"),
+        ];
+        let mut seen = BTreeSet::new();
+        let mut tutorial = Tutorial::new(&prompts, &seen);
+        tutorial.check(&puzzle);
+        assert_eq!(tutorial.current(), Some(&prompts[0]));
+
+        puzzle
+            .set_loadout(Inventory {
+                ladder_count: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        for action in [Action::Move(East), Action::Move(East)] {
+            puzzle.apply(action).unwrap();
+        }
+        tutorial.check(&puzzle);
+        assert_eq!(tutorial.current(), Some(&prompts[0]));
+        tutorial.dismiss(&mut seen);
+        assert_eq!(tutorial.current(), Some(&prompts[1]));
+        tutorial.dismiss(&mut seen);
+        assert_eq!(tutorial.current(), None);
+
+        puzzle.apply(Action::UseLadder).unwrap();
+        puzzle.apply(Action::Move(East)).unwrap();
+        tutorial.check(&puzzle);
+        assert_eq!(tutorial.current(), None);
+        assert_eq!(puzzle.apply(Action::Undo), Ok(Outcome::Undone));
+        tutorial.check(&puzzle);
+        assert_eq!(tutorial.current(), None);
+
+        // the ladder was placed between checks, so it is the last step that counts
+        puzzle.apply(Action::UseLadder).unwrap();
+        puzzle.apply(Action::UseLadder).unwrap();
+        tutorial.check(&puzzle);
+        assert_eq!(tutorial.current(), Some(&prompts[2]));
+        tutorial.dismiss(&mut seen);
+
+        let again = Tutorial::new(&prompts, &seen);
+        assert_eq!(again.waiting, [prompts[3].clone()]);
+    }
+
+    #[test]
+    fn going_through_the_cave_either_way_counts() {
+        let mut map = Map::from_heights(&[&[1, 1, 1], &[1, 1, 1], &[1, 1, 1]], &[]);
+        map.caves = vec![CaveData {
+            first_pos: (0, 1),
+            second_pos: (2, 1),
+            entrance: North,
+            lock: CaveLock::AnyGem,
+        }];
+        map.gems = vec![Gem {
+            pos: (1, 0),
+            colour: GemColour::Red,
+        }];
+        let mut puzzle = Puzzle::new(map, 20);
+        let prompts = [
+            prompt(Trigger::EnterCave, "cave"),
+            prompt(Trigger::PickUpGem, "gem"),
+        ];
+        let mut tutorial = Tutorial::new(&prompts, &BTreeSet::new());
+        tutorial.check(&puzzle);
+        for action in [Action::Move(East), Action::Move(East), Action::Move(East)] {
+            puzzle.apply(action).unwrap();
+            tutorial.check(&puzzle);
+        }
+        assert_eq!(tutorial.current(), Some(&prompts[1]));
+        tutorial.dismiss(&mut BTreeSet::new());
+
+        for action in [
+            Action::Move(South),
+            Action::Move(South),
+            Action::Move(North),
+        ] {
+            puzzle.apply(action).unwrap();
+            tutorial.check(&puzzle);
+        }
+        assert_eq!(tutorial.current(), None);
+        puzzle.apply(Action::Move(North)).unwrap();
+        tutorial.check(&puzzle);
+        assert_eq!(tutorial.current(), Some(&prompts[0]));
+    }
+}
@@ -1,6 +1,12 @@
 use bevy::prelude::*;
 
-use crate::{level_manager::LevelManager, scale::ScaleCounter, states::loading::FontAssets};
+use crate::{
+    level_manager::LevelManager,
+    localization::Localization,
+    scale::ScaleCounter,
+    states::loading::FontAssets,
+    stats::{clock_time, Counts, SessionStats, Stats},
+};
 
 use super::{
     constants::{SKY_BLUE, UI_YELLOW},
@@ -10,11 +16,42 @@ use super::{
 #[derive(Component)]
 pub struct EndUIRoot;
 
+/// A grid of text with the given number of columns, filled in a row at a time
+fn spawn_table(
+    parent: &mut ChildBuilder,
+    columns: usize,
+    cells: Vec<String>,
+    style: &TextStyle,
+    header: &TextStyle,
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Grid,
+                grid_template_columns: vec![GridTrack::auto(); columns],
+                column_gap: Val::Px(16.0),
+                row_gap: Val::Px(2.0),
+                margin: UiRect::top(Val::Px(10.0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for (index, cell) in cells.into_iter().enumerate() {
+                let style = if index < columns { header } else { style };
+                parent.spawn(TextBundle::from_section(cell, style.clone()));
+            }
+        });
+}
+
 pub fn setup_end_screen(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     scale_count: Res<ScaleCounter>,
     level_manager: Res<LevelManager>,
+    stats: Res<Stats>,
+    session: Res<SessionStats>,
+    localization: Res<Localization>,
 ) {
     // Count the total number of scales across all levels
     let mut number_of_scales = 0;
@@ -23,6 +60,69 @@ pub fn setup_end_screen(
             number_of_scales += 1;
         }
     }
+    let text_style = |font_size: f32| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    let panel = |width: f32| NodeBundle {
+        style: Style {
+            width: Val::Px(width),
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(10.0)),
+            ..Default::default()
+        },
+        background_color: UI_YELLOW.into(),
+        ..Default::default()
+    };
+
+    // this session against every session so far
+    let overall = stats.data.overall();
+    let rows: [(&str, fn(&Counts) -> String); 10] = [
+        ("end.levels_finished", |counts| counts.finishes.to_string()),
+        ("end.moves", |counts| counts.moves.to_string()),
+        ("end.undos", |counts| counts.undos.to_string()),
+        ("end.restarts", |counts| counts.restarts.to_string()),
+        ("end.time_climbing", |counts| clock_time(counts.seconds)),
+        ("end.ladders", |counts| counts.ladders.to_string()),
+        ("end.ropes", |counts| counts.ropes.to_string()),
+        ("end.runes", |counts| counts.runes.to_string()),
+        ("end.potions", |counts| counts.potions.to_string()),
+        ("end.scales_found", |counts| counts.scales.to_string()),
+    ];
+    let mut totals = vec![
+        String::new(),
+        localization.get("end.this_session"),
+        localization.get("end.all_time"),
+    ];
+    for (key, value) in rows {
+        totals.extend([localization.get(key), value(&session.0), value(&overall)]);
+    }
+
+    let mut by_level: Vec<String> = [
+        "end.level",
+        "end.moves",
+        "end.undos",
+        "end.restarts",
+        "end.time",
+        "end.equipment",
+    ]
+    .into_iter()
+    .map(|key| localization.get(key))
+    .collect();
+    for index in 0..level_manager.levels.len() {
+        let counts = stats.data.levels.get(index).copied().unwrap_or_default();
+        by_level.extend([
+            (index + 1).to_string(),
+            counts.moves.to_string(),
+            counts.undos.to_string(),
+            counts.restarts.to_string(),
+            clock_time(counts.seconds),
+            counts.equipment().to_string(),
+        ]);
+    }
+
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -30,6 +130,7 @@ pub fn setup_end_screen(
                 height: Val::Percent(100.0),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
+                column_gap: Val::Px(20.0),
                 ..Default::default()
             },
             background_color: SKY_BLUE.into(),
@@ -39,52 +140,34 @@ pub fn setup_end_screen(
         .insert(UiRoot)
         .insert(Name::new("End screen UI"))
         .with_children(|parent| {
-            parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        width: Val::Px(600.0),
-                        height: Val::Px(190.0),
-                        justify_content: JustifyContent::SpaceAround,
-                        align_content: AlignContent::Center,
-                        flex_direction: FlexDirection::Column,
-                        padding: UiRect::all(Val::Px(10.0)),
-                        ..Default::default()
-                    },
-                    background_color: UI_YELLOW.into(),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "You've climbed every mountain! Good job!",
-                        TextStyle {
-                            font: font_assets.fira_sans.clone(),
-                            font_size: 45.0,
-                            color: Color::WHITE,
-                        },
-                    ));
-                    parent.spawn(TextBundle::from_section(
-                        format!("Collected {}/{} scales.", scale_count.0, number_of_scales),
-                        TextStyle {
-                            font: font_assets.fira_sans.clone(),
-                            font_size: 35.0,
-                            color: Color::WHITE,
-                        },
-                    ));
-                    let message = if scale_count.0 == number_of_scales {
-                        "You got them all. Impressive!"
-                    } else if scale_count.0 < 3 {
-                        "Oh well. There's always next time..."
-                    } else {
-                        "Hey, not bad."
-                    };
-                    parent.spawn(TextBundle::from_section(
-                        message,
-                        TextStyle {
-                            font: font_assets.fira_sans.clone(),
-                            font_size: 25.0,
-                            color: Color::WHITE,
-                        },
-                    ));
-                });
+            parent.spawn(panel(520.0)).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    localization.get("end.title"),
+                    text_style(32.0),
+                ));
+                parent.spawn(TextBundle::from_section(
+                    localization.format("end.scales", &[&scale_count.0, &number_of_scales]),
+                    text_style(28.0),
+                ));
+                let message = if scale_count.0 == number_of_scales {
+                    "end.all_scales"
+                } else if scale_count.0 < 3 {
+                    "end.few_scales"
+                } else {
+                    "end.some_scales"
+                };
+                parent.spawn(TextBundle::from_section(
+                    localization.get(message),
+                    text_style(22.0),
+                ));
+                spawn_table(parent, 3, totals, &text_style(20.0), &text_style(22.0));
+            });
+            parent.spawn(panel(480.0)).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    localization.get("end.by_level"),
+                    text_style(28.0),
+                ));
+                spawn_table(parent, 6, by_level, &text_style(16.0), &text_style(18.0));
+            });
         });
 }
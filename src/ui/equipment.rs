@@ -1,9 +1,10 @@
 use bevy::prelude::*;
 
 use crate::{
-    equipment::Inventory,
+    controls::{Control, Controls, KeyBindings},
     level_manager::LevelManager,
-    player::Player,
+    localization::{Localization, Localized},
+    rules::{Puzzle, LADDER_WEIGHT, POTION_STAMINA, POTION_WEIGHT, REWIND_WEIGHT, ROPE_WEIGHT},
     states::{
         level::DespawnOnTransition,
         loading::{FontAssets, TextureAssets},
@@ -33,7 +34,6 @@ pub struct AddButton {
 #[derive(Debug, Component)]
 pub struct SubtractButton {
     pub equipment: Equipment,
-    pub weight: u8,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -46,14 +46,15 @@ pub enum Equipment {
 
 struct EquipmentInfo {
     pub variant: Equipment,
-    pub name: String,
-    pub description: String,
+    pub name: Localized,
+    pub description: Localized,
     pub weight: u8,
 }
 
 fn draw_equimpment_card(
     parent: &mut ChildBuilder,
     font: Handle<Font>,
+    localization: &Localization,
     equipment: EquipmentInfo,
     texture: Handle<Image>,
 ) {
@@ -98,7 +99,7 @@ fn draw_equimpment_card(
                             ..Default::default()
                         })
                         .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
+                            parent.spawn(localization.text(
                                 equipment.name,
                                 TextStyle {
                                     font: font.clone(),
@@ -120,8 +121,8 @@ fn draw_equimpment_card(
                                 },
                                 ..Default::default()
                             });
-                            parent.spawn(TextBundle::from_section(
-                                format!("weight: {}", equipment.weight),
+                            parent.spawn(localization.text(
+                                Localized::with_args("equipment.weight", &[&equipment.weight]),
                                 TextStyle {
                                     font: font.clone(),
                                     font_size: 15.0,
@@ -139,7 +140,7 @@ fn draw_equimpment_card(
                             ..Default::default()
                         })
                         .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
+                            parent.spawn(localization.text(
                                 equipment.description,
                                 TextStyle {
                                     font: font.clone(),
@@ -176,7 +177,6 @@ fn draw_equimpment_card(
                         })
                         .insert(SubtractButton {
                             equipment: equipment.variant,
-                            weight: equipment.weight,
                         })
                         .with_children(|parent| {
                             parent.spawn(TextBundle::from_section(
@@ -208,10 +208,14 @@ fn draw_equimpment_card(
                         .with_children(|parent| {
                             parent
                                 .spawn(TextBundle::from_sections([
-                                    TextSection::new("have: ", style.clone()),
+                                    TextSection::new(
+                                        localization.get("equipment.have"),
+                                        style.clone(),
+                                    ),
                                     TextSection::new("0", style),
                                 ]))
-                                .insert(InventoryCounter(equipment.variant));
+                                .insert(InventoryCounter(equipment.variant))
+                                .insert(Localized::new("equipment.have"));
                         });
                     // + button
                     parent
@@ -252,6 +256,7 @@ pub fn draw_equimpment_cards(
     font_assets: Res<FontAssets>,
     level_manager: Res<LevelManager>,
     texture_assets: Res<TextureAssets>,
+    localization: Res<Localization>,
 ) {
     commands
         .spawn(NodeBundle {
@@ -265,98 +270,118 @@ pub fn draw_equimpment_cards(
                 ..Default::default()
             },
             ..Default::default()
-        }).insert(PickingUiRoot)
+        })
+        .insert(PickingUiRoot)
         .insert(UiRoot)
         .insert(DespawnOnTransition)
         .insert(Name::new("Equipment Cards UI"))
         .with_children(|parent| {
             let level = level_manager.get_current_level();
-            if level.ladder_unlocked || level.rope_unlocked || level.potion_unlocked || level.rewind_unlocked {
+            if level.ladder_unlocked
+                || level.rope_unlocked
+                || level.potion_unlocked
+                || level.rewind_unlocked
+            {
                 // Draw weight budget
-                parent.spawn(NodeBundle {
-                    style: Style {
-                        width: Val::Px(380.0),
-                        height: Val::Px(50.0),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        align_self: AlignSelf::Center,
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(380.0),
+                            height: Val::Px(50.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            align_self: AlignSelf::Center,
+                            ..Default::default()
+                        },
+                        background_color: UI_YELLOW.into(),
                         ..Default::default()
-                    },
-                    background_color: UI_YELLOW.into(),
-                    ..Default::default()
-                }).with_children(|parent| {
-                    let style = TextStyle {
-                        font: font_assets.fira_sans.clone(),
-                        font_size: 35.0,
-                        color: Color::WHITE
-                    };
-                    parent.spawn(TextBundle::from_sections([
-                        TextSection::new("Current weight: ", style.clone()),
-                        TextSection::new("0", style.clone()),
-                        TextSection::new(format!("/{}", level.weight_budget), style),
-                    ]))
-                    .insert(WeightText);
-                });
+                    })
+                    .with_children(|parent| {
+                        let style = TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 35.0,
+                            color: Color::WHITE,
+                        };
+                        parent
+                            .spawn(TextBundle::from_sections([
+                                TextSection::new(
+                                    localization.get("equipment.current_weight"),
+                                    style.clone(),
+                                ),
+                                TextSection::new("0", style.clone()),
+                                TextSection::new(format!("/{}", level.weight_budget), style),
+                            ]))
+                            .insert(WeightText)
+                            .insert(Localized::new("equipment.current_weight"));
+                    });
             }
             if level.ladder_unlocked {
                 draw_equimpment_card(
                     parent,
                     font_assets.fira_sans.clone(),
+                    &localization,
                     EquipmentInfo {
                         variant: Equipment::Ladder,
-                        name: "Ladder".to_string(),
-                        description: "Used to climb up two squares using less stamina. Can also be placed horizontally to cross gaps. Can be picked up again and reused.".to_string(),
-                        weight: 2,
+                        name: Localized::new("equipment.ladder"),
+                        description: Localized::new("equipment.ladder.description"),
+                        weight: LADDER_WEIGHT,
                     },
-                    texture_assets.ladder_icon.clone()
+                    texture_assets.ladder_icon.clone(),
                 );
             }
             if level.rope_unlocked {
                 draw_equimpment_card(
                     parent,
                     font_assets.fira_sans.clone(),
+                    &localization,
                     EquipmentInfo {
                         variant: Equipment::Rope,
-                        name: "Rope".to_string(),
-                        description: "Used to descend/ascend cliffs of any height using less stamina. Cannot be picked back up once placed.".to_string(),
-                        weight: 1,
+                        name: Localized::new("equipment.rope"),
+                        description: Localized::new("equipment.rope.description"),
+                        weight: ROPE_WEIGHT,
                     },
-                    texture_assets.rope_icon.clone()
+                    texture_assets.rope_icon.clone(),
                 );
             }
             if level.rewind_unlocked {
                 draw_equimpment_card(
                     parent,
                     font_assets.fira_sans.clone(),
+                    &localization,
                     EquipmentInfo {
                         variant: Equipment::Rewind,
-                        name: "Rune of Rewind".to_string(),
-                        description: "Once placed you have 5 turns until you are teleported back to the location it was placed, reclaiming any stamina spent since. Equipment placed/picked up remains.".to_string(),
-                        weight: 1,
+                        name: Localized::new("equipment.rune"),
+                        description: Localized::new("equipment.rune.description"),
+                        weight: REWIND_WEIGHT,
                     },
-                    texture_assets.rune_icon.clone()
+                    texture_assets.rune_icon.clone(),
                 );
             }
             if level.potion_unlocked {
                 draw_equimpment_card(
                     parent,
                     font_assets.fira_sans.clone(),
+                    &localization,
                     EquipmentInfo {
                         variant: Equipment::Potion,
-                        name: "Stamina Potion".to_string(),
-                        description: "A flask of green liquid. Grants an extra point of stamina.".to_string(),
-                        weight: 1
+                        name: Localized::new("equipment.potion"),
+                        description: Localized::with_args(
+                            "equipment.potion.description",
+                            &[&POTION_STAMINA],
+                        ),
+                        weight: POTION_WEIGHT,
                     },
-                    texture_assets.potion_icon.clone()
+                    texture_assets.potion_icon.clone(),
                 );
             }
         });
 }
 
 pub fn update_inventory_counters(
-    inventory: Res<Inventory>,
+    puzzle: Res<Puzzle>,
     mut counters: Query<(&mut Text, &InventoryCounter)>,
 ) {
+    let inventory = &puzzle.state.inventory;
     for (mut text, counter) in counters.iter_mut() {
         match counter.0 {
             Equipment::Ladder => text.sections[1].value = format!("{}", inventory.ladder_count),
@@ -367,9 +392,9 @@ pub fn update_inventory_counters(
     }
 }
 
-pub fn update_weight_text(mut text: Query<&mut Text, With<WeightText>>, inventory: Res<Inventory>) {
+pub fn update_weight_text(mut text: Query<&mut Text, With<WeightText>>, puzzle: Res<Puzzle>) {
     for mut text in text.iter_mut() {
-        text.sections[1].value = inventory.weight.to_string();
+        text.sections[1].value = puzzle.state.inventory.weight().to_string();
     }
 }
 
@@ -378,25 +403,25 @@ pub fn handle_add_buttons(
         (&Interaction, &mut BackgroundColor, &AddButton),
         (Changed<Interaction>, With<Button>),
     >,
-    mut inventory: ResMut<Inventory>,
+    mut puzzle: ResMut<Puzzle>,
     level_manager: Res<LevelManager>,
-    mut player: Query<&mut Player>,
 ) {
     for (interaction, mut color, add) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
-                if level_manager.get_current_level().weight_budget >= inventory.weight + add.weight
+                // equipment is picked before the first move, so this sets the starting loadout
+                let mut inventory = puzzle.state.inventory;
+                if level_manager.get_current_level().weight_budget
+                    >= inventory.weight() + add.weight
                 {
-                    inventory.weight += add.weight;
                     match add.equipment {
                         Equipment::Ladder => inventory.ladder_count += 1,
                         Equipment::Rope => inventory.rope_count += 1,
-                        Equipment::Potion => {
-                            inventory.potion_count += 1;
-                            player.get_single_mut().unwrap().stamina += 1;
-                        }
+                        Equipment::Potion => inventory.potion_count += 1,
                         Equipment::Rewind => inventory.rewind_count += 1,
                     }
+                    // turned down once the climb has started
+                    let _ = puzzle.set_loadout(inventory);
                 }
             }
             Interaction::Hovered => {
@@ -414,38 +439,23 @@ pub fn handle_subtract_buttons(
         (&Interaction, &mut BackgroundColor, &SubtractButton),
         (Changed<Interaction>, With<Button>),
     >,
-    mut inventory: ResMut<Inventory>,
-    mut player: Query<&mut Player>,
+    mut puzzle: ResMut<Puzzle>,
 ) {
     for (interaction, mut color, sub) in &mut interaction_query {
         match *interaction {
-            Interaction::Pressed => match sub.equipment {
-                Equipment::Ladder => {
-                    if inventory.ladder_count > 0 {
-                        inventory.ladder_count -= 1;
-                        inventory.weight -= sub.weight;
-                    }
-                }
-                Equipment::Rope => {
-                    if inventory.rope_count > 0 {
-                        inventory.rope_count -= 1;
-                        inventory.weight -= sub.weight;
-                    }
-                }
-                Equipment::Potion => {
-                    if inventory.potion_count > 0 {
-                        inventory.potion_count -= 1;
-                        inventory.weight -= sub.weight;
-                        player.get_single_mut().unwrap().stamina -= 1;
-                    }
-                }
-                Equipment::Rewind => {
-                    if inventory.rewind_count > 0 {
-                        inventory.rewind_count -= 1;
-                        inventory.weight -= sub.weight;
-                    }
+            Interaction::Pressed => {
+                let mut inventory = puzzle.state.inventory;
+                let count = match sub.equipment {
+                    Equipment::Ladder => &mut inventory.ladder_count,
+                    Equipment::Rope => &mut inventory.rope_count,
+                    Equipment::Potion => &mut inventory.potion_count,
+                    Equipment::Rewind => &mut inventory.rewind_count,
+                };
+                if *count > 0 {
+                    *count -= 1;
+                    let _ = puzzle.set_loadout(inventory);
                 }
-            },
+            }
             Interaction::Hovered => {
                 *color = UI_YELLOW_HOVER.into();
             }
@@ -456,11 +466,29 @@ pub fn handle_subtract_buttons(
     }
 }
 
+/// Swap the equipment picker for the inventory once anything has been done that can be undone,
+/// whether that was a move or using a piece of equipment
+pub fn hide_picking_ui(
+    puzzle: Res<Puzzle>,
+    mut picking_ui: Query<&mut Visibility, With<PickingUiRoot>>,
+    mut info_ui: Query<&mut Visibility, (With<InfoUiRoot>, Without<PickingUiRoot>)>,
+) {
+    if puzzle.can_undo() {
+        for mut visibility in picking_ui.iter_mut() {
+            *visibility = Visibility::Hidden;
+        }
+        for mut visibility in info_ui.iter_mut() {
+            *visibility = Visibility::Visible;
+        }
+    }
+}
+
 fn draw_inventory_icon(
     parent: &mut ChildBuilder,
     font: Handle<Font>,
     equipment: Equipment,
-    key: char,
+    bindings: &KeyBindings,
+    control: Control,
     texture: Handle<Image>,
 ) {
     parent
@@ -476,7 +504,7 @@ fn draw_inventory_icon(
         })
         .with_children(|parent| {
             // Key
-            draw_key(parent, font.clone(), key);
+            draw_key(parent, font.clone(), bindings, control);
             // Icon
             parent.spawn(ImageBundle {
                 image: UiImage {
@@ -528,6 +556,7 @@ pub fn draw_inventory_icons(
     font_assets: Res<FontAssets>,
     level_manager: Res<LevelManager>,
     texture_assets: Res<TextureAssets>,
+    controls: Res<Controls>,
 ) {
     commands
         .spawn(NodeBundle {
@@ -554,7 +583,8 @@ pub fn draw_inventory_icons(
                     parent,
                     font_assets.fira_sans.clone(),
                     Equipment::Ladder,
-                    '1',
+                    &controls,
+                    Control::Ladder,
                     texture_assets.ladder_icon.clone(),
                 );
             }
@@ -563,7 +593,8 @@ pub fn draw_inventory_icons(
                     parent,
                     font_assets.fira_sans.clone(),
                     Equipment::Rope,
-                    '2',
+                    &controls,
+                    Control::Rope,
                     texture_assets.rope_icon.clone(),
                 );
             }
@@ -572,9 +603,20 @@ pub fn draw_inventory_icons(
                     parent,
                     font_assets.fira_sans.clone(),
                     Equipment::Rewind,
-                    '3',
+                    &controls,
+                    Control::Rune,
                     texture_assets.rune_icon.clone(),
                 );
             }
+            if level.potion_unlocked {
+                draw_inventory_icon(
+                    parent,
+                    font_assets.fira_sans.clone(),
+                    Equipment::Potion,
+                    &controls,
+                    Control::Potion,
+                    texture_assets.potion_icon.clone(),
+                );
+            }
         });
 }
@@ -1,16 +1,33 @@
-use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
 
 use crate::{
-    player::Player,
+    controls::{Control, Controls},
+    localization::{Localization, Localized},
+    rules::{Puzzle, PuzzleState},
+    solver::{dead_end, DeadEnd},
     states::{level::DespawnOnTransition, loading::FontAssets, transition::TransitionManager},
 };
 
-use super::{constants::UI_YELLOW, keys::StaminaCosts, UiRoot};
+use super::{constants::UI_YELLOW, UiRoot};
 
 #[derive(Component)]
 pub struct FailureUIRoot;
 
-pub fn setup_failure_help(mut commands: Commands, font_assets: Res<FontAssets>) {
+pub fn setup_failure_help(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    controls: Res<Controls>,
+    localization: Res<Localization>,
+) {
+    // a new level, so whatever was found out about the last one no longer holds
+    commands.insert_resource(DeadEndCheck::default());
+    let text_style = |font_size: f32| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -20,6 +37,7 @@ pub fn setup_failure_help(mut commands: Commands, font_assets: Res<FontAssets>)
                 align_items: AlignItems::Center,
                 ..Default::default()
             },
+            visibility: Visibility::Hidden,
             ..Default::default()
         })
         .insert(DespawnOnTransition)
@@ -31,7 +49,6 @@ pub fn setup_failure_help(mut commands: Commands, font_assets: Res<FontAssets>)
                 .spawn(NodeBundle {
                     style: Style {
                         width: Val::Px(550.0),
-                        height: Val::Px(190.0),
                         justify_content: JustifyContent::Center,
                         align_content: AlignContent::Center,
                         flex_direction: FlexDirection::Column,
@@ -42,66 +59,87 @@ pub fn setup_failure_help(mut commands: Commands, font_assets: Res<FontAssets>)
                     ..Default::default()
                 })
                 .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Out of stamina!",
-                        TextStyle {
-                            font: font_assets.fira_sans.clone(),
-                            font_size: 45.0,
-                            color: Color::WHITE,
-                        },
+                    parent.spawn(
+                        localization.text(Localized::new("failure.title"), text_style(45.0)),
+                    );
+                    parent
+                        .spawn(
+                            TextBundle::from_section("", text_style(25.0)).with_style(Style {
+                                margin: UiRect::vertical(Val::Px(10.0)),
+                                ..Default::default()
+                            }),
+                        )
+                        .insert(DeadEndText);
+                    parent.spawn(localization.text(
+                        Localized::with_args("failure.undo", &[&controls.label(Control::Undo)]),
+                        text_style(45.0),
                     ));
-                    parent.spawn(TextBundle::from_section(
-                        "Press ctrl-z to undo last move",
-                        TextStyle {
-                            font: font_assets.fira_sans.clone(),
-                            font_size: 45.0,
-                            color: Color::WHITE,
-                        },
-                    ));
-                    parent.spawn(TextBundle::from_section(
-                        "or press 'r' to restart level.",
-                        TextStyle {
-                            font: font_assets.fira_sans.clone(),
-                            font_size: 45.0,
-                            color: Color::WHITE,
-                        },
+                    parent.spawn(localization.text(
+                        Localized::with_args(
+                            "failure.restart",
+                            &[&controls.label(Control::Restart)],
+                        ),
+                        text_style(45.0),
                     ));
                 });
         });
 }
 
-pub fn check_if_no_valid_moves(
+/// The explanation of why the level can't be finished any more
+#[derive(Component)]
+pub struct DeadEndText;
+
+/// Whether the level can still be finished from where things stand. Finding out means searching
+/// everything that could still be done, so it happens off the main thread.
+#[derive(Default, Resource)]
+pub struct DeadEndCheck {
+    /// The state being checked, so that a check started before the last move is ignored
+    state: Option<PuzzleState>,
+    verdict: Arc<Mutex<Option<DeadEnd>>>,
+}
+
+/// Start checking the position after every move
+pub fn check_for_dead_end(puzzle: Res<Puzzle>, mut check: ResMut<DeadEndCheck>) {
+    if check.state.as_ref() == Some(&puzzle.state) {
+        return;
+    }
+    check.state = Some(puzzle.state.clone());
+    check.verdict = Arc::default();
+    // the equipment is still being picked before the first move
+    if !puzzle.can_undo() || puzzle.is_complete() {
+        return;
+    }
+    let verdict = check.verdict.clone();
+    let map = puzzle.map.clone();
+    let state = puzzle.state.clone();
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            *verdict.lock().unwrap() = dead_end(&map, &state);
+        })
+        .detach();
+}
+
+pub fn show_failure_help(
     mut root: Query<&mut Visibility, With<FailureUIRoot>>,
-    stamina_costs: Res<StaminaCosts>,
-    player: Query<&Player>,
+    mut text: Query<&mut Text, With<DeadEndText>>,
+    check: Res<DeadEndCheck>,
     transition_manager: Res<TransitionManager>,
+    localization: Res<Localization>,
 ) {
-    if let Ok(player) = player.get_single() {
-        if let Ok(mut visibility) = root.get_single_mut() {
-            let mut is_valid_move = false;
-            if player.stamina != 0 {
-                if let Some(val) = stamina_costs.north {
-                    is_valid_move = val as u16 <= player.stamina;
-                }
-                if let Some(val) = stamina_costs.east {
-                    is_valid_move = val as u16 <= player.stamina;
-                }
-                if let Some(val) = stamina_costs.south {
-                    is_valid_move = val as u16 <= player.stamina;
-                }
-                if let Some(val) = stamina_costs.west {
-                    is_valid_move = val as u16 <= player.stamina;
-                }
-            }
-            if player.stamina >= 4 {
-                is_valid_move = true;
-            }
-
-            if is_valid_move || !matches!(*transition_manager, TransitionManager::Normal) {
-                *visibility = Visibility::Hidden;
-            } else {
-                *visibility = Visibility::Visible;
+    let verdict = *check.verdict.lock().unwrap();
+    let (Ok(mut visibility), Ok(mut text)) = (root.get_single_mut(), text.get_single_mut()) else {
+        return;
+    };
+    match verdict {
+        Some(dead_end) if matches!(*transition_manager, TransitionManager::Normal) => {
+            *visibility = Visibility::Visible;
+            let explanation = localization.get(dead_end.explanation_key());
+            if text.sections[0].value != explanation {
+                text.sections[0].value = explanation;
             }
         }
+        _ => {
+            *visibility = Visibility::Hidden;
+        }
     }
 }
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Control, ControlInput},
+    states::{GameState, PauseState},
+};
+
+/// The button picked out with the gamepad, if any. It is shown as hovered, and pressing A
+/// presses it, so the screens' own button systems don't need to know about gamepads.
+#[derive(Debug, Default, Resource)]
+pub struct Focus(pub Option<Entity>);
+
+/// A panel shown over the rest of the screen. While one is up, only its buttons can take the focus.
+#[derive(Component)]
+pub struct Modal;
+
+/// Run condition for input that shouldn't happen while the gamepad is on the buttons
+pub fn no_focus(focus: Res<Focus>) -> bool {
+    focus.0.is_none()
+}
+
+/// Which way each movement control points on screen, where y goes down
+const DIRECTIONS: [(Control, Vec2); 4] = [
+    (Control::MoveNorth, Vec2::NEG_Y),
+    (Control::MoveEast, Vec2::X),
+    (Control::MoveSouth, Vec2::Y),
+    (Control::MoveWest, Vec2::NEG_X),
+];
+
+/// The top left button, to start from
+fn first_focus(candidates: impl IntoIterator<Item = (Entity, Vec2)>) -> Option<Entity> {
+    candidates
+        .into_iter()
+        .min_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)))
+        .map(|(entity, _)| entity)
+}
+
+/// The closest button in the given direction, preferring ones in line over ones off to the side
+fn next_focus(
+    from: Vec2,
+    direction: Vec2,
+    candidates: impl IntoIterator<Item = (Entity, Vec2)>,
+) -> Option<Entity> {
+    candidates
+        .into_iter()
+        .filter_map(|(entity, position)| {
+            let offset = position - from;
+            let along = offset.dot(direction);
+            // only buttons ahead count, not ones level with this one
+            (along > 1.0).then(|| (entity, along + 2.0 * offset.perp_dot(direction).abs()))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity)
+}
+
+/// Move the focus around the visible buttons with the d-pad or stick, and press the focused one
+/// with A. In a level the d-pad moves the climber and Start pauses, so the buttons there take the
+/// focus on B and give it back on B again, unless the level is paused.
+pub fn gamepad_focus(
+    input: ControlInput,
+    state: Res<State<GameState>>,
+    pause_state: Res<State<PauseState>>,
+    mut focus: ResMut<Focus>,
+    mut buttons: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &ComputedVisibility,
+            &mut Interaction,
+        ),
+        With<Button>,
+    >,
+    modals: Query<(), With<Modal>>,
+    parents: Query<&Parent>,
+    mut pressed: Local<Option<Entity>>,
+) {
+    // let go of the button pressed on the last frame
+    if let Some(entity) = pressed.take() {
+        if let Ok((_, _, _, mut interaction)) = buttons.get_mut(entity) {
+            if *interaction == Interaction::Pressed {
+                *interaction = Interaction::Hovered;
+            }
+        }
+    }
+
+    let candidates: Vec<(Entity, Vec2)> = buttons
+        .iter()
+        .filter(|(_, _, visibility, _)| visibility.is_visible_in_hierarchy())
+        .filter(|(entity, _, _, _)| {
+            modals.is_empty()
+                || parents
+                    .iter_ancestors(*entity)
+                    .any(|ancestor| modals.contains(ancestor))
+        })
+        .map(|(entity, transform, _, _)| (entity, transform.translation().truncate()))
+        .collect();
+    let position = |entity| {
+        candidates
+            .iter()
+            .find(|(candidate, _)| *candidate == entity)
+            .map(|&(_, position)| position)
+    };
+    // the focused button may have been despawned or hidden since
+    let previous = focus.0.filter(|&entity| position(entity).is_some());
+
+    let direction = DIRECTIONS
+        .into_iter()
+        .find(|&(control, _)| input.gamepad_just_pressed(control))
+        .map(|(_, direction)| direction);
+    let moved = match (previous, direction) {
+        (Some(entity), Some(direction)) => {
+            let from = position(entity).unwrap();
+            next_focus(from, direction, candidates.iter().copied()).or(previous)
+        }
+        _ => previous,
+    };
+    focus.0 = if *state.get() == GameState::Level && *pause_state.get() == PauseState::Playing {
+        if !input.button_just_pressed(&[GamepadButtonType::East]) {
+            moved
+        } else if previous.is_none() {
+            first_focus(candidates.iter().copied())
+        } else {
+            None
+        }
+    } else if previous.is_none() && direction.is_some() {
+        first_focus(candidates.iter().copied())
+    } else {
+        moved
+    };
+
+    if previous != focus.0 {
+        if let Some((_, _, _, mut interaction)) =
+            previous.and_then(|entity| buttons.get_mut(entity).ok())
+        {
+            if *interaction == Interaction::Hovered {
+                *interaction = Interaction::None;
+            }
+        }
+    }
+    let Some((entity, _, _, mut interaction)) =
+        focus.0.and_then(|entity| buttons.get_mut(entity).ok())
+    else {
+        return;
+    };
+    if input.button_just_pressed(&[GamepadButtonType::South]) {
+        *interaction = Interaction::Pressed;
+        *pressed = Some(entity);
+    } else if *interaction == Interaction::None {
+        // the UI's own focus system clears this every frame the cursor isn't over the button
+        *interaction = Interaction::Hovered;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> Vec<(Entity, Vec2)> {
+        // two rows of three, like the equipment cards' buttons
+        (0..6)
+            .map(|index| {
+                let position = Vec2::new((index % 3) as f32 * 100.0, (index / 3) as f32 * 60.0);
+                (Entity::from_raw(index), position)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn focus_starts_in_the_top_left() {
+        let mut buttons = grid();
+        buttons.reverse();
+        assert_eq!(first_focus(buttons), Some(Entity::from_raw(0)));
+        assert_eq!(first_focus([]), None);
+    }
+
+    #[test]
+    fn focus_moves_to_the_nearest_button_that_way() {
+        let from = Vec2::new(100.0, 0.0);
+        assert_eq!(next_focus(from, Vec2::X, grid()), Some(Entity::from_raw(2)));
+        assert_eq!(
+            next_focus(from, Vec2::NEG_X, grid()),
+            Some(Entity::from_raw(0))
+        );
+        assert_eq!(next_focus(from, Vec2::Y, grid()), Some(Entity::from_raw(4)));
+        assert_eq!(next_focus(from, Vec2::NEG_Y, grid()), None);
+
+        // off to the side when nothing is straight ahead
+        let from = Vec2::new(250.0, 0.0);
+        assert_eq!(next_focus(from, Vec2::Y, grid()), Some(Entity::from_raw(5)));
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
+
+use crate::{
+    controls::{Control, ControlInput, Controls},
+    localization::Localization,
+    rules::{Action, Puzzle, PuzzleState},
+    solver::{hint, Hint},
+    states::{level::DespawnOnTransition, loading::FontAssets},
+};
+
+use super::{
+    constants::{GREY, SKY_BLUE, UI_YELLOW},
+    keys::KeyCap,
+    UiRoot,
+};
+
+#[derive(Component)]
+pub struct HintUiRoot;
+
+#[derive(Component)]
+pub struct HintText;
+
+/// The hint asked for, which is worked out off the main thread and only shown until the next
+/// move
+#[derive(Default, Resource)]
+pub struct HintRequest {
+    /// The state the hint is for
+    state: Option<PuzzleState>,
+    hint: Arc<Mutex<Option<Hint>>>,
+}
+
+pub fn setup_hint_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
+    commands.insert_resource(HintRequest::default());
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        })
+        .insert(DespawnOnTransition)
+        .insert(HintUiRoot)
+        .insert(UiRoot)
+        .insert(Name::new("Hint UI"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    background_color: UI_YELLOW.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 25.0,
+                                color: Color::WHITE,
+                            },
+                        ))
+                        .insert(HintText);
+                });
+        });
+}
+
+/// Start working out a hint for where things stand
+pub fn request_hint(input: ControlInput, puzzle: Res<Puzzle>, mut request: ResMut<HintRequest>) {
+    if !input.just_pressed(Control::Hint) || request.state.as_ref() == Some(&puzzle.state) {
+        return;
+    }
+    request.state = Some(puzzle.state.clone());
+    request.hint = Arc::default();
+    let result = request.hint.clone();
+    let map = puzzle.map.clone();
+    let state = puzzle.state.clone();
+    let earlier: Vec<PuzzleState> = puzzle.earlier_states().cloned().collect();
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            *result.lock().unwrap() = Some(hint(&map, &state, &earlier));
+        })
+        .detach();
+}
+
+/// The control that takes an action
+fn control_for(action: Action) -> Control {
+    match action {
+        Action::Move(direction) => Control::movement(direction),
+        Action::UseLadder => Control::Ladder,
+        Action::PlaceRope => Control::Rope,
+        Action::PlaceRune => Control::Rune,
+        Action::DrinkPotion => Control::Potion,
+        Action::Undo => Control::Undo,
+        Action::Redo => Control::Redo,
+    }
+}
+
+/// Show the hint for the current state, if one was asked for, and pick out the key to press
+pub fn show_hint(
+    mut root: Query<&mut Visibility, With<HintUiRoot>>,
+    mut text: Query<&mut Text, With<HintText>>,
+    mut key_caps: Query<(&KeyCap, &mut BackgroundColor)>,
+    puzzle: Res<Puzzle>,
+    request: Res<HintRequest>,
+    controls: Res<Controls>,
+    localization: Res<Localization>,
+) {
+    let (Ok(mut visibility), Ok(mut text)) = (root.get_single_mut(), text.get_single_mut()) else {
+        return;
+    };
+    // the hint goes as soon as the puzzle moves on
+    let asked = request.state.as_ref() == Some(&puzzle.state);
+    let key = |control| controls.label(control);
+    let (message, picked) = match *request.hint.lock().unwrap() {
+        _ if !asked => (None, None),
+        None => (Some(localization.get("hint.thinking")), None),
+        Some(Hint::Next(action)) => {
+            let control = control_for(action);
+            let name = localization.get(control.name_key());
+            (
+                Some(localization.format("hint.next", &[&name, &key(control)])),
+                Some(control),
+            )
+        }
+        Some(Hint::Undo(undos)) => {
+            let message = if undos == 1 {
+                "hint.undo_one"
+            } else {
+                "hint.undo_many"
+            };
+            (
+                Some(localization.format(message, &[&undos, &key(Control::Undo)])),
+                Some(Control::Undo),
+            )
+        }
+        Some(Hint::Restart) => (
+            Some(localization.format("hint.restart", &[&key(Control::Restart)])),
+            Some(Control::Restart),
+        ),
+        Some(Hint::Unknown) => (Some(localization.get("hint.unknown")), None),
+    };
+
+    match message {
+        Some(message) => {
+            *visibility = Visibility::Visible;
+            if text.sections[0].value != message {
+                text.sections[0].value = message;
+            }
+        }
+        None => {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    for (key_cap, mut color) in key_caps.iter_mut() {
+        let wanted = if picked == Some(key_cap.0) {
+            SKY_BLUE
+        } else {
+            GREY
+        };
+        if color.0 != wanted {
+            color.0 = wanted;
+        }
+    }
+}
@@ -1,11 +1,9 @@
 use bevy::prelude::*;
 
 use crate::{
-    cave::HasGem,
-    level_manager::LevelManager,
-    player::{Player, PlayerState},
+    controls::{Control, Controls, KeyBindings},
+    rules::{CardinalDirection, Puzzle},
     states::{level::DespawnOnTransition, loading::FontAssets},
-    util::CardinalDirection,
 };
 
 use super::{constants::*, UiRoot};
@@ -21,7 +19,11 @@ pub struct StaminaCosts {
 #[derive(Component)]
 pub struct StaminaValue(CardinalDirection);
 
-pub fn setup_keys_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
+pub fn setup_keys_ui(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    controls: Res<Controls>,
+) {
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -99,7 +101,12 @@ pub fn setup_keys_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                             ..Default::default()
                         })
                         .with_children(|parent| {
-                            draw_key(parent, font_assets.fira_sans.clone(), 'W');
+                            draw_key(
+                                parent,
+                                font_assets.fira_sans.clone(),
+                                &controls,
+                                Control::MoveNorth,
+                            );
                         });
                     // left value
                     parent
@@ -127,7 +134,12 @@ pub fn setup_keys_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                             ..Default::default()
                         })
                         .with_children(|parent| {
-                            draw_key(parent, font_assets.fira_sans.clone(), 'A');
+                            draw_key(
+                                parent,
+                                font_assets.fira_sans.clone(),
+                                &controls,
+                                Control::MoveWest,
+                            );
                         });
                     // down key
                     parent
@@ -140,7 +152,12 @@ pub fn setup_keys_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                             ..Default::default()
                         })
                         .with_children(|parent| {
-                            draw_key(parent, font_assets.fira_sans.clone(), 'S');
+                            draw_key(
+                                parent,
+                                font_assets.fira_sans.clone(),
+                                &controls,
+                                Control::MoveSouth,
+                            );
                         });
                     // right key
                     parent
@@ -154,7 +171,12 @@ pub fn setup_keys_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                             ..Default::default()
                         })
                         .with_children(|parent| {
-                            draw_key(parent, font_assets.fira_sans.clone(), 'D');
+                            draw_key(
+                                parent,
+                                font_assets.fira_sans.clone(),
+                                &controls,
+                                Control::MoveEast,
+                            );
                         });
                     // right value
                     parent
@@ -194,12 +216,24 @@ pub fn setup_keys_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
         });
 }
 
-pub fn draw_key(parent: &mut ChildBuilder, font: Handle<Font>, key: char) {
+/// Marks a key cap with the control it is for, so that it can be picked out
+#[derive(Component)]
+pub struct KeyCap(pub Control);
+
+/// A key cap with the name of the key bound to a control on it, which widens to fit longer names
+/// like `Ctrl+Z`
+pub fn draw_key(
+    parent: &mut ChildBuilder,
+    font: Handle<Font>,
+    bindings: &KeyBindings,
+    control: Control,
+) {
     parent
         .spawn(NodeBundle {
             style: Style {
-                width: Val::Px(20.0),
+                min_width: Val::Px(20.0),
                 height: Val::Px(20.0),
+                padding: UiRect::horizontal(Val::Px(3.0)),
                 border: UiRect::bottom(Val::Px(2.0)),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
@@ -209,9 +243,10 @@ pub fn draw_key(parent: &mut ChildBuilder, font: Handle<Font>, key: char) {
             background_color: GREY.into(),
             ..Default::default()
         })
+        .insert(KeyCap(control))
         .with_children(|parent| {
             parent.spawn(TextBundle::from_section(
-                key,
+                bindings.label(control),
                 TextStyle {
                     font: font.clone(),
                     font_size: 15.0,
@@ -221,47 +256,17 @@ pub fn draw_key(parent: &mut ChildBuilder, font: Handle<Font>, key: char) {
         });
 }
 
-pub fn update_stamina_costs(
-    mut player: Query<&mut Player, Changed<Player>>,
-    level_manager: Res<LevelManager>,
-    mut stamina_costs: ResMut<StaminaCosts>,
-    has_gem: Res<HasGem>,
-) {
-    if let Ok(mut player) = player.get_single_mut() {
-        let map = &level_manager.get_current_level().map;
-        if let PlayerState::Standing(player_direction) = player.state {
-            player.state = PlayerState::Standing(CardinalDirection::North);
-            stamina_costs.north = player
-                .go(CardinalDirection::North, map, has_gem.0)
-                .map(|p| (player.stamina - p.stamina) as u8);
-            player.state = PlayerState::Standing(CardinalDirection::East);
-            stamina_costs.east = player
-                .go(CardinalDirection::East, map, has_gem.0)
-                .map(|p| (player.stamina - p.stamina) as u8);
-            player.state = PlayerState::Standing(CardinalDirection::South);
-            stamina_costs.south = player
-                .go(CardinalDirection::South, map, has_gem.0)
-                .map(|p| (player.stamina - p.stamina) as u8);
-            player.state = PlayerState::Standing(CardinalDirection::West);
-            stamina_costs.west = player
-                .go(CardinalDirection::West, map, has_gem.0)
-                .map(|p| (player.stamina - p.stamina) as u8);
-            player.state = PlayerState::Standing(player_direction);
-        } else {
-            stamina_costs.north = player
-                .go(CardinalDirection::North, map, has_gem.0)
-                .map(|p| (player.stamina - p.stamina) as u8);
-            stamina_costs.east = player
-                .go(CardinalDirection::East, map, has_gem.0)
-                .map(|p| (player.stamina - p.stamina) as u8);
-            stamina_costs.south = player
-                .go(CardinalDirection::South, map, has_gem.0)
-                .map(|p| (player.stamina - p.stamina) as u8);
-            stamina_costs.west = player
-                .go(CardinalDirection::West, map, has_gem.0)
-                .map(|p| (player.stamina - p.stamina) as u8);
-        }
-    }
+pub fn update_stamina_costs(puzzle: Res<Puzzle>, mut stamina_costs: ResMut<StaminaCosts>) {
+    let cost = |direction| {
+        puzzle
+            .state
+            .move_cost(&puzzle.map, direction)
+            .map(|cost| cost as u8)
+    };
+    stamina_costs.north = cost(CardinalDirection::North);
+    stamina_costs.east = cost(CardinalDirection::East);
+    stamina_costs.south = cost(CardinalDirection::South);
+    stamina_costs.west = cost(CardinalDirection::West);
 }
 
 pub fn update_stamina_values(
@@ -1,33 +1,52 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, ui::UiSystem};
 
 use crate::{
-    equipment::Inventory,
+    controls::read_sticks,
+    localization::Localization,
+    replay::Playback,
+    rules::Puzzle,
     scale::ScaleCounter,
+    settings::Settings,
     states::{
         transition::{hide_ui_on_transition, update_transition_manager, TransitionManager},
-        GameState,
+        GameState, PauseState,
     },
+    tutorial::Tutorial,
 };
 
 use self::{
     end::setup_end_screen,
     equipment::{
         draw_equimpment_cards, draw_inventory_icons, handle_add_buttons, handle_subtract_buttons,
-        update_inventory_counters, update_weight_text,
+        hide_picking_ui, update_inventory_counters, update_weight_text,
     },
-    failure::{check_if_no_valid_moves, setup_failure_help},
+    failure::{check_for_dead_end, setup_failure_help, show_failure_help, DeadEndCheck},
+    focus::{gamepad_focus, no_focus, Focus},
+    hint::{request_hint, setup_hint_ui, show_hint, HintRequest},
     keys::{setup_keys_ui, update_stamina_costs, update_stamina_values},
+    reach::{draw_reach_overlay, toggle_reach_overlay, ReachOverlay},
+    results::{clear_results, continue_from_results, hide_results, show_results, LevelResults},
     scale::{setup_scale_count_ui, update_scale_count_ui},
+    settings::{drag_volume_sliders, settings_buttons, update_settings_ui},
     stamina::{setup_stamina_ui, update_stamina_ui},
+    touch::{press_control_buttons, setup_control_buttons},
+    tutorial::{dismiss_tutorial_prompt, setup_tutorial_ui, show_tutorial_prompt},
 };
 
 pub mod constants;
 pub mod end;
 pub mod equipment;
 pub mod failure;
+pub mod focus;
+pub mod hint;
 pub mod keys;
+pub mod reach;
+pub mod results;
 pub mod scale;
+pub mod settings;
 pub mod stamina;
+pub mod touch;
+pub mod tutorial;
 
 #[derive(Component)]
 pub struct UiRoot;
@@ -45,12 +64,19 @@ impl Plugin for UiPlugin {
                 setup_scale_count_ui,
                 draw_equimpment_cards,
                 draw_inventory_icons,
+                setup_control_buttons,
+                setup_hint_ui,
+                setup_tutorial_ui,
             ),
         )
         .add_systems(
             Update,
-            update_transition_manager
-                .run_if(in_state(GameState::MainMenu).or_else(in_state(GameState::Level))),
+            update_transition_manager.run_if(
+                in_state(GameState::MainMenu)
+                    .or_else(in_state(GameState::LevelSelect))
+                    .or_else(in_state(GameState::Level))
+                    .or_else(in_state(GameState::Editor)),
+            ),
         )
         .add_systems(
             Update,
@@ -61,18 +87,100 @@ impl Plugin for UiPlugin {
             (
                 update_stamina_ui,
                 (
-                    update_stamina_costs,
-                    (update_stamina_values, check_if_no_valid_moves),
+                    update_stamina_costs.run_if(resource_changed::<Puzzle>()),
+                    update_stamina_values,
                 )
                     .chain(),
-                handle_add_buttons,
-                handle_subtract_buttons,
-                update_inventory_counters.run_if(resource_changed::<Inventory>()),
-                update_weight_text.run_if(resource_changed::<Inventory>()),
+                (
+                    check_for_dead_end.run_if(resource_changed::<Puzzle>()),
+                    show_failure_help,
+                )
+                    .chain(),
+                (
+                    request_hint
+                        .run_if(no_focus)
+                        .run_if(in_state(PauseState::Playing)),
+                    show_hint,
+                )
+                    .chain(),
+                (handle_add_buttons, handle_subtract_buttons)
+                    .run_if(not(resource_exists::<Playback>()))
+                    .run_if(in_state(PauseState::Playing)),
+                hide_picking_ui.run_if(resource_changed::<Puzzle>()),
+                update_inventory_counters.run_if(resource_changed::<Puzzle>()),
+                update_weight_text.run_if(resource_changed::<Puzzle>()),
                 update_scale_count_ui.run_if(resource_changed::<ScaleCounter>()),
             )
                 .run_if(in_state(GameState::Level)),
         )
-        .add_systems(OnEnter(GameState::End), setup_end_screen);
+        .init_resource::<DeadEndCheck>()
+        .init_resource::<HintRequest>()
+        .init_resource::<ReachOverlay>()
+        .add_systems(
+            Update,
+            (
+                toggle_reach_overlay
+                    .run_if(no_focus)
+                    .run_if(in_state(PauseState::Playing)),
+                draw_reach_overlay.run_if(
+                    resource_changed::<Puzzle>().or_else(resource_changed::<ReachOverlay>()),
+                ),
+            )
+                .chain()
+                .run_if(in_state(GameState::Level)),
+        )
+        .add_systems(
+            Update,
+            (
+                hide_results.run_if(resource_changed::<Puzzle>()),
+                show_results.run_if(resource_added::<LevelResults>()),
+                continue_from_results
+                    .run_if(resource_exists::<LevelResults>())
+                    .run_if(no_focus)
+                    .run_if(in_state(PauseState::Playing)),
+            )
+                .run_if(in_state(GameState::Level)),
+        )
+        .add_systems(
+            Update,
+            (
+                dismiss_tutorial_prompt
+                    .run_if(resource_exists::<Tutorial>())
+                    .run_if(no_focus)
+                    .run_if(in_state(PauseState::Playing)),
+                show_tutorial_prompt.run_if(
+                    resource_exists_and_changed::<Tutorial>()
+                        .or_else(resource_changed::<Localization>()),
+                ),
+            )
+                .chain()
+                .run_if(in_state(GameState::Level)),
+        )
+        .add_systems(OnExit(GameState::Level), clear_results)
+        .add_systems(OnEnter(GameState::End), setup_end_screen)
+        // the settings can be changed from more than one screen
+        .add_systems(
+            Update,
+            (
+                (settings_buttons, drag_volume_sliders),
+                update_settings_ui.run_if(
+                    resource_changed::<Settings>().or_else(resource_changed::<Localization>()),
+                ),
+            )
+                .chain(),
+        )
+        .init_resource::<Focus>()
+        .add_systems(
+            PreUpdate,
+            gamepad_focus.after(UiSystem::Focus).after(read_sticks),
+        )
+        .add_systems(
+            PreUpdate,
+            press_control_buttons
+                .after(gamepad_focus)
+                .run_if(in_state(GameState::Level))
+                .run_if(in_state(PauseState::Playing))
+                .run_if(not(resource_exists::<Playback>())),
+        );
     }
 }
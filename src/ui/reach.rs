@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Control, ControlInput},
+    rules::Puzzle,
+    solver::{reachable, Spot},
+    states::level::DespawnOnTransition,
+};
+
+/// Whether every spot the climber can still get to is tinted by what it costs to get there
+#[derive(Debug, Default, Resource)]
+pub struct ReachOverlay {
+    pub shown: bool,
+}
+
+/// A tinted square or wall face of the reach overlay
+#[derive(Component)]
+pub struct ReachTile;
+
+/// Tint of a spot that costs nothing more to get to
+const CHEAP: Color = Color::rgba(0.2, 0.8, 0.3, 0.6);
+/// Tint of a spot that takes all the stamina left
+const DEAR: Color = Color::rgba(0.9, 0.2, 0.1, 0.6);
+
+pub fn toggle_reach_overlay(input: ControlInput, mut overlay: ResMut<ReachOverlay>) {
+    if input.just_pressed(Control::Reach) {
+        overlay.shown = !overlay.shown;
+    }
+}
+
+/// Redraws the overlay from scratch, as any change to the puzzle can change every cost
+pub fn draw_reach_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    tiles: Query<Entity, With<ReachTile>>,
+    overlay: Res<ReachOverlay>,
+    puzzle: Res<Puzzle>,
+) {
+    for tile in tiles.iter() {
+        commands.entity(tile).despawn_recursive();
+    }
+    if !overlay.shown {
+        return;
+    }
+
+    let stamina = puzzle.state.player.stamina.max(1) as f32;
+    let mesh = meshes.add(shape::Plane::from_size(0.9).into());
+    let mut tints = HashMap::new();
+    let heights = &puzzle.map.grid_heights;
+    for (spot, cost) in reachable(&puzzle.map, &puzzle.state) {
+        let transform = match spot {
+            Spot::Square(x, y) => Transform::from_xyz(
+                x as f32,
+                heights[y as usize][x as usize] as f32 + 0.01,
+                y as f32,
+            ),
+            Spot::Face {
+                x,
+                y,
+                direction,
+                elevation,
+            } => {
+                // just off the wall, facing the climber
+                let towards: Vec3 = direction.into();
+                Transform::from_translation(
+                    Vec3::new(x as f32, elevation as f32 - 0.5, y as f32) + towards * 0.49,
+                )
+                .with_rotation(Quat::from_rotation_arc(Vec3::Y, -towards))
+            }
+        };
+        let material = tints
+            .entry(cost)
+            .or_insert_with(|| {
+                let t = (cost as f32 / stamina).min(1.0);
+                materials.add(StandardMaterial {
+                    base_color: lerp(CHEAP, DEAR, t),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..Default::default()
+                })
+            })
+            .clone();
+        commands
+            .spawn(PbrBundle {
+                mesh: mesh.clone(),
+                material,
+                transform,
+                ..Default::default()
+            })
+            .insert(ReachTile)
+            .insert(DespawnOnTransition)
+            .insert(Name::new("Reach tile"));
+    }
+}
+
+fn lerp(from: Color, to: Color, t: f32) -> Color {
+    let from = Vec4::from(from.as_rgba_f32());
+    let to = Vec4::from(to.as_rgba_f32());
+    Color::from(from.lerp(to, t))
+}
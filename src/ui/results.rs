@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    controls::{Control, ControlInput, Controls},
+    localization::{Localization, Localized},
+    rating::{Finish, Par, MOST_STARS},
+    rules::Puzzle,
+    save::LevelRecord,
+    states::{level::DespawnOnTransition, loading::FontAssets, transition::TransitionManager},
+};
+
+use super::{
+    constants::{SKY_BLUE, UI_YELLOW},
+    touch::ControlButton,
+    UiRoot,
+};
+
+/// How the level just finished went. Shown until the player carries on, or undoes the last move
+/// to try for more stars.
+#[derive(Debug, Resource)]
+pub struct LevelResults {
+    pub finish: Finish,
+    pub stars: u8,
+    pub par: Option<Par>,
+    /// The level's record from before this finish, if it is a campaign level finished before
+    pub previous: Option<LevelRecord>,
+}
+
+#[derive(Component)]
+pub struct ResultsUiRoot;
+
+pub fn show_results(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    results: Res<LevelResults>,
+    controls: Res<Controls>,
+    localization: Res<Localization>,
+) {
+    let text_style = |font_size: f32| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    let finish = &results.finish;
+    // each line has its own text for when there's a par to show
+    let against_par = |keys: (&str, &str), value: u16, par: Option<u16>| match par {
+        Some(par) => Localized::with_args(keys.1, &[&value, &par]),
+        None => Localized::with_args(keys.0, &[&value]),
+    };
+    let mut lines = vec![
+        Localized::with_args("common.stars", &[&results.stars, &MOST_STARS]),
+        against_par(
+            ("results.moves", "results.moves_par"),
+            finish.moves,
+            results.par.map(|par| par.moves),
+        ),
+        against_par(
+            ("results.stamina", "results.stamina_par"),
+            finish.stamina_left,
+            results.par.map(|par| par.stamina_left),
+        ),
+    ];
+    if let Some(previous) = &results.previous {
+        let fewer_moves = previous.best_moves.is_none_or(|best| finish.moves < best);
+        let more_stamina = previous
+            .best_stamina
+            .is_none_or(|best| finish.stamina_left > best);
+        lines.push(if fewer_moves || more_stamina {
+            Localized::new("results.new_best")
+        } else {
+            Localized::with_args(
+                "results.best",
+                &[
+                    &previous.best_moves.unwrap_or(finish.moves),
+                    &previous.best_stamina.unwrap_or(finish.stamina_left),
+                ],
+            )
+        });
+    }
+    if results.stars < MOST_STARS {
+        lines.push(Localized::with_args(
+            "results.undo",
+            &[&controls.label(Control::Undo)],
+        ));
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(DespawnOnTransition)
+        .insert(ResultsUiRoot)
+        .insert(UiRoot)
+        .insert(Name::new("Results UI"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(450.0),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        row_gap: Val::Px(6.0),
+                        ..Default::default()
+                    },
+                    background_color: SKY_BLUE.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        localization.text(Localized::new("results.title"), text_style(45.0)),
+                    );
+                    for line in lines {
+                        parent.spawn(localization.text(line, text_style(25.0)));
+                    }
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(20.0), Val::Px(8.0)),
+                                margin: UiRect::top(Val::Px(10.0)),
+                                ..Default::default()
+                            },
+                            background_color: UI_YELLOW.into(),
+                            ..Default::default()
+                        })
+                        .insert(ControlButton(Control::Continue))
+                        .with_children(|parent| {
+                            parent.spawn(localization.text(
+                                Localized::with_args(
+                                    "results.continue",
+                                    &[&controls.label(Control::Continue)],
+                                ),
+                                text_style(30.0),
+                            ));
+                        });
+                });
+        });
+}
+
+/// Undoing the last move takes the climber off the flag, and the results with it
+pub fn hide_results(
+    mut commands: Commands,
+    root: Query<Entity, With<ResultsUiRoot>>,
+    puzzle: Res<Puzzle>,
+) {
+    if puzzle.is_complete() {
+        return;
+    }
+    commands.remove_resource::<LevelResults>();
+    for entity in root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn continue_from_results(
+    input: ControlInput,
+    mut transition_manager: ResMut<TransitionManager>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    if input.just_pressed(Control::Continue)
+        && matches!(*transition_manager, TransitionManager::Normal)
+    {
+        sound_channel.play(audio_assets.woosh.clone());
+        *transition_manager = TransitionManager::TransitioningOut(0.0);
+    }
+}
+
+pub fn clear_results(mut commands: Commands) {
+    commands.remove_resource::<LevelResults>();
+}
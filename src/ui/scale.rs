@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
 use crate::{
+    localization::{Localization, Localized},
     scale::ScaleCounter,
     states::{level::DespawnOnTransition, loading::FontAssets},
 };
@@ -13,6 +14,7 @@ pub struct ScaleText;
 pub fn setup_scale_count_ui(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
+    localization: Res<Localization>,
     scale_counter: Res<ScaleCounter>,
 ) {
     commands
@@ -40,10 +42,11 @@ pub fn setup_scale_count_ui(
             };
             parent
                 .spawn(TextBundle::from_sections([
-                    TextSection::new("Scales collected: ", style.clone()),
+                    TextSection::new(localization.get("hud.scales"), style.clone()),
                     TextSection::new(scale_counter.0.to_string(), style),
                 ]))
-                .insert(ScaleText);
+                .insert(ScaleText)
+                .insert(Localized::new("hud.scales"));
         });
 }
 
@@ -0,0 +1,300 @@
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    localization::{Localization, Localized},
+    settings::{Settings, SettingsData, Volume},
+};
+
+use super::constants::{DARK_GREY, UI_YELLOW, UI_YELLOW_HOVER};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Volume(Volume),
+    Choice(Choice),
+}
+
+/// A setting that is changed by going on to its next value, rather than with a slider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    Fullscreen,
+    Msaa,
+    Shadows,
+    Language,
+}
+
+impl Setting {
+    const ALL: [Setting; 6] = [
+        Setting::Volume(Volume::Music),
+        Setting::Volume(Volume::Sound),
+        Setting::Choice(Choice::Fullscreen),
+        Setting::Choice(Choice::Msaa),
+        Setting::Choice(Choice::Shadows),
+        Setting::Choice(Choice::Language),
+    ];
+
+    fn name_key(&self) -> &'static str {
+        match self {
+            Setting::Volume(Volume::Music) => "settings.music",
+            Setting::Volume(Volume::Sound) => "settings.sound",
+            Setting::Choice(Choice::Fullscreen) => "settings.window",
+            Setting::Choice(Choice::Msaa) => "settings.anti_aliasing",
+            Setting::Choice(Choice::Shadows) => "settings.shadows",
+            Setting::Choice(Choice::Language) => "settings.language",
+        }
+    }
+
+    fn value(&self, data: &SettingsData, localization: &Localization) -> String {
+        match self {
+            Setting::Volume(volume) => format!("{}%", (data.volume(*volume) * 100.0).round()),
+            Setting::Choice(Choice::Fullscreen) if data.fullscreen => {
+                localization.get("settings.fullscreen")
+            }
+            Setting::Choice(Choice::Fullscreen) => localization.get("settings.windowed"),
+            Setting::Choice(Choice::Msaa) => match data.msaa().samples() {
+                1 => localization.get("settings.off"),
+                samples => format!("{}x", samples),
+            },
+            Setting::Choice(Choice::Shadows) if data.shadows => localization.get("settings.on"),
+            Setting::Choice(Choice::Shadows) => localization.get("settings.off"),
+            Setting::Choice(Choice::Language) => localization.language_name(&data.language),
+        }
+    }
+}
+
+#[derive(Component)]
+pub enum SettingsButton {
+    /// Turn a volume up or down by this many steps
+    Step(Volume, f64),
+    /// Go on to the next value of a setting that isn't a volume
+    Cycle(Choice),
+}
+
+/// The bar of a volume slider, which sets the volume to wherever along it is clicked or dragged.
+/// It isn't a `Button`, so the gamepad focus skips it for the step buttons either side.
+#[derive(Component)]
+pub struct VolumeSlider(Volume);
+
+/// The filled part of a volume slider
+#[derive(Component)]
+pub struct SliderFill(Volume);
+
+#[derive(Component)]
+pub struct SettingText(Setting);
+
+/// A row for each setting, with its name, a way to change it and what it is now
+pub fn spawn_settings_panel(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    data: &SettingsData,
+    localization: &Localization,
+) {
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+    let cycle_button = || ButtonBundle {
+        style: Style {
+            width: Val::Px(200.0),
+            height: Val::Px(36.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        background_color: UI_YELLOW.into(),
+        ..Default::default()
+    };
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Grid,
+                grid_template_columns: vec![GridTrack::px(150.0), GridTrack::px(330.0)],
+                row_gap: Val::Px(10.0),
+                column_gap: Val::Px(10.0),
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for setting in Setting::ALL {
+                parent.spawn(
+                    localization.text(Localized::new(setting.name_key()), text_style.clone()),
+                );
+                let volume = match setting {
+                    Setting::Volume(volume) => volume,
+                    Setting::Choice(choice) => {
+                        parent
+                            .spawn(cycle_button())
+                            .insert(SettingsButton::Cycle(choice))
+                            .with_children(|parent| {
+                                parent
+                                    .spawn(TextBundle::from_section(
+                                        setting.value(data, localization),
+                                        text_style.clone(),
+                                    ))
+                                    .insert(SettingText(setting));
+                            });
+                        continue;
+                    }
+                };
+                // quieter, the slider, louder and the volume
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(8.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        spawn_step_button(parent, volume, -1.0, &text_style);
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    width: Val::Px(160.0),
+                                    height: Val::Px(20.0),
+                                    ..Default::default()
+                                },
+                                background_color: DARK_GREY.into(),
+                                ..Default::default()
+                            })
+                            .insert(Interaction::default())
+                            .insert(RelativeCursorPosition::default())
+                            .insert(VolumeSlider(volume))
+                            .with_children(|parent| {
+                                parent
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            width: Val::Percent(data.volume(volume) as f32 * 100.0),
+                                            height: Val::Percent(100.0),
+                                            ..Default::default()
+                                        },
+                                        background_color: UI_YELLOW.into(),
+                                        ..Default::default()
+                                    })
+                                    .insert(SliderFill(volume));
+                            });
+                        spawn_step_button(parent, volume, 1.0, &text_style);
+                        parent
+                            .spawn(TextBundle::from_section(
+                                setting.value(data, localization),
+                                text_style.clone(),
+                            ))
+                            .insert(SettingText(setting));
+                    });
+            }
+        });
+}
+
+fn spawn_step_button(parent: &mut ChildBuilder, volume: Volume, steps: f64, style: &TextStyle) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                width: Val::Px(36.0),
+                height: Val::Px(36.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            background_color: UI_YELLOW.into(),
+            ..Default::default()
+        })
+        .insert(SettingsButton::Step(volume, steps))
+        .with_children(|parent| {
+            let text = if steps < 0.0 { "-" } else { "+" };
+            parent.spawn(TextBundle::from_section(text, style.clone()));
+        });
+}
+
+pub fn settings_buttons(
+    mut interaction_query: Query<
+        (&SettingsButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut settings: ResMut<Settings>,
+    localization: Res<Localization>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    for (button, interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                match button {
+                    SettingsButton::Step(volume, steps) => {
+                        settings.data.step_volume(*volume, *steps);
+                    }
+                    SettingsButton::Cycle(Choice::Fullscreen) => {
+                        settings.data.fullscreen = !settings.data.fullscreen;
+                    }
+                    SettingsButton::Cycle(Choice::Msaa) => settings.data.next_msaa(),
+                    SettingsButton::Cycle(Choice::Shadows) => {
+                        settings.data.shadows = !settings.data.shadows;
+                    }
+                    SettingsButton::Cycle(Choice::Language) => {
+                        settings.data.language =
+                            localization.next_language(&settings.data.language);
+                    }
+                }
+                save_settings(&mut settings);
+                sound_channel.play(audio_assets.pop.clone());
+            }
+            Interaction::Hovered => {
+                *color = UI_YELLOW_HOVER.into();
+            }
+            Interaction::None => {
+                *color = UI_YELLOW.into();
+            }
+        }
+    }
+}
+
+/// Follow the pointer while a slider is held, and save once it is let go rather than every frame
+pub fn drag_volume_sliders(
+    sliders: Query<(&VolumeSlider, &Interaction, &RelativeCursorPosition)>,
+    mut settings: ResMut<Settings>,
+    mut dragging: Local<Option<Volume>>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    let held = sliders
+        .iter()
+        .find(|(_, interaction, _)| **interaction == Interaction::Pressed);
+    if let Some((slider, _, cursor)) = held {
+        *dragging = Some(slider.0);
+        if let Some(position) = cursor.normalized {
+            let value = (position.x.clamp(0.0, 1.0) as f64 * 100.0).round() / 100.0;
+            // only touch the settings when the volume moves, so they aren't changed every frame
+            if settings.data.volume(slider.0) != value {
+                settings.data.set_volume(slider.0, value);
+            }
+        }
+    } else if dragging.take().is_some() {
+        save_settings(&mut settings);
+        // a pop at the new volume, to hear how loud the sounds now are
+        sound_channel.play(audio_assets.pop.clone());
+    }
+}
+
+pub fn update_settings_ui(
+    mut texts: Query<(&SettingText, &mut Text)>,
+    mut fills: Query<(&SliderFill, &mut Style)>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+) {
+    for (setting, mut text) in &mut texts {
+        text.sections[0].value = setting.0.value(&settings.data, &localization);
+    }
+    for (fill, mut style) in &mut fills {
+        style.width = Val::Percent(settings.data.volume(fill.0) as f32 * 100.0);
+    }
+}
+
+fn save_settings(settings: &mut Settings) {
+    if let Err(e) = settings.save() {
+        warn!("Couldn't save the settings: {}", e);
+    }
+}
@@ -1,7 +1,8 @@
 use bevy::prelude::*;
 
 use crate::{
-    player::Player,
+    localization::{Localization, Localized},
+    rules::Player,
     states::{level::DespawnOnTransition, loading::FontAssets},
 };
 
@@ -10,7 +11,11 @@ use super::{constants::UI_YELLOW, UiRoot};
 #[derive(Debug, Component)]
 pub struct StaminaText;
 
-pub fn setup_stamina_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
+pub fn setup_stamina_ui(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    localization: Res<Localization>,
+) {
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -37,7 +42,7 @@ pub fn setup_stamina_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
             parent
                 .spawn(
                     TextBundle::from_sections([
-                        TextSection::new("Stamina: ", style.clone()),
+                        TextSection::new(localization.get("hud.stamina"), style.clone()),
                         TextSection::from_style(style),
                     ])
                     .with_style(Style {
@@ -45,7 +50,8 @@ pub fn setup_stamina_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                         ..Default::default()
                     }),
                 )
-                .insert(StaminaText);
+                .insert(StaminaText)
+                .insert(Localized::new("hud.stamina"));
         });
 }
 
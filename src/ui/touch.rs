@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Control, PointerPresses},
+    level_manager::LevelManager,
+    localization::{Localization, Localized},
+    states::{level::DespawnOnTransition, loading::FontAssets},
+};
+
+use super::{
+    constants::{UI_YELLOW, UI_YELLOW_HOVER},
+    UiRoot,
+};
+
+/// An on-screen button that does the same as a key, for playing without a keyboard
+#[derive(Component)]
+pub struct ControlButton(pub Control);
+
+pub fn setup_control_buttons(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    level_manager: Res<LevelManager>,
+    localization: Res<Localization>,
+) {
+    let level = level_manager.get_current_level();
+    let controls = [
+        (Control::Pause, true),
+        (Control::Undo, true),
+        (Control::Restart, true),
+        (Control::Ladder, level.ladder_unlocked),
+        (Control::Rope, level.rope_unlocked),
+        (Control::Rune, level.rewind_unlocked),
+        (Control::Potion, level.potion_unlocked),
+    ];
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                left: Val::Px(10.0),
+                top: Val::Px(250.0),
+                row_gap: Val::Px(8.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(DespawnOnTransition)
+        .insert(UiRoot)
+        .insert(Name::new("Control buttons UI"))
+        .with_children(|parent| {
+            for (control, _) in controls.into_iter().filter(|(_, shown)| *shown) {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            width: Val::Px(100.0),
+                            height: Val::Px(40.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        background_color: UI_YELLOW.into(),
+                        ..Default::default()
+                    })
+                    .insert(ControlButton(control))
+                    .with_children(|parent| {
+                        parent.spawn(localization.text(
+                            Localized::new(control.name_key()),
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+/// Runs before the input systems, which pick the presses up along with the keys
+pub fn press_control_buttons(
+    mut interaction_query: Query<
+        (&ControlButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut pointer_presses: ResMut<PointerPresses>,
+) {
+    for (button, interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                pointer_presses.0.push(button.0);
+            }
+            Interaction::Hovered => {
+                *color = UI_YELLOW_HOVER.into();
+            }
+            Interaction::None => {
+                *color = UI_YELLOW.into();
+            }
+        }
+    }
+}
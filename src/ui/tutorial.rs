@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{
+    audio::{AudioAssets, SoundChannel},
+    controls::{Control, ControlInput, Controls},
+    localization::{Localization, Localized},
+    states::{level::DespawnOnTransition, loading::FontAssets},
+    tutorial::{Tutorial, TutorialProgress},
+};
+
+use super::{
+    constants::{SKY_BLUE, UI_YELLOW, UI_YELLOW_HOVER},
+    results::LevelResults,
+    UiRoot,
+};
+
+#[derive(Component)]
+pub struct TutorialUiRoot;
+
+#[derive(Component)]
+pub struct TutorialText;
+
+#[derive(Component)]
+pub struct DismissButton;
+
+pub fn setup_tutorial_ui(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    controls: Res<Controls>,
+    localization: Res<Localization>,
+) {
+    let text_style = |font_size: f32| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        })
+        .insert(DespawnOnTransition)
+        .insert(TutorialUiRoot)
+        .insert(UiRoot)
+        .insert(Name::new("Tutorial UI"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(500.0),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        row_gap: Val::Px(8.0),
+                        ..Default::default()
+                    },
+                    background_color: SKY_BLUE.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section("", text_style(22.0)))
+                        .insert(TutorialText);
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(20.0), Val::Px(6.0)),
+                                ..Default::default()
+                            },
+                            background_color: UI_YELLOW.into(),
+                            ..Default::default()
+                        })
+                        .insert(DismissButton)
+                        .with_children(|parent| {
+                            parent.spawn(localization.text(
+                                Localized::with_args(
+                                    "tutorial.dismiss",
+                                    &[&controls.label(Control::Continue)],
+                                ),
+                                text_style(22.0),
+                            ));
+                        });
+                });
+        });
+}
+
+/// Show the prompt at the front of the queue, if there is one
+pub fn show_tutorial_prompt(
+    mut root: Query<&mut Visibility, With<TutorialUiRoot>>,
+    mut text: Query<&mut Text, With<TutorialText>>,
+    tutorial: Option<Res<Tutorial>>,
+    localization: Res<Localization>,
+) {
+    let (Ok(mut visibility), Ok(mut text)) = (root.get_single_mut(), text.get_single_mut()) else {
+        return;
+    };
+    match tutorial.as_ref().and_then(|tutorial| tutorial.current()) {
+        Some(prompt) => {
+            *visibility = Visibility::Visible;
+            text.sections[0].value = localization.get(&prompt.text);
+        }
+        None => {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+/// The button or the continue key puts the prompt away for good
+pub fn dismiss_tutorial_prompt(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<DismissButton>),
+    >,
+    input: ControlInput,
+    results: Option<Res<LevelResults>>,
+    mut tutorial: ResMut<Tutorial>,
+    mut progress: ResMut<TutorialProgress>,
+    sound_channel: Res<AudioChannel<SoundChannel>>,
+    audio_assets: Res<AudioAssets>,
+) {
+    // the key goes on from the results instead while they're up
+    let mut pressed = results.is_none() && input.just_pressed(Control::Continue);
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => pressed = true,
+            Interaction::Hovered => {
+                *color = UI_YELLOW_HOVER.into();
+            }
+            Interaction::None => {
+                *color = UI_YELLOW.into();
+            }
+        }
+    }
+    if !pressed || tutorial.current().is_none() {
+        return;
+    }
+    tutorial.dismiss(&mut progress.data.seen);
+    if let Err(e) = progress.save() {
+        warn!("Couldn't save the tutorial progress: {}", e);
+    }
+    sound_channel.play(audio_assets.pop.clone());
+}
@@ -1,268 +1,245 @@
-use std::collections::hash_map::Entry;
-
 use bevy::prelude::*;
 
 use crate::{
-    cave::{spawn_gem, HasGem},
-    equipment::{
-        ladder::{place_horizontal_ladder, place_vertical_ladder},
-        rewind::{spawn_rune, RewindRune},
-        Inventory,
-    },
-    level_manager::LevelManager,
-    player::{Player, PlayerHistoryEvent},
-    scale::{spawn_scale, ScaleCounter},
-    states::loading::{ModelAssets, TextureAssets},
-    util::{Alignment, CardinalDirection},
+    controls::{Control, ControlInput},
+    rules::{Action, Puzzle},
+    ui::equipment::{InfoUiRoot, PickingUiRoot},
 };
 
-pub fn handle_undo_player_move(
-    mut undo_event_reader: EventReader<PlayerHistoryEvent>,
-    mut player: Query<&mut Player>,
-    mut rewind_runes: Query<(Entity, &mut RewindRune)>,
+/// Step the puzzle back on Ctrl+Z, or whatever undo is bound to. The entities catch up through
+/// the sync systems, so nothing here needs to know what the undone action was.
+pub fn handle_undo_input(
+    input: ControlInput,
+    mut puzzle: ResMut<Puzzle>,
+    mut picking_ui: Query<&mut Visibility, With<PickingUiRoot>>,
+    mut info_ui: Query<&mut Visibility, (With<InfoUiRoot>, Without<PickingUiRoot>)>,
 ) {
-    for event in undo_event_reader.iter() {
-        match event {
-            PlayerHistoryEvent::PlayerMove(old_player)
-            | PlayerHistoryEvent::PlayerMoveToScale(old_player)
-            | PlayerHistoryEvent::PlayerMoveToGem(old_player) => {
-                let mut player = player
-                    .get_single_mut()
-                    .expect("There should only be one player");
-                *player = old_player.clone();
-
-                // undo rune countdowns
-                for (_, mut rune) in rewind_runes.iter_mut() {
-                    rune.countdown += 1;
-                }
-            }
-            _ => {}
-        }
+    if input.just_pressed(Control::Undo) && puzzle.apply(Action::Undo).is_err() {
+        // nothing left to undo -> back to picking equipment
+        *picking_ui.get_single_mut().unwrap() = Visibility::Visible;
+        *info_ui.get_single_mut().unwrap() = Visibility::Hidden;
     }
 }
 
-pub fn handle_undo_collect_scale(
-    mut commands: Commands,
-    mut undo_event_reader: EventReader<PlayerHistoryEvent>,
-    level_manager: Res<LevelManager>,
-    mut scale_counter: ResMut<ScaleCounter>,
-    model_assets: Res<ModelAssets>,
+/// Take the last undone action again on Ctrl+Y or Ctrl+Shift+Z by default
+pub fn handle_redo_input(
+    input: ControlInput,
+    mut puzzle: ResMut<Puzzle>,
+    mut picking_ui: Query<&mut Visibility, With<PickingUiRoot>>,
+    mut info_ui: Query<&mut Visibility, (With<InfoUiRoot>, Without<PickingUiRoot>)>,
 ) {
-    for event in undo_event_reader.iter() {
-        if let PlayerHistoryEvent::PlayerMoveToScale(_) = event {
-            // Spawn scale
-            let map = &level_manager.get_current_level().map;
-            if let Some((scale_x, scale_y)) = map.scale_pos {
-                spawn_scale(
-                    &mut commands,
-                    scale_x,
-                    scale_y,
-                    map.grid_heights[scale_y as usize][scale_x as usize],
-                    model_assets.scale.clone(),
-                );
-            }
-            // decrement scale counter
-            if scale_counter.0 > 0 {
-                scale_counter.0 -= 1;
-            } else {
-                warn!("Un-did scale pickup, but the scale counter was zero!");
-            }
-        }
+    if input.just_pressed(Control::Redo) && puzzle.apply(Action::Redo).is_ok() {
+        // undoing may have gone all the way back to picking equipment
+        *picking_ui.get_single_mut().unwrap() = Visibility::Hidden;
+        *info_ui.get_single_mut().unwrap() = Visibility::Visible;
     }
 }
 
-pub fn handle_undo_collect_gem(
-    mut commands: Commands,
-    mut undo_event_reader: EventReader<PlayerHistoryEvent>,
-    level_manager: Res<LevelManager>,
-    mut has_gem: ResMut<HasGem>,
-    model_assets: Res<ModelAssets>,
-) {
-    for event in undo_event_reader.iter() {
-        if let PlayerHistoryEvent::PlayerMoveToGem(_) = event {
-            // Spawn gem
-            let map = &level_manager.get_current_level().map;
-            if let Some(cave_data) = &map.cave_data {
-                if let Some((gem_x, gem_y)) = cave_data.gem_pos {
-                    spawn_gem(
-                        &mut commands,
-                        gem_x,
-                        gem_y,
-                        map.grid_heights[gem_y as usize][gem_x as usize] as f32 + 0.3,
-                        model_assets.gem.clone(),
-                    );
-                }
-            }
-            // no longer have gem
-            has_gem.0 = false;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cave::{Cave, Gem, GemCave, HeldGems},
+        equipment::{ladder::Ladder, EquipmentEntities},
+        rules::{self, CaveData, CaveLock, GemColour, Inventory, Map},
+        testing::{connect_gamepad, level_app, tap, tap_button},
+    };
+
+    fn picking_visibility(app: &mut App) -> Visibility {
+        *app.world
+            .query_filtered::<&Visibility, With<PickingUiRoot>>()
+            .single(&app.world)
     }
-}
 
-pub fn handle_undo_place_item(
-    mut commands: Commands,
-    mut undo_event_reader: EventReader<PlayerHistoryEvent>,
-    mut level_manager: ResMut<LevelManager>,
-    mut inventory: ResMut<Inventory>,
-    rewind_runes: Query<(Entity, &RewindRune)>,
-) {
-    for event in undo_event_reader.iter() {
-        match event {
-            PlayerHistoryEvent::PlaceVerticalLadder(key) => {
-                if let Some(entity) = level_manager
-                    .get_current_map_mut()
-                    .vertical_ladders
-                    .remove(key)
-                {
-                    commands.entity(entity).despawn_recursive();
-                    inventory.ladder_count += 1;
-                } else {
-                    warn!("Tried to undo vertical ladder placement, but it didn't exist!");
-                }
-            }
-            PlayerHistoryEvent::PlaceHorizontalLadder(key) => {
-                if let Some(entity) = level_manager
-                    .get_current_map_mut()
-                    .horizontal_ladders
-                    .remove(key)
-                {
-                    commands.entity(entity).despawn_recursive();
-                    inventory.ladder_count += 1;
-                } else {
-                    warn!("Tried to undo horizontal ladder placement, but it didn't exist!");
-                }
-            }
-            PlayerHistoryEvent::PlaceRope(key) => {
-                if let Some(entity) = level_manager.get_current_map_mut().ropes.remove(key) {
-                    commands.entity(entity).despawn_recursive();
-                    inventory.rope_count += 1;
-                } else {
-                    warn!("Tried to undo rope placement, but it didn't exist!");
-                }
-            }
-            PlayerHistoryEvent::PlaceRune => {
-                // find the most recently placed rune and delete it
-                let mut most_recent = None;
-                let mut most_recent_timestamp = 0.0;
-                for (entity, rune) in rewind_runes.iter() {
-                    if rune.timestamp > most_recent_timestamp {
-                        most_recent = Some(entity);
-                        most_recent_timestamp = rune.timestamp;
-                    }
-                }
-                if let Some(entity) = most_recent {
-                    commands.entity(entity).despawn_recursive();
-                    inventory.rewind_count += 1;
-                } else {
-                    warn!("Tried to undo rune placement, but no runes exist!");
-                }
-            }
-            _ => (),
+    fn climber_x(app: &mut App) -> f32 {
+        app.world
+            .query_filtered::<&Transform, With<rules::Player>>()
+            .single(&app.world)
+            .translation
+            .x
+    }
+
+    fn count<T: Component>(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<T>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    fn set_loadout(app: &mut App, inventory: Inventory) {
+        app.world
+            .resource_mut::<Puzzle>()
+            .set_loadout(inventory)
+            .unwrap();
+    }
+
+    #[test]
+    fn ctrl_z_undoes_the_last_move() {
+        let mut app = level_app(Map::from_heights(&[&[1, 1, 1]], &[]), 5);
+        // the first press only turns the climber to face east
+        for _ in 0..3 {
+            tap(&mut app, &[KeyCode::D]);
         }
+        assert_eq!(climber_x(&mut app), 2.0);
+
+        tap(&mut app, &[KeyCode::Z]);
+        assert_eq!(climber_x(&mut app), 2.0);
+
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Z]);
+        assert_eq!(climber_x(&mut app), 1.0);
+        assert_eq!(app.world.resource::<Puzzle>().state.player.grid_pos_x, 1);
+        assert_eq!(picking_visibility(&mut app), Visibility::Hidden);
     }
-}
 
-pub fn handle_undo_pickup_ladder(
-    mut commands: Commands,
-    mut undo_event_reader: EventReader<PlayerHistoryEvent>,
-    player: Query<&Player>,
-    mut inventory: ResMut<Inventory>,
-    mut level_manager: ResMut<LevelManager>,
-    model_assets: Res<ModelAssets>,
-) {
-    for event in undo_event_reader.iter() {
-        match event {
-            PlayerHistoryEvent::PickUpVerticalLadder(key) => {
-                match level_manager
-                    .get_current_map_mut()
-                    .vertical_ladders
-                    .entry(key.clone())
-                {
-                    Entry::Occupied(_) => {
-                        warn!("Tried to undo ladder pickup, but a ladder was already there!")
-                    }
-                    Entry::Vacant(v) => {
-                        let key = v.key();
-                        let player = player
-                            .get_single()
-                            .expect("There should only be one player");
-                        place_vertical_ladder(
-                            &mut commands,
-                            model_assets.ladder.clone(),
-                            key.direction,
-                            player.grid_pos_x as f32,
-                            player.grid_pos_y as f32,
-                            key.height as f32,
-                            v,
-                        );
-                        inventory.ladder_count -= 1;
-                    }
-                }
-            }
-            PlayerHistoryEvent::PickUpHorizontalLadder(key) => {
-                match level_manager
-                    .get_current_map_mut()
-                    .horizontal_ladders
-                    .entry(key.clone())
-                {
-                    Entry::Occupied(_) => {
-                        warn!("Tried to undo ladder pickup, but a ladder was already there!")
-                    }
-                    Entry::Vacant(v) => {
-                        let key = v.key();
-                        let player = player
-                            .get_single()
-                            .expect("There should only be one player");
-                        let direction = match key.alignment {
-                            Alignment::Xaxis => CardinalDirection::East,
-                            Alignment::Yaxis => CardinalDirection::North,
-                        };
-                        place_horizontal_ladder(
-                            &mut commands,
-                            model_assets.ladder.clone(),
-                            direction,
-                            player.grid_pos_x as f32,
-                            player.grid_pos_y as f32,
-                            key.height as f32,
-                            v,
-                        );
-                        inventory.ladder_count -= 1;
-                    }
-                }
-            }
-            _ => {}
+    #[test]
+    fn undoing_past_the_first_move_goes_back_to_picking() {
+        let mut app = level_app(Map::from_heights(&[&[1, 1, 1]], &[]), 5);
+        tap(&mut app, &[KeyCode::D]);
+        tap(&mut app, &[KeyCode::D]);
+        assert_eq!(picking_visibility(&mut app), Visibility::Hidden);
+
+        tap(&mut app, &[KeyCode::ControlRight, KeyCode::Z]);
+        assert_eq!(climber_x(&mut app), 0.0);
+        assert_eq!(picking_visibility(&mut app), Visibility::Hidden);
+
+        tap(&mut app, &[KeyCode::ControlRight, KeyCode::Z]);
+        assert_eq!(picking_visibility(&mut app), Visibility::Visible);
+        let info_visibility = *app
+            .world
+            .query_filtered::<&Visibility, With<InfoUiRoot>>()
+            .single(&app.world);
+        assert_eq!(info_visibility, Visibility::Hidden);
+    }
+
+    #[test]
+    fn ctrl_y_and_ctrl_shift_z_redo() {
+        let mut app = level_app(Map::from_heights(&[&[1, 1, 1]], &[]), 5);
+        for _ in 0..3 {
+            tap(&mut app, &[KeyCode::D]);
         }
+        for _ in 0..3 {
+            tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Z]);
+        }
+        assert_eq!(climber_x(&mut app), 0.0);
+        assert_eq!(picking_visibility(&mut app), Visibility::Visible);
+
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Y]);
+        assert_eq!(picking_visibility(&mut app), Visibility::Hidden);
+        assert_eq!(climber_x(&mut app), 1.0);
+        tap(
+            &mut app,
+            &[KeyCode::ControlLeft, KeyCode::ShiftLeft, KeyCode::Z],
+        );
+        assert_eq!(climber_x(&mut app), 2.0);
     }
-}
 
-pub fn handle_undo_teleport(
-    mut commands: Commands,
-    mut undo_event_reader: EventReader<PlayerHistoryEvent>,
-    player: Query<&Player>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    level_manager: Res<LevelManager>,
-    texture_assets: Res<TextureAssets>,
-) {
-    for event in undo_event_reader.iter() {
-        if let PlayerHistoryEvent::Teleport((x, y, timestamp)) = event {
-            let player = player
-                .get_single()
-                .expect("There should only be one player");
-            let player_height =
-                level_manager.get_current_level().map.grid_heights[*y as usize][*x as usize];
-            spawn_rune(
-                &mut commands,
-                &texture_assets,
-                *x,
-                *y,
-                player_height as f32 + 0.01,
-                &mut meshes,
-                &mut materials,
-                player.stamina,
-                *timestamp,
-                1,
-            );
+    #[test]
+    fn undo_and_redo_take_down_and_put_back_a_ladder() {
+        let mut map = Map::from_heights(&[&[3, 3], &[1, 1]], &[]);
+        map.player_start_pos = (0, 1);
+        let mut app = level_app(map, 10);
+        set_loadout(
+            &mut app,
+            Inventory {
+                ladder_count: 1,
+                ..Default::default()
+            },
+        );
+        tap(&mut app, &[KeyCode::W]);
+        tap(&mut app, &[KeyCode::Key1]);
+        assert_eq!(count::<Ladder>(&mut app), 1);
+
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Z]);
+        assert_eq!(count::<Ladder>(&mut app), 0);
+        assert!(app
+            .world
+            .resource::<EquipmentEntities>()
+            .vertical_ladders
+            .is_empty());
+
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Y]);
+        assert_eq!(count::<Ladder>(&mut app), 1);
+    }
+
+    #[test]
+    fn undo_and_redo_take_down_and_put_back_a_rope() {
+        let mut app = level_app(Map::from_heights(&[&[3, 1]], &[]), 10);
+        set_loadout(
+            &mut app,
+            Inventory {
+                rope_count: 1,
+                ..Default::default()
+            },
+        );
+        tap(&mut app, &[KeyCode::D]);
+        tap(&mut app, &[KeyCode::Key2]);
+        let rope = *app
+            .world
+            .resource::<EquipmentEntities>()
+            .ropes
+            .values()
+            .next()
+            .unwrap();
+        assert_eq!(app.world.get::<Name>(rope).unwrap().as_str(), "Rope");
+
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Z]);
+        assert!(app.world.get_entity(rope).is_none());
+        assert!(app.world.resource::<EquipmentEntities>().ropes.is_empty());
+
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Y]);
+        assert_eq!(app.world.resource::<EquipmentEntities>().ropes.len(), 1);
+    }
+
+    #[test]
+    fn undo_and_redo_swap_the_gem_and_the_caves() {
+        let mut map = Map::from_heights(&[&[1, 1, 1], &[1, 1, 1], &[1, 1, 1]], &[]);
+        map.caves = vec![CaveData {
+            first_pos: (0, 1),
+            second_pos: (2, 1),
+            entrance: rules::CardinalDirection::North,
+            lock: CaveLock::AnyGem,
+        }];
+        map.gems = vec![rules::Gem {
+            pos: (1, 0),
+            colour: GemColour::Green,
+        }];
+        let mut app = level_app(map, 20);
+        let sealed = |app: &mut App| {
+            let mut open = app.world.query_filtered::<&Visibility, With<Cave>>();
+            let open = open.iter(&app.world).all(|v| *v == Visibility::Hidden);
+            let mut sealed = app.world.query_filtered::<&Visibility, With<GemCave>>();
+            let sealed = sealed.iter(&app.world).all(|v| *v == Visibility::Visible);
+            assert_eq!(open, sealed);
+            sealed
+        };
+        assert_eq!(count::<Gem>(&mut app), 1);
+        assert!(sealed(&mut app));
+
+        tap(&mut app, &[KeyCode::D]);
+        tap(&mut app, &[KeyCode::D]);
+        assert_eq!(count::<Gem>(&mut app), 0);
+        assert!(!sealed(&mut app));
+
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Z]);
+        assert_eq!(count::<Gem>(&mut app), 1);
+        assert!(app.world.resource::<HeldGems>().0.is_empty());
+        assert!(sealed(&mut app));
+
+        tap(&mut app, &[KeyCode::ControlLeft, KeyCode::Y]);
+        assert_eq!(count::<Gem>(&mut app), 0);
+        assert!(!sealed(&mut app));
+    }
+
+    #[test]
+    fn gamepad_buttons_undo_and_redo() {
+        let mut app = level_app(Map::from_heights(&[&[1, 1, 1]], &[]), 5);
+        for _ in 0..3 {
+            tap(&mut app, &[KeyCode::D]);
         }
+        let pad = connect_gamepad(&mut app);
+        tap_button(&mut app, pad, GamepadButtonType::West);
+        assert_eq!(climber_x(&mut app), 1.0);
+        tap_button(&mut app, pad, GamepadButtonType::North);
+        assert_eq!(climber_x(&mut app), 2.0);
     }
 }
@@ -1,25 +1,6 @@
-use bevy::{
-    prelude::{Component, Vec3},
-    reflect::Reflect,
-};
+use bevy::prelude::{Component, Vec3};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
-pub enum CardinalDirection {
-    North,
-    East,
-    South,
-    West,
-}
-impl CardinalDirection {
-    pub fn reverse(&self) -> Self {
-        match self {
-            CardinalDirection::North => CardinalDirection::South,
-            CardinalDirection::East => CardinalDirection::West,
-            CardinalDirection::South => CardinalDirection::North,
-            CardinalDirection::West => CardinalDirection::East,
-        }
-    }
-}
+use crate::rules::CardinalDirection;
 
 impl From<CardinalDirection> for Vec3 {
     fn from(value: CardinalDirection) -> Self {
@@ -32,23 +13,6 @@ impl From<CardinalDirection> for Vec3 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
-pub enum Alignment {
-    Xaxis,
-    Yaxis,
-}
-
-impl From<CardinalDirection> for Alignment {
-    fn from(value: CardinalDirection) -> Self {
-        match value {
-            CardinalDirection::North => Alignment::Yaxis,
-            CardinalDirection::East => Alignment::Xaxis,
-            CardinalDirection::South => Alignment::Yaxis,
-            CardinalDirection::West => Alignment::Xaxis,
-        }
-    }
-}
-
 pub fn cubic_ease_in_out(t: f32) -> f32 {
     let t = t.clamp(0.0, 1.0);
     let t = t * 2.0;